@@ -1,5 +1,5 @@
 use clap::{Parser, Subcommand};
-use crate::commands::{send::SendCommand, listen::ListenCommand, bridge::BridgeCommand};
+use crate::commands::{send::SendCommand, listen::ListenCommand, bridge::BridgeCommand, import_curl::ImportCurlCommand, run::RunCommand, test::TestCommand, bench::BenchCommand};
 use clap_complete::Shell;
 
 #[derive(Parser)]
@@ -13,11 +13,19 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Send a network request")]
-    Send(SendCommand),
+    Send(Box<SendCommand>),
     #[command(about = "Listen for incoming traffic")]
     Listen(ListenCommand),
     #[command(about = "Create a tunnel to a local port")]
     Bridge(BridgeCommand),
+    #[command(about = "Run the requests in a .http file")]
+    Run(RunCommand),
+    #[command(about = "Run a YAML test scenario and check its assertions")]
+    Test(TestCommand),
+    #[command(about = "Load test an HTTP endpoint and report latency percentiles")]
+    Bench(BenchCommand),
+    #[command(about = "Run or print a curl command as a varyag request")]
+    ImportCurl(ImportCurlCommand),
     #[command(about = "Generate shell completions")]
     GenerateCompletion {
         #[arg(value_enum)]
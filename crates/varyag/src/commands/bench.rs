@@ -0,0 +1,221 @@
+use clap::Args;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use vyg_core::bench::{self, BenchLimit, BenchOptions, BenchReport};
+use vyg_core::compression::ResponseEncoding;
+use vyg_core::http_client::{BodyMode, HttpRequest};
+use vyg_core::request_items;
+use vyg_core::tls::TlsOptions;
+use vyg_core::transport::TransportOptions;
+use vyg_display::{logger, progress::CountProgress, table};
+use crate::commands::send::{parse_seconds, resolve_auth, AuthType};
+
+const DEFAULT_REQUESTS: u64 = 200;
+const PERCENTILES: [f64; 4] = [50.0, 90.0, 99.0, 99.9];
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct BenchCommand {
+    /// The target URL. If no scheme is provided, `http://` is assumed.
+    #[arg()]
+    pub url: String,
+
+    /// Optional: The HTTP method. Defaults to POST with body items or --data-file, GET otherwise.
+    #[arg(value_parser = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"])]
+    pub method: Option<String>,
+
+    /// Request items, as for `varyag send`: body fields (`name=value`, `field:=json_value`),
+    /// query parameters (`name==value`) and headers (`Header:value`).
+    #[arg(num_args = 0..)]
+    pub body: Vec<String>,
+
+    /// Custom headers (e.g., `-H "X-API-Key: secret"`).
+    #[arg(short = 'H', long = "header")]
+    pub headers: Vec<String>,
+
+    /// Send the request body from a file.
+    #[arg(long, value_name = "FILE_PATH")]
+    pub data_file: Option<PathBuf>,
+
+    /// Serialize body items as a URL-encoded form.
+    #[arg(short, long, conflicts_with = "data_file")]
+    pub form: bool,
+
+    /// Credentials as `USER:PASSWORD`, or a token for `--auth-type bearer`.
+    #[arg(short, long, value_name = "USER:PASSWORD")]
+    pub auth: Option<String>,
+
+    /// The authentication scheme used with --auth (Digest is not supported).
+    #[arg(short = 'A', long, value_enum, default_value_t = AuthType::Basic, requires = "auth")]
+    pub auth_type: AuthType,
+
+    /// The number of requests in flight at once, sharing a pool of keep-alive connections.
+    #[arg(short, long, value_name = "N", default_value_t = 50, value_parser = clap::value_parser!(u64).range(1..))]
+    pub connections: u64,
+
+    /// Stop after this many requests (the default is 200).
+    #[arg(short = 'n', long, value_name = "N", group = "limit", value_parser = clap::value_parser!(u64).range(1..))]
+    pub requests: Option<u64>,
+
+    /// Run for this long instead, e.g. `30s`, `500ms` or `2m`.
+    #[arg(short, long, value_name = "DURATION", group = "limit", value_parser = parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// Start requests at a constant rate per second, whether or not earlier ones have finished
+    /// (an open model). Latency then includes the time a request waited for a free connection.
+    #[arg(short, long, value_name = "PER_SECOND", value_parser = parse_rate)]
+    pub rate: Option<f64>,
+
+    /// Allow insecure server connections when using SSL.
+    #[arg(short = 'k', long)]
+    pub insecure: bool,
+
+    /// Count a request as failed after this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+
+    /// Ignore proxy environment variables.
+    #[arg(long)]
+    pub noproxy: bool,
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(format!("'{}' is not a duration like 30s, 500ms, 2m or 1h", value)),
+    };
+    match number.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(Duration::from_secs_f64(number * scale)),
+        _ => Err(format!("'{}' is not a duration like 30s, 500ms, 2m or 1h", value)),
+    }
+}
+
+fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(format!("'{}' is not a positive number of requests per second", value)),
+    }
+}
+
+pub async fn handle_bench(command: BenchCommand) -> ExitCode {
+    let url = if command.url.contains("://") { command.url.clone() } else { format!("http://{}", command.url) };
+    let auth = match command.auth.as_deref().map(|credentials| resolve_auth(credentials, command.auth_type)) {
+        Some(Ok(auth)) => Some(auth),
+        Some(Err(e)) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    let has_body_items = command.body.iter().any(|item| request_items::is_body_item(item));
+    let method = command.method.unwrap_or_else(|| {
+        if command.data_file.is_some() || has_body_items { "POST" } else { "GET" }.to_string()
+    });
+    let request = HttpRequest {
+        url: url.clone(),
+        method,
+        headers: command.headers,
+        body: command.body,
+        data_file: command.data_file,
+        noproxy: command.noproxy,
+        body_mode: if command.form { BodyMode::Form } else { BodyMode::Json },
+        auth,
+        transport: TransportOptions {
+            timeout: command.timeout,
+            tls: TlsOptions { insecure: command.insecure, ..Default::default() },
+            ..Default::default()
+        },
+        // Responses are counted, not read, so there is no point in asking for compression.
+        response_encoding: ResponseEncoding::AsIs,
+        ..Default::default()
+    };
+
+    let limit = match command.duration {
+        Some(duration) => BenchLimit::Duration(duration),
+        None => BenchLimit::Requests(command.requests.unwrap_or(DEFAULT_REQUESTS)),
+    };
+    let options = BenchOptions { connections: command.connections as usize, limit, rate: command.rate };
+    if io::stdout().is_terminal() {
+        let until = match limit {
+            BenchLimit::Requests(requests) => format!("{} requests", requests),
+            BenchLimit::Duration(duration) => format!("{:.1}s", duration.as_secs_f64()),
+        };
+        let rate = command.rate.map(|rate| format!(" at {} requests/s", rate)).unwrap_or_default();
+        logger::info(&format!("Benchmarking {} with {} connections for {}{}", url, command.connections, until, rate));
+    }
+
+    let total = match limit {
+        BenchLimit::Requests(requests) => Some(requests),
+        BenchLimit::Duration(_) => None,
+    };
+    let progress = Arc::new(CountProgress::new(total, "requests"));
+    let counter = progress.clone();
+    let report = bench::run(request, options, move || counter.inc()).await;
+    progress.finish();
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            logger::error(&format!("{:#}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = print_report(&report) {
+        logger::error(&format!("Failed to print the report: {}", e));
+        return ExitCode::FAILURE;
+    }
+    if report.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_report(report: &BenchReport) -> anyhow::Result<()> {
+    table::print_key_value_table(&[
+        ("Requests".to_string(), report.requests().to_string()),
+        ("Duration".to_string(), format!("{:.2}s", report.elapsed.as_secs_f64())),
+        ("Requests/s".to_string(), format!("{:.1}", report.requests_per_second())),
+        ("Received".to_string(), format!("{} bytes", report.bytes_received)),
+    ])?;
+
+    if report.latency.count() > 0 {
+        let latency = &report.latency;
+        let mut titles = vec!["Min".to_string(), "Mean".to_string()];
+        titles.extend(PERCENTILES.iter().map(|percentile| format!("p{}", percentile)));
+        titles.push("Max".to_string());
+        let mut row = vec![milliseconds(latency.min()), milliseconds(latency.mean())];
+        row.extend(PERCENTILES.iter().map(|&percentile| milliseconds(latency.percentile(percentile))));
+        row.push(milliseconds(latency.max()));
+        table::print_table(&titles.iter().map(String::as_str).collect::<Vec<_>>(), vec![row])?;
+    }
+
+    if !report.statuses.is_empty() {
+        let requests = report.requests() as f64;
+        let statuses = report
+            .statuses
+            .iter()
+            .map(|(status, &count)| vec![status.to_string(), count.to_string(), format!("{:.1}%", count as f64 * 100.0 / requests)])
+            .collect();
+        table::print_table(&["Status", "Count", "Share"], statuses)?;
+    }
+
+    if !report.errors.is_empty() {
+        let mut errors: Vec<_> = report.errors.iter().collect();
+        errors.sort_by(|a, b| b.1.cmp(a.1));
+        table::print_table(&["Error", "Count"], errors.into_iter().map(|(error, count)| vec![error.clone(), count.to_string()]).collect())?;
+    }
+    Ok(())
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
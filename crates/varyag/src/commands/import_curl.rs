@@ -0,0 +1,43 @@
+use clap::{Args, Parser};
+use std::process::ExitCode;
+use vyg_core::{curl_import, export};
+use vyg_display::logger;
+use crate::cli::{Cli, Commands};
+use crate::commands::send::handle_send;
+
+#[derive(Args, Debug)]
+pub struct ImportCurlCommand {
+    /// The curl command line in one argument, e.g. `'curl -X POST https://api.example.com -d name=varyag'`.
+    #[arg()]
+    pub command: String,
+
+    /// Print the equivalent `varyag send` command instead of running it.
+    #[arg(long)]
+    pub print: bool,
+}
+
+pub async fn handle_import_curl(command: ImportCurlCommand) -> ExitCode {
+    let args = match curl_import::send_args(&command.command) {
+        Ok(args) => args,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if command.print {
+        let args: Vec<_> = args.iter().map(|arg| export::quote(arg)).collect();
+        println!("varyag send {}", args.join(" "));
+        return ExitCode::SUCCESS;
+    }
+
+    match Cli::try_parse_from(["varyag".to_string(), "send".to_string()].into_iter().chain(args)) {
+        Ok(Cli { command: Commands::Send(send) }) => handle_send(*send).await,
+        Ok(_) => unreachable!("the arguments start with the send subcommand"),
+        Err(e) => {
+            logger::error("The curl command does not translate to a valid varyag request:");
+            let _ = e.print();
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,3 +1,7 @@
 pub mod send;
 pub mod listen;
+pub mod bench;
 pub mod bridge;
+pub mod import_curl;
+pub mod run;
+pub mod test;
@@ -0,0 +1,129 @@
+use clap::Args;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use vyg_core::compression::ResponseEncoding;
+use vyg_core::cookie_jar::{CookieJar, SharedCookieJar};
+use vyg_core::http_client::{self, HttpRequest};
+use vyg_core::http_file::{HttpFile, Variables};
+use vyg_core::redirect::RedirectOptions;
+use vyg_core::tls::TlsOptions;
+use vyg_core::transport::TransportOptions;
+use vyg_display::logger;
+use crate::commands::send::{parse_seconds, Output};
+
+#[derive(Args, Debug)]
+pub struct RunCommand {
+    /// The request file, in the `.http` format of IDE REST clients: requests separated by `###`,
+    /// `@name = value` variables and `{{name}}` or `{{request.response.body.$.path}}` references.
+    #[arg()]
+    pub file: PathBuf,
+
+    /// Run only the request named NAME (`# @name NAME`), after the named requests it takes values from.
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Set a variable, overriding its `@NAME = ...` definition in the file.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+
+    /// Allow insecure server connections when using SSL.
+    #[arg(short = 'k', long)]
+    pub insecure: bool,
+
+    /// Give up on each request after this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+
+    /// Return redirect responses instead of following them.
+    #[arg(long)]
+    pub no_follow: bool,
+
+    /// Print each request and response as raw HTTP.
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+pub async fn handle_run(command: RunCommand) -> ExitCode {
+    let file = match HttpFile::load(&command.file) {
+        Ok(file) => file,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+    let plan = match file.plan(command.name.as_deref()) {
+        Ok(plan) => plan,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+    if plan.is_empty() {
+        logger::warn(&format!("No requests in {}", command.file.display()));
+        return ExitCode::SUCCESS;
+    }
+
+    let pretty = io::stdout().is_terminal();
+    let mut variables = Variables::new(&file, command.variables.iter().cloned().collect::<HashMap<_, _>>());
+    // As in REST clients, cookies set by one response are sent with the following requests.
+    let template = HttpRequest {
+        cookie_jar: Some(Arc::new(SharedCookieJar::new(CookieJar::default()))),
+        transport: TransportOptions {
+            timeout: command.timeout,
+            tls: TlsOptions { insecure: command.insecure, ..Default::default() },
+            ..Default::default()
+        },
+        redirect: RedirectOptions { follow: !command.no_follow, ..Default::default() },
+        response_encoding: ResponseEncoding::Decode,
+        ..Default::default()
+    };
+
+    let mut output = Output::new(pretty, command.verbose);
+    for request in plan {
+        let failed = |e: anyhow::Error| {
+            logger::error(&format!("{} (line {}) failed: {:#}", request.title(), request.line, e));
+            ExitCode::FAILURE
+        };
+        let http_request = match request.to_http_request(&variables, &template) {
+            Ok(http_request) => http_request,
+            Err(e) => return failed(e),
+        };
+        if pretty {
+            logger::info(&format!("### {}", request.title()));
+        }
+        let response = match http_client::send_request(http_request).await {
+            Ok(response) => response,
+            Err(e) => return failed(e),
+        };
+
+        if command.verbose {
+            output.request_headers(&response.request, &response.version);
+            output.request_body(&response.request);
+        }
+        if pretty || command.verbose {
+            output.redirects(&response.redirects);
+            output.response_headers(&format!("{} {}", response.version, response.status), &http_client::header_pairs(&response.headers));
+        }
+        output.response_body(&response, false, false);
+        if pretty {
+            println!();
+        }
+
+        if let Some(name) = &request.name {
+            variables.record(name, &response);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub(crate) fn parse_variable(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .ok_or_else(|| format!("'{}' is not NAME=VALUE", value))
+}
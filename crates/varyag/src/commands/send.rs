@@ -1,24 +1,49 @@
-use clap::Args;
-use std::path::PathBuf;
+use clap::{Args, ValueEnum};
+use std::io::{self, IsTerminal, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
 use url::Url;
-use vyg_core::{http_client, net_client, ws_client};
-use vyg_display::{json::pretty_print_json, table::print_key_value_table, logger};
+use vyg_core::{auth::Auth, download, http_client, net_client, request_items, ws_client};
+use vyg_core::event_stream::{self, SseEvent, StreamItem, StreamOptions};
+use vyg_core::http_client::{HttpRequest, HttpResponse, HttpVersion, Opened, SentRequest, StreamingResponse};
+use vyg_core::proxy::Proxy;
+use vyg_core::redirect::{RedirectHop, RedirectOptions};
+use vyg_core::resolve::{ConnectTo, IpFamily, ResolveOptions, ResolveOverride};
+use vyg_core::compression::{Encoding, ResponseEncoding};
+use vyg_core::export::{self, ExportFormat};
+use vyg_core::filter::Filter;
+use vyg_core::graphql::{self, GraphqlRequest, GraphqlResponse};
+use vyg_core::cookie_jar::{CookieJar, SharedCookieJar};
+use vyg_core::retry::{RetryPolicy, DEFAULT_RETRY_STATUSES};
+use vyg_core::session::Session;
+use vyg_core::tls::{TlsDetails, TlsOptions, TlsVersion};
+use vyg_core::transport::TransportOptions;
+use vyg_core::write_out::{self, Transfer};
+use vyg_display::{http, json::pretty_print_json, table::{eprint_key_value_table, print_key_value_table}, logger};
+use vyg_display::waterfall::{self, Phase};
 
 
 #[derive(Args, Debug)]
 #[command(arg_required_else_help = true)]
 pub struct SendCommand {
-    /// The target URL or address (e.g., `https://api.example.com`, `example.com`, `ws://host:port`, `tcp://host:port`).
+    /// The target URL or address (e.g., `https://api.example.com`, `example.com`, `ws://host:port`, `tcp://host:port`,
+    /// `unix:///path/to.sock`).
     /// If no scheme is provided, `http://` is assumed.
     #[arg()]
     pub destination: String,
 
     /// Optional: The HTTP method (e.g., GET, POST).
-    /// If body items or --data-file are present, it defaults to POST. Otherwise, it defaults to GET.
+    /// If body fields or --data-file are present, it defaults to POST. Otherwise, it defaults to GET.
     #[arg(value_parser = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS"])]
     pub method: Option<String>,
 
-    /// Request body items for HTTP requests (e.g., `name=value` `field:=json_value`).
+    /// Request items for HTTP requests: body fields (`name=value`, `field:=json_value`, `file@./path.png`),
+    /// query parameters (`name==value`) and headers (`Header:value`).
     #[arg(num_args = 0..)]
     pub body: Vec<String>,
 
@@ -26,20 +51,457 @@ pub struct SendCommand {
     #[arg(short = 'H', long = "header")]
     pub headers: Vec<String>,
 
-    /// Send request body from a file.
+    /// Send request body from a file. The Content-Type is inferred from the extension or the content.
     #[arg(long, value_name = "FILE_PATH")]
     pub data_file: Option<PathBuf>,
 
+    /// Do not read a request body from stdin, even when it is piped.
+    #[arg(long)]
+    pub ignore_stdin: bool,
+
+    /// Print binary response bodies even when stdout is a terminal.
+    #[arg(long)]
+    pub binary: bool,
+
+    /// Stream the response body to a file, named after `Content-Disposition` or the URL.
+    #[arg(short, long)]
+    pub download: bool,
+
+    /// Save the response body to this file (implies --download).
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Resume a partial download of --output using a Range request.
+    #[arg(short = 'c', long = "continue", requires = "output")]
+    pub resume: bool,
+
+    /// Serialize body items as a URL-encoded form (multipart if file fields are present).
+    #[arg(short, long, conflicts_with_all = ["multipart", "data_file"])]
+    pub form: bool,
+
+    /// Serialize body items as multipart form data.
+    #[arg(long, conflicts_with = "data_file")]
+    pub multipart: bool,
+
+    /// Send a GraphQL operation: the query itself, or a `.graphql` file holding it. Body items
+    /// become its variables, and the result's `data` and `errors` are printed separately.
+    #[arg(long, value_name = "QUERY|FILE", conflicts_with_all = ["form", "multipart", "data_file"])]
+    pub graphql: Option<String>,
+
+    /// The operation to run when the GraphQL document defines several.
+    #[arg(long, value_name = "NAME", requires = "graphql")]
+    pub operation: Option<String>,
+
+    /// Fetch the GraphQL schema with an introspection query and print it as SDL.
+    #[arg(long, conflicts_with_all = ["graphql", "form", "multipart", "data_file"])]
+    pub introspect: bool,
+
+    /// Credentials as `USER[:PASSWORD]`, or a token for `--auth-type bearer`.
+    /// The password is prompted for on a terminal when omitted.
+    #[arg(short, long, value_name = "USER[:PASSWORD]")]
+    pub auth: Option<String>,
+
+    /// The authentication scheme used with --auth.
+    #[arg(short = 'A', long, value_enum, default_value_t = AuthType::Basic, requires = "auth")]
+    pub auth_type: AuthType,
+
+    /// Persist cookies, custom headers and credentials across requests in a named session
+    /// (stored in the config directory) or a session file path. Headers and credentials are
+    /// only sent back to the host and port they were used with.
+    #[arg(long, value_name = "NAME_OR_PATH")]
+    pub session: Option<String>,
+
+    /// Load cookies from a Netscape/curl `cookies.txt` file before sending.
+    #[arg(long, value_name = "FILE")]
+    pub import_cookies: Option<PathBuf>,
+
+    /// Write the cookie jar to a Netscape/curl `cookies.txt` file after the response.
+    #[arg(long, value_name = "FILE")]
+    pub export_cookies: Option<PathBuf>,
+
+    /// Speak HTTP/1.1 only. By default HTTP/2 is used when a TLS server offers it.
+    #[arg(long = "http1.1", group = "http_version")]
+    pub http1_1: bool,
+
+    /// Require HTTP/2, negotiated during the TLS handshake.
+    #[arg(long, group = "http_version")]
+    pub http2: bool,
+
+    /// Speak HTTP/2 without negotiation, also over cleartext `http://` (h2c).
+    #[arg(long, group = "http_version")]
+    pub http2_prior_knowledge: bool,
+
+    /// Follow HTTP redirects (the default).
+    #[arg(long, overrides_with = "no_follow")]
+    pub follow: bool,
+
+    /// Return redirect responses instead of following them.
+    #[arg(long)]
+    pub no_follow: bool,
+
+    /// Fail after following this many redirects.
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Resend the method and body when following 301, 302 and 303 redirects too.
+    /// 307 and 308 redirects always keep them.
+    #[arg(long)]
+    pub preserve_method: bool,
+
+    /// Maximum time in seconds for the whole HTTP exchange, or to wait for a reply over TCP, UDP and WebSocket.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+
+    /// Maximum time in seconds to establish a connection.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub connect_timeout: Option<Duration>,
+
+    /// Retry failed connections and retryable HTTP statuses up to N times with exponential backoff.
+    /// UDP datagrams are resent when no reply arrives within --timeout.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub retry: u32,
+
+    /// HTTP statuses that are retried with --retry. A `Retry-After` header is honored.
+    #[arg(long, value_name = "STATUS", value_delimiter = ',', default_values_t = DEFAULT_RETRY_STATUSES)]
+    pub retry_on: Vec<u16>,
+
+    /// Trust the CA certificates in this PEM bundle in addition to the system ones.
+    #[arg(long, value_name = "FILE")]
+    pub cacert: Option<PathBuf>,
+
+    /// Client certificate (PEM) for mutual TLS. May also contain the private key.
+    #[arg(long, value_name = "FILE")]
+    pub cert: Option<PathBuf>,
+
+    /// Private key (PKCS#8 PEM) for --cert.
+    #[arg(long, value_name = "FILE", requires = "cert")]
+    pub key: Option<PathBuf>,
+
+    /// Do not verify the server's TLS certificate or host name.
+    #[arg(short = 'k', long)]
+    pub insecure: bool,
+
+    /// Minimum TLS version to accept: 1.0, 1.1, 1.2 or 1.3.
+    #[arg(long, value_name = "VERSION", value_parser = parse_tls_version)]
+    pub tls_min_version: Option<TlsVersion>,
+
+    /// Only connect if the server's public key has this base64 SHA-256 hash (repeatable).
+    #[arg(long = "pin-sha256", value_name = "HASH")]
+    pub pins: Vec<String>,
+
+    /// Use these addresses for HOST:PORT instead of resolving it, e.g. `example.com:443:127.0.0.1` (repeatable).
+    #[arg(long, value_name = "HOST:PORT:ADDRESS[,ADDRESS...]", value_parser = parse_resolve)]
+    pub resolve: Vec<ResolveOverride>,
+
+    /// Connect to HOST2:PORT2 whenever HOST1:PORT1 is requested; the URL, Host header and
+    /// TLS server name stay unchanged. Empty fields match anything (repeatable).
+    #[arg(long, value_name = "HOST1:PORT1:HOST2:PORT2", value_parser = parse_connect_to)]
+    pub connect_to: Vec<ConnectTo>,
+
+    /// Make connections from this network interface (Linux) or, given an IP address, from that address.
+    #[arg(long, value_name = "NAME|ADDRESS")]
+    pub interface: Option<String>,
+
+    /// Make connections from this source address.
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "interface")]
+    pub local_address: Option<IpAddr>,
+
+    /// Only use IPv4 addresses.
+    #[arg(short = '4', long, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+
+    /// Only use IPv6 addresses.
+    #[arg(short = '6', long)]
+    pub ipv6: bool,
+
+    /// Print the request and response as raw HTTP, and the certificate the server presented
+    /// on the connection; for `wss` with rustls, also the negotiated TLS protocol and cipher.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Print the request that would be sent, without sending it.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Print an equivalent curl command, with everything varyag would add, instead of sending the request.
+    #[arg(long, group = "export")]
+    pub as_curl: bool,
+
+    /// Print an equivalent wget command instead of sending the request.
+    #[arg(long, group = "export")]
+    pub as_wget: bool,
+
+    /// Print an equivalent HTTPie command instead of sending the request.
+    #[arg(long, group = "export")]
+    pub as_httpie: bool,
+
+    /// What to print for HTTP: any of `H` (request headers), `B` (request body),
+    /// `h` (response headers) and `b` (response body).
+    /// Defaults to `hb` on a terminal and `b` when stdout is redirected.
+    #[arg(short, long, value_name = "WHAT", value_parser = parse_print)]
+    pub print: Option<PrintSelection>,
+
+    /// Print only the response body (same as `--print=b`).
+    #[arg(short, long, conflicts_with = "print")]
+    pub body_only: bool,
+
+    /// Print only what this expression selects from a JSON response body, each SSE event's
+    /// data or each WebSocket message: a jq-style path such as `.items[] | .id`, or a
+    /// JSONPath starting with `$`. Selects the response body for printing unless --print is given.
+    #[arg(long, value_name = "EXPR", value_parser = parse_filter)]
+    pub filter: Option<Filter>,
+
+    /// Print strings selected by --filter without quotes, for use in scripts.
+    #[arg(short = 'r', long, requires = "filter")]
+    pub raw_output: bool,
+
+    /// Reopen a Server-Sent Events stream whenever it ends, resuming with `Last-Event-ID`.
+    #[arg(long)]
+    pub reconnect: bool,
+
+    /// Exit with 3, 4 or 5 when the HTTP status is 3xx, 4xx or 5xx.
+    #[arg(long)]
+    pub check_status: bool,
+
+    /// Show how long DNS, connecting, TLS, the server and the transfer took, as a waterfall.
+    #[arg(long)]
+    pub timing: bool,
+
+    /// Print a curl-style template after the response, e.g. `'%{http_code} %{time_total}\n'`.
+    /// Use `@FILE` to read the template from a file.
+    #[arg(short = 'w', long, value_name = "TEMPLATE", value_parser = parse_write_out)]
+    pub write_out: Option<String>,
+
     /// Use interactive mode for TCP connections.
     #[arg(short, long)]
     pub interactive: bool,
 
+    /// Keep compressed response bodies as received instead of decoding them.
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Compress the request body with `gzip`, `deflate`, `br` or `zstd` and set `Content-Encoding`.
+    #[arg(long, value_name = "ENCODING", value_parser = parse_encoding)]
+    pub compress: Option<Encoding>,
+
     /// Disable proxy for this request.
     #[arg(long)]
     pub noproxy: bool,
+
+    /// Send HTTP requests and tunnel TCP and WebSocket connections through this proxy:
+    /// `http://`, `socks5://` or `socks5h://` (proxy resolves names), with optional `USER:PASSWORD@`.
+    #[arg(short = 'x', long, value_name = "URL", value_parser = parse_proxy, conflicts_with = "noproxy")]
+    pub proxy: Option<Proxy>,
+
+    /// Send HTTP requests over this Unix domain socket, e.g. `/var/run/docker.sock`.
+    /// URLs of the form `http+unix://%2Fvar%2Frun%2Fdocker.sock/PATH` do the same.
+    #[arg(long, value_name = "PATH", conflicts_with = "proxy")]
+    pub unix_socket: Option<PathBuf>,
+}
+
+/// The parts of an HTTP exchange selected with `--print`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrintSelection {
+    pub request_headers: bool,
+    pub request_body: bool,
+    pub response_headers: bool,
+    pub response_body: bool,
+}
+
+impl PrintSelection {
+    const BODY: PrintSelection = PrintSelection {
+        request_headers: false,
+        request_body: false,
+        response_headers: false,
+        response_body: true,
+    };
+    const RESPONSE: PrintSelection = PrintSelection { response_headers: true, ..PrintSelection::BODY };
+    const ALL: PrintSelection = PrintSelection {
+        request_headers: true,
+        request_body: true,
+        response_headers: true,
+        response_body: true,
+    };
+}
+
+impl FromStr for PrintSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        let mut selection = PrintSelection::default();
+        for c in value.chars() {
+            match c {
+                'H' => selection.request_headers = true,
+                'B' => selection.request_body = true,
+                'h' => selection.response_headers = true,
+                'b' => selection.response_body = true,
+                _ => anyhow::bail!(
+                    "Invalid --print value: '{}'. Use any of H (request headers), B (request body), h (response headers) and b (response body).",
+                    value
+                ),
+            }
+        }
+        Ok(selection)
+    }
 }
 
-pub async fn handle_send(command: SendCommand) {
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthType {
+    Basic,
+    Bearer,
+    Digest,
+}
+
+impl SendCommand {
+    fn http_version(&self) -> HttpVersion {
+        if self.http1_1 {
+            HttpVersion::Http1_1
+        } else if self.http2 {
+            HttpVersion::Http2
+        } else if self.http2_prior_knowledge {
+            HttpVersion::Http2PriorKnowledge
+        } else {
+            HttpVersion::Negotiate
+        }
+    }
+
+    fn redirect(&self) -> RedirectOptions {
+        RedirectOptions {
+            follow: !self.no_follow,
+            max: self.max_redirects,
+            preserve_method: self.preserve_method,
+        }
+    }
+
+    /// The tool to print an equivalent command for, instead of sending the request.
+    fn export_format(&self) -> Option<ExportFormat> {
+        if self.as_curl {
+            Some(ExportFormat::Curl)
+        } else if self.as_wget {
+            Some(ExportFormat::Wget)
+        } else if self.as_httpie {
+            Some(ExportFormat::Httpie)
+        } else {
+            None
+        }
+    }
+
+    fn graphql_request(&self) -> anyhow::Result<Option<GraphqlRequest>> {
+        if self.introspect {
+            return Ok(Some(GraphqlRequest::introspection(true)));
+        }
+        let Some(source) = &self.graphql else {
+            return Ok(None);
+        };
+        Ok(Some(GraphqlRequest {
+            query: GraphqlRequest::load_query(source)?,
+            variables: Default::default(),
+            operation_name: self.operation.clone(),
+        }))
+    }
+
+    /// The parts to print; plain output (`pretty == false`) selects just the body by default.
+    fn print_selection(&self, pretty: bool) -> PrintSelection {
+        match self.print {
+            _ if self.body_only => PrintSelection::BODY,
+            Some(selection) => selection,
+            None if self.verbose => PrintSelection::ALL,
+            None if self.filter.is_some() => PrintSelection::BODY,
+            None if pretty => PrintSelection::RESPONSE,
+            None => PrintSelection::BODY,
+        }
+    }
+
+    fn response_filter(&self) -> Option<Filter> {
+        self.filter.clone().map(|filter| filter.with_raw_output(self.raw_output))
+    }
+
+    fn transport(&self) -> TransportOptions {
+        TransportOptions {
+            timeout: self.timeout,
+            connect_timeout: self.connect_timeout,
+            retry: RetryPolicy {
+                retries: self.retry,
+                statuses: self.retry_on.clone(),
+                ..Default::default()
+            },
+            tls: TlsOptions {
+                ca_cert: self.cacert.clone(),
+                client_cert: self.cert.clone(),
+                client_key: self.key.clone(),
+                insecure: self.insecure,
+                min_version: self.tls_min_version,
+                pins: self.pins.clone(),
+            },
+            resolve: self.resolve_options(),
+            proxy: self.proxy.clone(),
+            unix_socket: self.unix_socket.clone(),
+        }
+    }
+
+    fn resolve_options(&self) -> ResolveOptions {
+        let interface_address = self.interface.as_deref().and_then(|interface| interface.parse::<IpAddr>().ok());
+        ResolveOptions {
+            overrides: self.resolve.clone(),
+            connect_to: self.connect_to.clone(),
+            family: match (self.ipv4, self.ipv6) {
+                (true, _) => Some(IpFamily::V4),
+                (_, true) => Some(IpFamily::V6),
+                _ => None,
+            },
+            local_address: self.local_address.or(interface_address),
+            interface: self.interface.clone().filter(|_| interface_address.is_none()),
+        }
+    }
+}
+
+fn parse_print(value: &str) -> Result<PrintSelection, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_tls_version(value: &str) -> Result<TlsVersion, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_resolve(value: &str) -> Result<ResolveOverride, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_encoding(value: &str) -> Result<Encoding, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_proxy(value: &str) -> Result<Proxy, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn parse_connect_to(value: &str) -> Result<ConnectTo, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Reads `@FILE` templates and rejects unknown variables before anything is sent.
+fn parse_write_out(value: &str) -> Result<String, String> {
+    let template = match value.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path, e))?,
+        None => value.to_string(),
+    };
+    write_out::validate(&template).map_err(|e| e.to_string())?;
+    Ok(template)
+}
+
+/// Compiles a `--filter` expression, so that syntax errors are reported before anything is sent.
+fn parse_filter(value: &str) -> Result<Filter, String> {
+    Filter::parse(value).map_err(|e| e.to_string())
+}
+
+pub(crate) fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("'{}' is not a positive number of seconds", value)),
+    }
+}
+
+pub async fn handle_send(command: SendCommand) -> ExitCode {
     let destination = if !command.destination.contains("://") {
         format!("http://{}", command.destination)
     } else {
@@ -48,85 +510,785 @@ pub async fn handle_send(command: SendCommand) {
 
     if let Ok(url) = Url::parse(&destination) {
         match url.scheme() {
-            "http" | "https" => handle_http_request(command, url).await,
+            "http" | "https" | "http+unix" => handle_http_request(command, url).await,
             "ws" | "wss" => handle_ws_request(command, url).await,
-            "tcp" => handle_tcp_request(command, &destination).await,
-            "udp" => handle_udp_request(command, &destination).await,
-            _ => logger::error(&format!("Unsupported protocol: {}", url.scheme())),
+            "tcp" => handle_tcp_request(command, &destination["tcp://".len()..]).await,
+            "udp" => handle_udp_request(command, &destination["udp://".len()..]).await,
+            "unix" => handle_unix_request(command, Path::new(&destination["unix://".len()..])).await,
+            _ => {
+                logger::error(&format!("Unsupported protocol: {}", url.scheme()));
+                ExitCode::FAILURE
+            }
         }
     } else if let Some(addr) = destination.strip_prefix("tcp://").or_else(|| destination.strip_prefix("udp://")) {
         if destination.starts_with("tcp://") {
-            handle_tcp_request(command, addr).await;
+            handle_tcp_request(command, addr).await
         } else {
-            handle_udp_request(command, addr).await;
+            handle_udp_request(command, addr).await
         }
     }
     else {
         logger::error(&format!("Invalid URL or address: {}", command.destination));
+        ExitCode::FAILURE
     }
 }
 
-async fn handle_http_request(command: SendCommand, url: Url) {
-    logger::info(&format!("Sending HTTP request to: {}", url));
+async fn handle_http_request(command: SendCommand, url: Url) -> ExitCode {
+    // Decorations only make sense on a terminal; redirected output stays machine-readable.
+    let pretty = io::stdout().is_terminal();
+    let selection = command.print_selection(pretty);
+    let filter = command.response_filter();
+    let export_format = command.export_format();
+    let sending = !command.offline && export_format.is_none();
+    if pretty && sending {
+        logger::info(&format!("Sending HTTP request to: {}", url));
+    }
+    let graphql = match command.graphql_request() {
+        Ok(graphql) => graphql,
+        Err(e) => {
+            logger::error(&format!("{:#}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let has_body_items = command.body.iter().any(|item| request_items::is_body_item(item));
+    let raw_body = if command.data_file.is_none() && !has_body_items && !command.ignore_stdin && graphql.is_none() {
+        match read_piped_stdin().await {
+            Ok(body) => body,
+            Err(e) => {
+                logger::error(&format!("Failed to read request body from stdin: {}", e));
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        None
+    };
+
+    let auth = match command.auth.as_deref().map(|credentials| resolve_auth(credentials, command.auth_type)) {
+        Some(Ok(auth)) => Some(auth),
+        Some(Err(e)) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    let session_path = match command.session.as_deref().map(Session::path_for).transpose() {
+        Ok(path) => path,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+    let session = match session_path.as_deref().map(Session::load).transpose() {
+        Ok(session) => session,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+    let cookie_jar = match build_cookie_jar(&command, session.as_ref()) {
+        Ok(jar) => jar,
+        Err(e) => {
+            logger::error(&e.to_string());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let transport = command.transport();
+    let redirect = command.redirect();
+    let http_version = command.http_version();
     let method = command.method.unwrap_or_else(|| {
-        if command.data_file.is_some() || !command.body.is_empty() {
+        if command.data_file.is_some() || raw_body.is_some() || has_body_items || graphql.is_some() {
             "POST".to_string()
         } else {
             "GET".to_string()
         }
     });
 
-    let http_request = http_client::HttpRequest {
+    let mut http_request = http_client::HttpRequest {
         url: url.to_string(),
         method,
         headers: command.headers,
         body: command.body,
         data_file: command.data_file,
+        raw_body,
         noproxy: command.noproxy,
+        body_mode: if command.multipart {
+            http_client::BodyMode::Multipart
+        } else if command.form {
+            http_client::BodyMode::Form
+        } else {
+            http_client::BodyMode::Json
+        },
+        auth,
+        cookie_jar: cookie_jar.clone(),
+        transport,
+        redirect,
+        http_version,
+        compress: command.compress,
+        response_encoding: if command.raw { ResponseEncoding::Raw } else { ResponseEncoding::Decode },
     };
+    // What this request adds to the session is only remembered once a response arrives.
+    let given = session.is_some().then(|| http_request.clone());
+    if let Some(session) = &session {
+        session.apply(&mut http_request);
+    }
+    let graphql_mode = graphql.is_some();
+    // Kept to introspect again without `isRepeatable` if the server rejects that field.
+    let introspection_retry = command.introspect.then(|| http_request.clone());
+    if let Some(graphql) = graphql {
+        if let Err(e) = graphql.apply(&mut http_request) {
+            logger::error(&format!("Failed to build the GraphQL request: {:#}", e));
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(format) = export_format {
+        return match export::export(http_request, format).await {
+            Ok(line) => {
+                println!("{}", line);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                logger::error(&format!("Failed to export request: {:#}", e));
+                ExitCode::FAILURE
+            }
+        };
+    }
 
-    match http_client::send_request(http_request).await {
+    if command.offline {
+        let version = match http_version {
+            HttpVersion::Http2 | HttpVersion::Http2PriorKnowledge => "HTTP/2",
+            HttpVersion::Negotiate | HttpVersion::Http1_1 => "HTTP/1.1",
+        };
+        return match http_client::dry_run(http_request).await {
+            Ok(request) => {
+                let mut output = Output::new(pretty, true);
+                output.request_headers(&request, version);
+                output.request_body(&request);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                logger::error(&format!("Failed to build request: {:#}", e));
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if command.download || command.output.is_some() {
+        let options = download::DownloadOptions { output: command.output.clone(), resume: command.resume };
+        let result = download::download(http_request, options).await;
+        if result.is_ok() {
+            save_cookies(command.export_cookies.as_deref(), session, session_path.as_deref(), cookie_jar.as_deref(), given.as_ref());
+        }
+        return match result {
+            Ok(result) => {
+                if let Some(tls) = result.tls.as_ref().filter(|_| command.verbose) {
+                    print_tls_details(tls);
+                }
+                let mut output = Output::new(pretty, command.verbose);
+                if selection.request_headers {
+                    output.request_headers(&result.request, &result.version);
+                }
+                if selection.response_headers {
+                    output.redirects(&result.redirects);
+                    output.response_headers(&format!("{} {}", result.version, result.status), &http_client::header_pairs(&result.headers));
+                }
+                if pretty && result.resumed_from > 0 {
+                    logger::info(&format!(
+                        "Saved {} bytes to {} (resumed from byte {})",
+                        result.bytes_written,
+                        result.path.display(),
+                        result.resumed_from,
+                    ));
+                } else if pretty {
+                    logger::info(&format!("Saved {} bytes to {}", result.bytes_written, result.path.display()));
+                }
+                print_transfer_report(command.timing, command.write_out.as_deref(), &result.transfer());
+                status_exit_code(&result.status, command.check_status)
+            }
+            Err(e) => {
+                logger::error(&format!("Download failed: {:#}", e));
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    // Kept to reopen an event stream; responses are only streamed once their headers say so.
+    let stream_request = http_request.clone();
+    let result = http_client::open_request(http_request).await;
+    if result.is_ok() {
+        save_cookies(command.export_cookies.as_deref(), session, session_path.as_deref(), cookie_jar.as_deref(), given.as_ref());
+    }
+    let result = match result {
+        Ok(Opened::Complete(response)) => match introspection_retry {
+            Some(mut request) if GraphqlResponse::parse(&response.body).is_ok_and(|result| result.rejects_is_repeatable()) => {
+                logger::warn("The server does not support isRepeatable; introspecting without it");
+                match GraphqlRequest::introspection(false).apply(&mut request) {
+                    Ok(()) => http_client::send_request(request).await,
+                    Err(e) => Err(e),
+                }
+            }
+            _ => Ok(*response),
+        },
+        Ok(Opened::Streaming(response)) => {
+            if let Some(tls) = response.tls.as_ref().filter(|_| command.verbose) {
+                print_tls_details(tls);
+            }
+            let options = StreamOptions { reconnect: command.reconnect, ..Default::default() };
+            let output = Output::new(pretty, command.verbose).with_filter(filter.clone());
+            return print_stream(*response, &stream_request, &options, &selection, output, command.check_status).await;
+        }
+        Err(e) => Err(e),
+    };
+    match result {
         Ok(response) => {
-            logger::info(&format!("Status: {}", response.status));
-            
-            let headers_for_table: Vec<(String, String)> = response.headers.iter()
-                .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-                .collect();
-            if !headers_for_table.is_empty() {
-                println!("Headers:");
-                if let Err(e) = print_key_value_table(&headers_for_table) {
-                    logger::error(&format!("Failed to print headers table: {}", e));
+            if let Some(tls) = response.tls.as_ref().filter(|_| command.verbose) {
+                print_tls_details(tls);
+            }
+            let mut output = Output::new(pretty, command.verbose).with_filter(filter.clone());
+            if selection.request_headers {
+                output.request_headers(&response.request, &response.version);
+            }
+            if selection.request_body {
+                output.request_body(&response.request);
+            }
+            if selection.response_headers {
+                output.redirects(&response.redirects);
+                output.response_headers(&format!("{} {}", response.version, response.status), &http_client::header_pairs(&response.headers));
+            }
+            let mut graphql_failed = false;
+            if selection.response_body && graphql_mode {
+                graphql_failed = !output.graphql_result(&response, command.introspect);
+            } else if selection.response_body {
+                output.response_body(&response, command.binary, command.raw);
+            }
+            print_transfer_report(command.timing, command.write_out.as_deref(), &response.transfer());
+            match status_exit_code(&response.status, command.check_status) {
+                ExitCode::SUCCESS if graphql_failed || output.filter_failed => ExitCode::FAILURE,
+                code => code,
+            }
+        },
+        Err(e) => {
+            logger::error(&format!("Request failed: {:#}", e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints an event stream or NDJSON body as it arrives, one event or line at a time.
+async fn print_stream(
+    response: StreamingResponse,
+    request: &HttpRequest,
+    options: &StreamOptions,
+    selection: &PrintSelection,
+    mut output: Output,
+    check_status: bool,
+) -> ExitCode {
+    if selection.request_headers {
+        output.request_headers(&response.request, &response.version);
+    }
+    if selection.request_body {
+        output.request_body(&response.request);
+    }
+    if selection.response_headers {
+        output.redirects(&response.redirects);
+        output.response_headers(&format!("{} {}", response.version, response.status), &http_client::header_pairs(&response.headers));
+    }
+    let status = response.status.clone();
+    let result = event_stream::follow(response, request, options, |item| match item {
+        StreamItem::Event(event) if selection.response_body => output.stream_event(&event),
+        StreamItem::Line(line) if selection.response_body => output.stream_line(&line),
+        StreamItem::Reconnecting { delay, last_event_id, reason } => {
+            let resume = last_event_id.map(|id| format!(" from event {}", id)).unwrap_or_default();
+            logger::warn(&format!("{}. Reconnecting in {:.1}s{}", reason, delay.as_secs_f64(), resume));
+        }
+        _ => {}
+    })
+    .await;
+    match result {
+        Ok(()) if output.filter_failed => ExitCode::FAILURE,
+        Ok(()) => status_exit_code(&status, check_status),
+        Err(e) => {
+            logger::error(&format!("Stream failed: {:#}", e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Maps 3xx, 4xx and 5xx statuses to exit codes 3, 4 and 5 for `--check-status`.
+fn status_exit_code(status: &str, check_status: bool) -> ExitCode {
+    let code = status.split_whitespace().next().and_then(|code| code.parse::<u16>().ok()).unwrap_or(0);
+    if !check_status || !(300..600).contains(&code) {
+        return ExitCode::SUCCESS;
+    }
+    logger::warn(&format!("Server responded with {}", status));
+    ExitCode::from((code / 100) as u8)
+}
+
+/// Writes the selected parts of an exchange: decorated tables and highlighting on a
+/// terminal, or HTTP-like plain text separated by blank lines otherwise.
+pub(crate) struct Output {
+    pretty: bool,
+    /// Show messages as raw HTTP, as they went over the wire.
+    wire: bool,
+    /// Whether something was written and whether it ended with a newline.
+    last: Option<bool>,
+    /// A streamed body has started, so its items follow one another without separators.
+    streaming: bool,
+    /// Prints only what this selects from JSON bodies, event data and NDJSON lines.
+    filter: Option<Filter>,
+    /// A body could not be filtered.
+    pub(crate) filter_failed: bool,
+}
+
+impl Output {
+    pub(crate) fn new(pretty: bool, wire: bool) -> Self {
+        Output { pretty, wire, last: None, streaming: false, filter: None, filter_failed: false }
+    }
+
+    pub(crate) fn with_filter(self, filter: Option<Filter>) -> Self {
+        Output { filter, ..self }
+    }
+
+    pub(crate) fn request_headers(&mut self, request: &SentRequest, version: &str) {
+        let headers = http_client::header_pairs(&request.headers);
+        if self.wire {
+            self.wire_head(&request.request_line(version), &headers);
+        } else if self.pretty {
+            logger::info(&format!("Request: {} {}", request.method, request.url));
+            print_headers(&headers);
+        } else {
+            self.write_head(&format!("{} {}", request.method, request.url), &headers);
+        }
+    }
+
+    pub(crate) fn request_body(&mut self, request: &SentRequest) {
+        let Some(body) = request.body.as_deref().filter(|body| !body.is_empty()) else {
+            let multipart = request.headers.get("content-type").and_then(|v| v.to_str().ok()).is_some_and(|v| v.starts_with("multipart/"));
+            if self.pretty && multipart {
+                logger::info("The multipart body is streamed from files and not shown.");
+            }
+            return;
+        };
+        if self.wire && self.pretty {
+            self.pretty_raw_body(body);
+        } else if self.pretty {
+            println!("\nRequest body:");
+            self.pretty_body(body, None, false);
+        } else {
+            self.write_body(body);
+        }
+    }
+
+    /// Shows the followed redirects: a summary table on a terminal, every hop's head otherwise.
+    pub(crate) fn redirects(&mut self, hops: &[RedirectHop]) {
+        if hops.is_empty() {
+            return;
+        }
+        if !self.pretty {
+            for hop in hops {
+                self.write_head(&format!("{} {}", hop.version, hop.status), &http_client::header_pairs(&hop.headers));
+            }
+            return;
+        }
+        let rows: Vec<(String, String)> = hops
+            .iter()
+            .map(|hop| {
+                (
+                    format!("{} {} ({})", hop.version, hop.status, waterfall::format_duration(hop.timings.total)),
+                    format!("{} {} -> {}", hop.method, hop.url, hop.location),
+                )
+            })
+            .collect();
+        println!("Redirects:");
+        if let Err(e) = print_key_value_table(&rows) {
+            logger::error(&format!("Failed to print redirects: {}", e));
+        }
+    }
+
+    pub(crate) fn response_headers(&mut self, status: &str, headers: &[(String, String)]) {
+        if self.wire {
+            self.wire_head(status, headers);
+        } else if self.pretty {
+            logger::info(&format!("Status: {}", status));
+            print_headers(headers);
+        } else {
+            self.write_head(status, headers);
+        }
+    }
+
+    pub(crate) fn response_body(&mut self, response: &HttpResponse, binary: bool, raw: bool) {
+        if self.pretty {
+            if let Some(encoding) = &response.content_encoding {
+                let wire_size = response.timings.bytes_received;
+                if response.decoded {
+                    logger::info(&format!("Content-Encoding: {} ({} bytes on the wire, {} decoded)", encoding, wire_size, response.body.len()));
+                } else if raw {
+                    logger::info(&format!("Content-Encoding: {} ({} bytes, kept as received)", encoding, wire_size));
+                } else {
+                    logger::warn(&format!("Content-Encoding: {} cannot be decoded; showing the {} bytes as received", encoding, wire_size));
                 }
             }
-            
             println!("\nBody:");
-            pretty_print_json(&response.body);
-        },
-        Err(e) => logger::error(&format!("Request failed: {}", e)),
+        }
+        if self.filter.is_some() {
+            if let Some(results) = self.apply_filter(&response.body) {
+                self.filter_results(&results, false);
+            }
+        } else if self.pretty {
+            self.pretty_body(&response.body, response.content_type(), binary);
+        } else {
+            self.write_body(&response.body);
+        }
+    }
+
+    /// Prints a Server-Sent Event: its type and ID, then its data, as pretty JSON when it is JSON.
+    /// Without a terminal, each event is written as one line of JSON, or with a filter, as its results.
+    pub(crate) fn stream_event(&mut self, event: &SseEvent) {
+        if self.pretty {
+            let id = event.id.as_ref().map(|id| format!(" (id {})", id)).unwrap_or_default();
+            logger::info(&format!("Event: {}{}", event.event.as_deref().unwrap_or("message"), id));
+        }
+        if self.filter.is_some() {
+            if let Some(results) = self.apply_filter(event.data.as_bytes()) {
+                self.filter_results(&results, true);
+            }
+        } else if self.pretty {
+            pretty_print_json(&event.data);
+        } else {
+            self.write_stream(format!("{}\n", event.to_json()).as_bytes());
+        }
+    }
+
+    /// Prints one line of an NDJSON body.
+    pub(crate) fn stream_line(&mut self, line: &str) {
+        if self.filter.is_some() {
+            if let Some(results) = self.apply_filter(line.as_bytes()) {
+                self.filter_results(&results, true);
+            }
+        } else if self.pretty {
+            pretty_print_json(line);
+        } else {
+            self.write_stream(format!("{}\n", line).as_bytes());
+        }
+    }
+
+    fn write_stream(&mut self, bytes: &[u8]) {
+        if self.streaming {
+            write_stdout(bytes);
+        } else {
+            self.streaming = true;
+            self.write_body(bytes);
+        }
+    }
+
+    /// Prints a GraphQL result's `data` (or with `sdl`, the schema it describes) and
+    /// reports its errors on stderr. Returns whether the result was free of errors.
+    pub(crate) fn graphql_result(&mut self, response: &HttpResponse, sdl: bool) -> bool {
+        let result = match GraphqlResponse::parse(&response.body) {
+            Ok(result) => result,
+            Err(e) => {
+                logger::warn(&format!("Not a GraphQL result: {:#}", e));
+                self.response_body(response, false, false);
+                return false;
+            }
+        };
+        match (&result.data, sdl) {
+            (Some(data), true) => match graphql::schema_sdl(data) {
+                Ok(schema) => self.write_body(schema.as_bytes()),
+                Err(e) => {
+                    logger::error(&format!("{:#}", e));
+                    return false;
+                }
+            },
+            (Some(_), false) => {
+                let data = result.data_json().unwrap_or_default();
+                if self.pretty {
+                    println!("\nData:");
+                }
+                if self.filter.is_some() {
+                    if let Some(results) = self.apply_filter(data.as_bytes()) {
+                        self.filter_results(&results, false);
+                    }
+                } else if self.pretty {
+                    pretty_print_json(&data);
+                } else {
+                    self.write_body(format!("{}\n", data).as_bytes());
+                }
+            }
+            (None, _) => {}
+        }
+        for message in result.error_messages() {
+            logger::error(&format!("GraphQL error: {}", message));
+        }
+        result.errors.is_empty()
+    }
+
+    /// Runs the filter over a JSON document, reporting a failure instead of printing anything.
+    fn apply_filter(&mut self, document: &[u8]) -> Option<Vec<String>> {
+        match self.filter.as_ref()?.render(document) {
+            Ok(results) => Some(results),
+            Err(e) => {
+                logger::error(&format!("Filter failed: {:#}", e));
+                self.filter_failed = true;
+                None
+            }
+        }
+    }
+
+    /// Prints filter results one after another, highlighted on a terminal.
+    fn filter_results(&mut self, results: &[String], streamed: bool) {
+        if self.pretty {
+            results.iter().for_each(|result| pretty_print_json(result));
+            return;
+        }
+        let text: String = results.iter().map(|result| format!("{}\n", result)).collect();
+        if streamed {
+            self.write_stream(text.as_bytes());
+        } else {
+            self.write_body(text.as_bytes());
+        }
+    }
+
+    /// A body as it was sent, unformatted; binary bodies are summarized.
+    fn pretty_raw_body(&self, body: &[u8]) {
+        if vyg_core::content::is_binary(None, body) {
+            println!("[{} bytes of binary data]", body.len());
+        } else {
+            println!("{}", String::from_utf8_lossy(body));
+        }
+        println!();
+    }
+
+    fn pretty_body(&self, body: &[u8], content_type: Option<&str>, binary: bool) {
+        if !vyg_core::content::is_binary(content_type, body) {
+            pretty_print_json(&String::from_utf8_lossy(body));
+        } else if !binary {
+            logger::warn(&format!(
+                "Binary body ({} bytes, {}) not shown. Use --binary to print it or redirect stdout to a file.",
+                body.len(),
+                content_type.unwrap_or("unknown type"),
+            ));
+        } else {
+            write_stdout(body);
+        }
+    }
+
+    /// Writes a message head as raw HTTP; its trailing blank line already separates it from the body.
+    fn wire_head(&mut self, start_line: &str, headers: &[(String, String)]) {
+        if self.pretty {
+            http::print_head(start_line, headers);
+        } else {
+            self.write_body(http::render_head(start_line, headers).as_bytes());
+            self.last = None;
+        }
+    }
+
+    fn write_head(&mut self, first_line: &str, headers: &[(String, String)]) {
+        let mut text = format!("{}\n", first_line);
+        for (name, value) in headers {
+            text.push_str(&format!("{}: {}\n", name, value));
+        }
+        self.write_body(text.as_bytes());
+    }
+
+    fn write_body(&mut self, bytes: &[u8]) {
+        match self.last {
+            Some(true) => write_stdout(b"\n"),
+            Some(false) => write_stdout(b"\n\n"),
+            None => {}
+        }
+        write_stdout(bytes);
+        self.last = Some(bytes.last().is_none_or(|&b| b == b'\n'));
+    }
+}
+
+fn write_stdout(bytes: &[u8]) {
+    let mut stdout = io::stdout();
+    if let Err(e) = stdout.write_all(bytes).and_then(|_| stdout.flush()) {
+        logger::error(&format!("Failed to write to stdout: {}", e));
+    }
+}
+
+
+/// Builds the cookie jar shared with the HTTP client when a session or cookie file is in use.
+fn build_cookie_jar(command: &SendCommand, session: Option<&Session>) -> anyhow::Result<Option<Arc<SharedCookieJar>>> {
+    if session.is_none() && command.import_cookies.is_none() && command.export_cookies.is_none() {
+        return Ok(None);
+    }
+    let mut jar = session.map(|s| s.cookies.clone()).unwrap_or_default();
+    if let Some(path) = &command.import_cookies {
+        jar.merge(CookieJar::import_netscape(path)?);
+    }
+    Ok(Some(Arc::new(SharedCookieJar::new(jar))))
+}
+
+/// Persists the session, with the headers and credentials `given` for the request, and
+/// exports cookies received during the request. Only called once a response has arrived.
+fn save_cookies(
+    export_path: Option<&Path>,
+    session: Option<Session>,
+    session_path: Option<&Path>,
+    cookie_jar: Option<&SharedCookieJar>,
+    given: Option<&HttpRequest>,
+) {
+    let Some(cookie_jar) = cookie_jar else { return };
+    let mut cookies = cookie_jar.snapshot();
+    cookies.remove_expired();
+
+    if let Some(path) = export_path {
+        if let Err(e) = cookies.export_netscape(path) {
+            logger::error(&e.to_string());
+        }
+    }
+    if let (Some(mut session), Some(path)) = (session, session_path) {
+        if let Some(request) = given {
+            session.remember(request);
+        }
+        session.cookies = cookies;
+        if let Err(e) = session.save(path) {
+            logger::error(&format!("Failed to save session: {}", e));
+        }
     }
 }
 
-async fn handle_ws_request(command: SendCommand, url: Url) {
+/// Turns `--auth` credentials into an [`Auth`], prompting for a missing password.
+pub(crate) fn resolve_auth(credentials: &str, auth_type: AuthType) -> anyhow::Result<Auth> {
+    if auth_type == AuthType::Bearer {
+        return Ok(Auth::Bearer(credentials.to_string()));
+    }
+
+    let (username, password) = match credentials.split_once(':') {
+        Some((username, password)) => (username.to_string(), password.to_string()),
+        None if io::stdin().is_terminal() => {
+            let password = rpassword::prompt_password(format!("Password for user '{}': ", credentials))?;
+            (credentials.to_string(), password)
+        }
+        None => anyhow::bail!("No password given for user '{}' and stdin is not a terminal to prompt for one.", credentials),
+    };
+
+    Ok(match auth_type {
+        AuthType::Digest => Auth::Digest { username, password },
+        _ => Auth::Basic { username, password },
+    })
+}
+
+/// Prints what the TLS handshake of the connection that was used negotiated, as far as it is known.
+/// Like the logger's diagnostics, it goes to stderr so that piped output stays the body alone.
+fn print_tls_details(details: &TlsDetails) {
+    let mut rows = Vec::new();
+    if let Some(protocol) = &details.protocol {
+        rows.push(("Protocol".to_string(), protocol.clone()));
+    }
+    if let Some(cipher) = &details.cipher {
+        rows.push(("Cipher".to_string(), cipher.clone()));
+    }
+    if let Some(certificate) = &details.peer_certificate {
+        rows.push(("Subject".to_string(), certificate.subject.clone()));
+        rows.push(("Issuer".to_string(), certificate.issuer.clone()));
+        rows.push(("Valid".to_string(), format!("{} to {}", certificate.not_before, certificate.not_after)));
+        if !certificate.subject_alt_names.is_empty() {
+            rows.push(("Names".to_string(), certificate.subject_alt_names.join(", ")));
+        }
+        rows.push(("Public key".to_string(), format!("sha256//{}", certificate.spki_sha256)));
+    }
+    eprintln!("TLS:");
+    if let Err(e) = eprint_key_value_table(&rows) {
+        logger::error(&format!("Failed to print TLS details: {}", e));
+    }
+}
+
+/// Prints the --timing waterfall to stderr and the --write-out template to stdout, when requested.
+fn print_transfer_report(timing: bool, write_out: Option<&str>, transfer: &Transfer) {
+    if timing {
+        let timings = transfer.timings;
+        let phases: Vec<Phase> = timings.phases()
+            .into_iter()
+            .map(|(label, start, end)| Phase::new(label, start, end))
+            .collect();
+        eprintln!("\nTiming:");
+        waterfall::print_waterfall(&phases);
+        match timings.bytes_sent {
+            Some(sent) => eprintln!("Sent {} bytes, received {} bytes", sent, timings.bytes_received),
+            None => eprintln!("Received {} bytes", timings.bytes_received),
+        }
+    }
+    if let Some(template) = write_out {
+        match write_out::render(template, transfer) {
+            Ok(output) => {
+                print!("{}", output);
+                let _ = io::stdout().flush();
+            }
+            Err(e) => logger::error(&e.to_string()),
+        }
+    }
+}
+
+fn print_headers(headers_for_table: &[(String, String)]) {
+    if !headers_for_table.is_empty() {
+        println!("Headers:");
+        if let Err(e) = print_key_value_table(headers_for_table) {
+            logger::error(&format!("Failed to print headers table: {}", e));
+        }
+    }
+}
+
+/// Reads a request body piped on stdin. Returns `None` for a terminal or empty input.
+async fn read_piped_stdin() -> io::Result<Option<Vec<u8>>> {
+    if io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut body = Vec::new();
+    tokio::io::stdin().read_to_end(&mut body).await?;
+    Ok(if body.is_empty() { None } else { Some(body) })
+}
+
+async fn handle_ws_request(command: SendCommand, url: Url) -> ExitCode {
     let message = if command.body.is_empty() {
         None
     } else {
         Some(command.body.join(" "))
     };
-    if let Err(e) = ws_client::connect_ws(url.as_str(), message).await {
-        logger::error(&format!("WebSocket connection failed: {}", e));
+    let filter = command.response_filter();
+    let on_tls = |tls: &TlsDetails| {
+        if command.verbose {
+            print_tls_details(tls);
+        }
+    };
+    match ws_client::connect_ws(url.as_str(), message, filter.as_ref(), &command.transport(), on_tls).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            logger::error(&format!("WebSocket connection failed: {}", e));
+            ExitCode::FAILURE
+        }
     }
 }
 
-async fn handle_tcp_request(command: SendCommand, address: &str) {
+async fn handle_tcp_request(command: SendCommand, address: &str) -> ExitCode {
     let data = command.body.join(" ").into_bytes();
-    if let Err(e) = net_client::send_tcp_request(address, data, command.interactive).await {
+    if let Err(e) = net_client::send_tcp_request(address, data, command.interactive, &command.transport()).await {
         logger::error(&format!("TCP request failed: {}", e));
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+async fn handle_unix_request(command: SendCommand, path: &Path) -> ExitCode {
+    let data = command.body.join(" ").into_bytes();
+    if let Err(e) = net_client::send_unix_request(path, data, command.interactive, &command.transport()).await {
+        logger::error(&format!("Unix socket request failed: {}", e));
+        return ExitCode::FAILURE;
     }
+    ExitCode::SUCCESS
 }
 
-async fn handle_udp_request(command: SendCommand, address: &str) {
+async fn handle_udp_request(command: SendCommand, address: &str) -> ExitCode {
     let data = command.body.join(" ").into_bytes();
-    if let Err(e) = net_client::send_udp_request(address, data).await {
+    if let Err(e) = net_client::send_udp_request(address, data, &command.transport()).await {
         logger::error(&format!("UDP request failed: {}", e));
+        return ExitCode::FAILURE;
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,131 @@
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use vyg_core::scenario::{self, Outcome, RunOptions, Scenario, StepResult};
+use vyg_core::test_report;
+use vyg_core::tls::TlsOptions;
+use vyg_core::transport::TransportOptions;
+use vyg_display::logger;
+use crate::commands::run::parse_variable;
+use crate::commands::send::parse_seconds;
+
+#[derive(Args, Debug)]
+pub struct TestCommand {
+    /// The scenario file (YAML): `steps` with an `http`, `ws` or `tcp` request each,
+    /// plus optional `capture` and `assert` sections.
+    #[arg()]
+    pub file: PathBuf,
+
+    /// Set a variable, overriding the scenario's `variables`.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+
+    /// Write a machine-readable report: to stdout, or to the --output file.
+    #[arg(long, value_enum)]
+    pub report: Option<ReportFormat>,
+
+    /// Write the report to this file and keep the step-by-step summary on stdout.
+    #[arg(short, long, value_name = "FILE", requires = "report")]
+    pub output: Option<PathBuf>,
+
+    /// Skip the remaining steps after the first failure.
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Allow insecure server connections when using SSL.
+    #[arg(short = 'k', long)]
+    pub insecure: bool,
+
+    /// Give up on each step after this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Junit,
+    Tap,
+}
+
+pub async fn handle_test(command: TestCommand) -> ExitCode {
+    let scenario = match Scenario::load(&command.file) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            logger::error(&format!("{:#}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let suite = scenario.name.clone().unwrap_or_else(|| command.file.display().to_string());
+    let options = RunOptions {
+        variables: command.variables.iter().cloned().collect(),
+        transport: TransportOptions {
+            timeout: command.timeout,
+            tls: TlsOptions { insecure: command.insecure, ..Default::default() },
+            ..Default::default()
+        },
+        fail_fast: command.fail_fast,
+    };
+
+    // The summary would corrupt a report written to stdout.
+    let summary = command.report.is_none() || command.output.is_some();
+    if summary && io::stdout().is_terminal() {
+        logger::info(&format!("Running {} ({} steps)", suite, scenario.steps.len()));
+    }
+    let results = scenario::run(&scenario, &options, |result| {
+        if summary {
+            print_step(result);
+        }
+    })
+    .await;
+
+    if let Some(format) = command.report {
+        let report = match format {
+            ReportFormat::Junit => test_report::junit(&suite, &results),
+            ReportFormat::Tap => test_report::tap(&results),
+        };
+        match &command.output {
+            Some(path) => {
+                if let Err(e) = fs::write(path, report) {
+                    logger::error(&format!("Failed to write the report to {}: {}", path.display(), e));
+                    return ExitCode::FAILURE;
+                }
+            }
+            None => print!("{}", report),
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    if summary {
+        let skipped = results.iter().filter(|result| result.outcome == Outcome::Skipped).count();
+        let passed = results.len() - failed - skipped;
+        let line = format!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+        if failed == 0 {
+            logger::success(&line);
+        } else {
+            logger::error(&line);
+        }
+    }
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn print_step(result: &StepResult) {
+    let duration = result.duration.as_millis();
+    match &result.outcome {
+        Outcome::Passed => logger::success(&format!("{} ({} ms)", result.name, duration)),
+        Outcome::Skipped => logger::warn(&format!("{} skipped", result.name)),
+        Outcome::Failed(failures) => {
+            logger::error(&format!("{} failed ({} ms)", result.name, duration));
+            for failure in failures {
+                eprintln!("  - {}", failure);
+            }
+        }
+        Outcome::Error(error) => logger::error(&format!("{}: {}", result.name, error)),
+    }
+}
@@ -1,29 +1,45 @@
 use clap::{Parser, CommandFactory};
 use cli::Cli;
-use commands::{send::handle_send, listen::handle_listen, bridge::handle_bridge};
+use commands::{send::handle_send, listen::handle_listen, bridge::handle_bridge, import_curl::handle_import_curl, run::handle_run, test::handle_test, bench::handle_bench};
 use std::io;
+use std::process::ExitCode;
 
 mod cli;
 mod commands;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
     match cli.command {
         cli::Commands::Send(command) => {
-            handle_send(command).await;
+            handle_send(*command).await
         }
         cli::Commands::Listen(command) => {
             handle_listen(command).await;
+            ExitCode::SUCCESS
         }
         cli::Commands::Bridge(command) => {
             handle_bridge(command).await;
+            ExitCode::SUCCESS
+        }
+        cli::Commands::Run(command) => {
+            handle_run(command).await
+        }
+        cli::Commands::Test(command) => {
+            handle_test(command).await
+        }
+        cli::Commands::Bench(command) => {
+            handle_bench(command).await
+        }
+        cli::Commands::ImportCurl(command) => {
+            handle_import_curl(command).await
         }
         cli::Commands::GenerateCompletion { shell } => {
             let mut cmd = Cli::command();
             let name = cmd.get_name().to_string();
             clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+            ExitCode::SUCCESS
         }
     }
 }
@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::Result;
+use futures_util::SinkExt;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::Message;
+use vyg_core::filter::Filter;
+use vyg_core::http_client::{self, HttpRequest};
+use vyg_core::tls::{TlsOptions, TlsVersion};
+use vyg_core::transport::TransportOptions;
+use vyg_core::ws_client;
+
+static TLS12_ONLY: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS12];
+
+/// The base64 SHA-256 hash of `server.pem`'s public key.
+const SERVER_PIN: &str = "5VtHUIvlBajAKysjPn2j8rqubEsxx6nwzlAOpeTNFcg=";
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+}
+
+fn certificates(name: &str) -> Vec<CertificateDer<'static>> {
+    CertificateDer::pem_file_iter(fixture(name)).unwrap().collect::<Result<_, _>>().unwrap()
+}
+
+struct ServerOptions {
+    versions: &'static [&'static rustls::SupportedProtocolVersion],
+    require_client_cert: bool,
+    websocket: bool,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        ServerOptions { versions: rustls::ALL_VERSIONS, require_client_cert: false, websocket: false }
+    }
+}
+
+/// Serves a fixed HTTP response (or a one-message WebSocket) over TLS and returns the port.
+async fn start_tls_server(options: ServerOptions) -> u16 {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(options.versions)
+        .unwrap();
+    let builder = if options.require_client_cert {
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(certificates("ca.pem"));
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build().unwrap();
+        builder.with_client_cert_verifier(verifier)
+    } else {
+        builder.with_no_client_auth()
+    };
+    let key = PrivateKeyDer::from_pem_file(fixture("server-key.pem")).unwrap();
+    let config = builder.with_single_cert(certificates("server.pem"), key).unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut stream) = acceptor.accept(stream).await else { return };
+                if options.websocket {
+                    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    ws.send(Message::Text("hello over wss".to_string())).await.unwrap();
+                    ws.close(None).await.unwrap();
+                    return;
+                }
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    port
+}
+
+async fn get(port: u16, tls: TlsOptions) -> Result<http_client::HttpResponse> {
+    http_client::send_request(HttpRequest {
+        url: format!("https://127.0.0.1:{}/", port),
+        method: "GET".to_string(),
+        noproxy: true,
+        transport: TransportOptions { tls, ..Default::default() },
+        ..Default::default()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_https_with_custom_ca() -> Result<()> {
+    let port = start_tls_server(ServerOptions::default()).await;
+
+    assert!(get(port, TlsOptions::default()).await.is_err());
+
+    let response = get(port, TlsOptions { ca_cert: Some(fixture("ca.pem")), ..Default::default() }).await?;
+    assert_eq!(response.text(), "ok");
+
+    let response = get(port, TlsOptions { insecure: true, ..Default::default() }).await?;
+    assert_eq!(response.text(), "ok");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_https_certificate_pinning() -> Result<()> {
+    let port = start_tls_server(ServerOptions::default()).await;
+    let options = |pin: &str| TlsOptions {
+        ca_cert: Some(fixture("ca.pem")),
+        pins: vec![pin.to_string()],
+        ..Default::default()
+    };
+
+    let error = get(port, options("sha256//AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")).await.unwrap_err();
+    assert!(format!("{:#}", error).contains(SERVER_PIN), "{:#}", error);
+
+    let response = get(port, options(SERVER_PIN)).await?;
+    assert_eq!(response.text(), "ok");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_https_mutual_tls() -> Result<()> {
+    let port = start_tls_server(ServerOptions { require_client_cert: true, ..Default::default() }).await;
+    let ca_only = TlsOptions { ca_cert: Some(fixture("ca.pem")), ..Default::default() };
+
+    assert!(get(port, ca_only.clone()).await.is_err());
+
+    let with_identity = TlsOptions {
+        client_cert: Some(fixture("client.pem")),
+        client_key: Some(fixture("client-key.pem")),
+        ..ca_only
+    };
+    assert_eq!(get(port, with_identity.clone()).await?.text(), "ok");
+    assert_eq!(get(port, TlsOptions { min_version: Some(TlsVersion::Tls1_3), ..with_identity }).await?.text(), "ok");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tls_min_version() -> Result<()> {
+    let tls12_only = start_tls_server(ServerOptions { versions: TLS12_ONLY, ..Default::default() }).await;
+    let options = |version| TlsOptions {
+        ca_cert: Some(fixture("ca.pem")),
+        min_version: Some(version),
+        ..Default::default()
+    };
+
+    assert_eq!(get(tls12_only, options(TlsVersion::Tls1_2)).await?.text(), "ok");
+    assert!(get(tls12_only, options(TlsVersion::Tls1_3)).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reports_the_tls_details_of_the_connection_used() -> Result<()> {
+    let port = start_tls_server(ServerOptions::default()).await;
+    let trusted = TlsOptions { ca_cert: Some(fixture("ca.pem")), ..Default::default() };
+
+    let details = get(port, trusted.clone()).await?.tls.expect("the response came over TLS");
+    let certificate = details.peer_certificate.unwrap();
+    assert_eq!(certificate.subject, "CN=localhost, O=Varyag");
+    assert_eq!(certificate.issuer, "CN=Varyag Test CA, O=Varyag");
+    assert_eq!(certificate.subject_alt_names, vec!["localhost", "127.0.0.1"]);
+    assert_eq!(certificate.spki_sha256, SERVER_PIN);
+    assert!(certificate.not_after.ends_with(" UTC"));
+
+    // rustls also reports the protocol and cipher of a WebSocket connection.
+    let port = start_tls_server(ServerOptions { websocket: true, ..Default::default() }).await;
+    let url = format!("wss://127.0.0.1:{}/", port);
+    let options = TransportOptions { tls: TlsOptions { min_version: Some(TlsVersion::Tls1_3), ..trusted }, ..Default::default() };
+    let mut reported = None;
+    ws_client::connect_ws(&url, None, None, &options, |details| reported = Some(details.clone())).await?;
+    let details = reported.expect("no TLS details were reported");
+    assert_eq!(details.protocol.as_deref(), Some("TLSv1.3"));
+    assert!(details.cipher.unwrap().starts_with("TLS13_"));
+    assert_eq!(details.peer_certificate.unwrap().spki_sha256, SERVER_PIN);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wss_with_custom_ca_and_pin() -> Result<()> {
+    let port = start_tls_server(ServerOptions { websocket: true, ..Default::default() }).await;
+    let url = format!("wss://127.0.0.1:{}/", port);
+    let options = |tls| TransportOptions { tls, ..Default::default() };
+
+    assert!(ws_client::connect_ws(&url, None, None, &options(TlsOptions::default()), |_| ()).await.is_err());
+
+    let trusted = TlsOptions { ca_cert: Some(fixture("ca.pem")), pins: vec![SERVER_PIN.to_string()], ..Default::default() };
+    assert!(ws_client::connect_ws(&url, None, None, &options(trusted.clone()), |_| ()).await?);
+    assert!(ws_client::connect_ws(&url, None, None, &options(TlsOptions { min_version: Some(TlsVersion::Tls1_3), ..trusted.clone() }), |_| ()).await?);
+
+    // The greeting is not JSON, so a filter fails on it.
+    let filter = Filter::parse(".greeting")?;
+    assert!(!ws_client::connect_ws(&url, None, Some(&filter), &options(trusted), |_| ()).await?);
+
+    let wrong_pin = TlsOptions { insecure: true, pins: vec!["bogus".to_string()], ..Default::default() };
+    assert!(ws_client::connect_ws(&url, None, None, &options(wrong_pin), |_| ()).await.is_err());
+    Ok(())
+}
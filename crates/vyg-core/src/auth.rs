@@ -0,0 +1,220 @@
+use anyhow::{anyhow, Result};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Credentials attached to an HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Auth {
+    Basic { username: String, password: String },
+    Bearer(String),
+    /// Sent only after the server answers `401` with a `WWW-Authenticate: Digest` challenge.
+    Digest { username: String, password: String },
+}
+
+/// A parsed `WWW-Authenticate: Digest ...` challenge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: String,
+    pub qop: Vec<String>,
+}
+
+/// Finds and parses a Digest challenge among `WWW-Authenticate` header values.
+pub fn find_digest_challenge<'a>(values: impl IntoIterator<Item = &'a str>) -> Option<DigestChallenge> {
+    values.into_iter().find_map(|value| {
+        let start = value.to_ascii_lowercase().find("digest ")?;
+        parse_digest_challenge(&value[start + "digest ".len()..])
+    })
+}
+
+fn parse_digest_challenge(params: &str) -> Option<DigestChallenge> {
+    let params = parse_auth_params(params);
+    Some(DigestChallenge {
+        realm: params.get("realm").cloned().unwrap_or_default(),
+        nonce: params.get("nonce")?.clone(),
+        opaque: params.get("opaque").cloned(),
+        algorithm: params.get("algorithm").cloned().unwrap_or_else(|| "MD5".to_string()),
+        qop: params
+            .get("qop")
+            .map(|qop| qop.split(',').map(|q| q.trim().to_ascii_lowercase()).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Parses `key=value, key="quoted, value"` pairs; keys are lowercased.
+fn parse_auth_params(input: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = input.trim();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().trim_start_matches(',').trim().to_ascii_lowercase();
+        rest = rest[eq + 1..].trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    _ => value.push(c),
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let value = rest[..end].trim().to_string();
+            rest = &rest[end..];
+            value
+        };
+        params.insert(key, value);
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+    }
+    params
+}
+
+/// Computes the `Authorization` header value answering a Digest challenge (RFC 7616).
+///
+/// `uri` is the request target (path and query). Only the `auth` quality of protection
+/// is supported; `auth-int` would require hashing the body.
+pub fn digest_authorization(
+    challenge: &DigestChallenge,
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Result<String> {
+    let algorithm = challenge.algorithm.to_ascii_uppercase();
+    let (hash, session): (fn(&str) -> String, bool) = match algorithm.as_str() {
+        "MD5" => (md5_hex, false),
+        "MD5-SESS" => (md5_hex, true),
+        "SHA-256" => (sha256_hex, false),
+        "SHA-256-SESS" => (sha256_hex, true),
+        other => return Err(anyhow!("Unsupported digest algorithm: {}", other)),
+    };
+
+    let qop = if challenge.qop.is_empty() {
+        None
+    } else if challenge.qop.iter().any(|q| q == "auth") {
+        Some("auth")
+    } else {
+        return Err(anyhow!("Unsupported digest qop: {}", challenge.qop.join(", ")));
+    };
+    let nc = "00000001";
+
+    let mut ha1 = hash(&format!("{}:{}:{}", username, challenge.realm, password));
+    if session {
+        ha1 = hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = hash(&format!("{}:{}", method, uri));
+    let response = match qop {
+        Some(qop) => hash(&format!("{}:{}:{}:{}:{}:{}", ha1, challenge.nonce, nc, cnonce, qop, ha2)),
+        None => hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2)),
+    };
+
+    let mut header = format!(
+        r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm={}, response="{}""#,
+        username, challenge.realm, challenge.nonce, uri, challenge.algorithm, response
+    );
+    if let Some(opaque) = &challenge.opaque {
+        header.push_str(&format!(r#", opaque="{}""#, opaque));
+    }
+    if let Some(qop) = qop {
+        header.push_str(&format!(r#", qop={}, nc={}, cnonce="{}""#, qop, nc, cnonce));
+    }
+    Ok(header)
+}
+
+/// A random client nonce for Digest authentication.
+pub fn generate_cnonce() -> String {
+    format!("{:016x}", fastrand::u64(..))
+}
+
+fn md5_hex(input: &str) -> String {
+    hex::encode(Md5::digest(input.as_bytes()))
+}
+
+fn sha256_hex(input: &str) -> String {
+    hex::encode(Sha256::digest(input.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_digest_challenge() {
+        let challenge = find_digest_challenge([
+            r#"Basic realm="fallback""#,
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        ])
+        .unwrap();
+        assert_eq!(challenge, DigestChallenge {
+            realm: "testrealm@host.com".to_string(),
+            nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_string(),
+            opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_string()),
+            algorithm: "MD5".to_string(),
+            qop: vec!["auth".to_string(), "auth-int".to_string()],
+        });
+        assert!(find_digest_challenge([r#"Basic realm="x""#]).is_none());
+    }
+
+    #[test]
+    fn test_digest_authorization_rfc2617_example() {
+        // The worked example from RFC 2617, section 3.5.
+        let challenge = find_digest_challenge([
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        ])
+        .unwrap();
+        let header = digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        )
+        .unwrap();
+        assert!(header.contains(r#"response="6629fae49393a05397450978507c4ef1""#));
+        assert!(header.contains("qop=auth, nc=00000001"));
+        assert!(header.contains(r#"opaque="5ccc069c403ebaf9f0171e9517f40e41""#));
+    }
+
+    #[test]
+    fn test_digest_authorization_sha256() {
+        // The SHA-256 example from RFC 7616, section 3.9.1.
+        let challenge = find_digest_challenge([
+            r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=SHA-256, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#,
+        ])
+        .unwrap();
+        let header = digest_authorization(
+            &challenge,
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        )
+        .unwrap();
+        assert!(header.contains(r#"response="753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1""#));
+    }
+
+    #[test]
+    fn test_digest_authorization_rejects_auth_int_only() {
+        let challenge = find_digest_challenge([r#"Digest realm="r", nonce="n", qop="auth-int""#]).unwrap();
+        assert!(digest_authorization(&challenge, "u", "p", "GET", "/", "c").is_err());
+    }
+}
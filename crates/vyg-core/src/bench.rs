@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use crate::histogram::Histogram;
+use crate::http_client::{HttpRequest, RepeatedRequest};
+
+/// When a benchmark stops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BenchLimit {
+    Requests(u64),
+    Duration(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchOptions {
+    /// How many requests are in flight at once.
+    pub connections: usize,
+    pub limit: BenchLimit,
+    /// Start requests at this fixed rate per second instead of as soon as the previous one
+    /// finishes. Latency is then measured from when a request was due, so a slow server
+    /// cannot hide its queueing delay by slowing the client down.
+    pub rate: Option<f64>,
+}
+
+/// What a benchmark observed.
+#[derive(Debug, Clone, Default)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    /// Latencies of requests that got a response, whatever its status.
+    pub latency: Histogram,
+    pub statuses: BTreeMap<u16, u64>,
+    /// Requests that got no response, by error message.
+    pub errors: BTreeMap<String, u64>,
+    pub bytes_received: u64,
+}
+
+impl BenchReport {
+    pub fn requests(&self) -> u64 {
+        self.statuses.values().sum::<u64>() + self.errors.values().sum::<u64>()
+    }
+
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn merge(&mut self, other: BenchReport) {
+        self.latency.merge(&other.latency);
+        for (status, count) in other.statuses {
+            *self.statuses.entry(status).or_default() += count;
+        }
+        for (error, count) in other.errors {
+            *self.errors.entry(error).or_default() += count;
+        }
+        self.bytes_received += other.bytes_received;
+    }
+}
+
+/// Sends `request` repeatedly from `options.connections` concurrent workers sharing one
+/// keep-alive client, calling `on_response` after every request.
+pub async fn run(request: HttpRequest, options: BenchOptions, on_response: impl Fn() + Send + Sync + 'static) -> Result<BenchReport> {
+    let request = Arc::new(RepeatedRequest::new(request).await?);
+    let on_response = Arc::new(on_response);
+    let next = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+
+    let workers: Vec<_> = (0..options.connections.max(1))
+        .map(|_| {
+            let (request, on_response, next) = (request.clone(), on_response.clone(), next.clone());
+            tokio::spawn(async move {
+                let mut report = BenchReport::default();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let due = match options.rate {
+                        Some(rate) => started + Duration::from_secs_f64(index as f64 / rate),
+                        None => Instant::now(),
+                    };
+                    let done = match options.limit {
+                        BenchLimit::Requests(limit) => index >= limit,
+                        BenchLimit::Duration(duration) => due >= started + duration,
+                    };
+                    if done {
+                        break;
+                    }
+                    time::sleep_until(due).await;
+                    match request.send().await {
+                        Ok((status, bytes)) => {
+                            report.latency.record(due.elapsed());
+                            *report.statuses.entry(status).or_default() += 1;
+                            report.bytes_received += bytes as u64;
+                        }
+                        Err(e) => *report.errors.entry(format!("{:#}", e)).or_default() += 1,
+                    }
+                    on_response();
+                }
+                report
+            })
+        })
+        .collect();
+
+    let mut report = BenchReport::default();
+    for worker in workers {
+        report.merge(worker.await?);
+    }
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_OID: u8 = 0x06;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_VERSION: u8 = 0xa0;
+const TAG_EXTENSIONS: u8 = 0xa3;
+const TAG_SAN_DNS: u8 = 0x82;
+const TAG_SAN_IP: u8 = 0x87;
+
+/// DER encoding of the subjectAltName extension OID, 2.5.29.17.
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// The parts of an X.509 certificate worth showing to a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    pub subject_alt_names: Vec<String>,
+    /// Base64 SHA-256 digest of the SubjectPublicKeyInfo, as used by `--pin-sha256`.
+    pub spki_sha256: String,
+}
+
+impl CertificateSummary {
+    /// Parses a DER-encoded certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        parse(der).ok_or_else(|| anyhow!("Invalid or unsupported X.509 certificate"))
+    }
+}
+
+/// The base64 SHA-256 digest of a DER certificate's SubjectPublicKeyInfo.
+pub fn spki_sha256(der: &[u8]) -> Result<String> {
+    Ok(CertificateSummary::from_der(der)?.spki_sha256)
+}
+
+/// A DER value: its tag and contents, plus the complete encoding.
+struct Element<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    encoded: &'a [u8],
+}
+
+/// Reads consecutive DER elements from a byte slice.
+struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Reader { input }
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.input.first().copied()
+    }
+
+    fn next(&mut self) -> Option<Element<'a>> {
+        let (&tag, rest) = self.input.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (length, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let length = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (length, &rest[count..])
+        };
+        if rest.len() < length {
+            return None;
+        }
+        let header = self.input.len() - rest.len();
+        let element = Element { tag, contents: &rest[..length], encoded: &self.input[..header + length] };
+        self.input = &rest[length..];
+        Some(element)
+    }
+
+    fn expect(&mut self, tag: u8) -> Option<Element<'a>> {
+        self.next().filter(|element| element.tag == tag)
+    }
+}
+
+fn parse(der: &[u8]) -> Option<CertificateSummary> {
+    let certificate = Reader::new(der).expect(TAG_SEQUENCE)?;
+    let tbs = Reader::new(certificate.contents).expect(TAG_SEQUENCE)?;
+    let mut fields = Reader::new(tbs.contents);
+
+    if fields.peek_tag() == Some(TAG_VERSION) {
+        fields.next()?;
+    }
+    fields.next()?; // serialNumber
+    fields.expect(TAG_SEQUENCE)?; // signature algorithm
+    let issuer = fields.expect(TAG_SEQUENCE)?;
+    let mut validity = Reader::new(fields.expect(TAG_SEQUENCE)?.contents);
+    let not_before = parse_time(&validity.next()?)?;
+    let not_after = parse_time(&validity.next()?)?;
+    let subject = fields.expect(TAG_SEQUENCE)?;
+    let spki = fields.expect(TAG_SEQUENCE)?;
+
+    let mut subject_alt_names = Vec::new();
+    while let Some(field) = fields.next() {
+        if field.tag == TAG_EXTENSIONS {
+            subject_alt_names = parse_subject_alt_names(field.contents).unwrap_or_default();
+        }
+    }
+
+    Some(CertificateSummary {
+        subject: format_name(subject.contents)?,
+        issuer: format_name(issuer.contents)?,
+        not_before,
+        not_after,
+        subject_alt_names,
+        spki_sha256: STANDARD.encode(Sha256::digest(spki.encoded)),
+    })
+}
+
+/// Renders a distinguished name as `CN=example.com, O=Example`.
+fn format_name(name: &[u8]) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut rdns = Reader::new(name);
+    while let Some(rdn) = rdns.next() {
+        if rdn.tag != TAG_SET {
+            return None;
+        }
+        let mut attributes = Reader::new(rdn.contents);
+        while let Some(attribute) = attributes.next() {
+            let mut attribute = Reader::new(attribute.contents);
+            let oid = attribute.expect(TAG_OID)?;
+            let value = attribute.next()?;
+            let label = match oid.contents {
+                [0x55, 0x04, 0x03] => "CN".to_string(),
+                [0x55, 0x04, 0x06] => "C".to_string(),
+                [0x55, 0x04, 0x07] => "L".to_string(),
+                [0x55, 0x04, 0x08] => "ST".to_string(),
+                [0x55, 0x04, 0x0a] => "O".to_string(),
+                [0x55, 0x04, 0x0b] => "OU".to_string(),
+                other => format!("OID:{}", hex::encode(other)),
+            };
+            parts.push(format!("{}={}", label, String::from_utf8_lossy(value.contents)));
+        }
+    }
+    Some(parts.join(", "))
+}
+
+/// Renders a UTCTime or GeneralizedTime as `YYYY-MM-DD HH:MM:SS UTC`.
+fn parse_time(element: &Element) -> Option<String> {
+    let text = std::str::from_utf8(element.contents).ok()?;
+    let digits = text.strip_suffix('Z')?;
+    let full = match element.tag {
+        // Two-digit years 50-99 are 19xx, 00-49 are 20xx (RFC 5280, section 4.1.2.5.1).
+        TAG_UTC_TIME if digits.len() == 12 => {
+            let century = if digits[..2].parse::<u8>().ok()? >= 50 { "19" } else { "20" };
+            format!("{}{}", century, digits)
+        }
+        TAG_GENERALIZED_TIME if digits.len() == 14 => digits.to_string(),
+        _ => return None,
+    };
+    if !full.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "{}-{}-{} {}:{}:{} UTC",
+        &full[0..4], &full[4..6], &full[6..8], &full[8..10], &full[10..12], &full[12..14]
+    ))
+}
+
+/// Extracts DNS names and IP addresses from the subjectAltName extension.
+fn parse_subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let list = Reader::new(extensions).expect(TAG_SEQUENCE)?;
+    let mut extensions = Reader::new(list.contents);
+    while let Some(extension) = extensions.next() {
+        let mut fields = Reader::new(extension.contents);
+        if fields.expect(TAG_OID)?.contents != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        let mut value = fields.next()?;
+        if value.tag != TAG_OCTET_STRING {
+            // Skip the optional `critical` flag.
+            value = fields.expect(TAG_OCTET_STRING)?;
+        }
+        let names = Reader::new(value.contents).expect(TAG_SEQUENCE)?;
+        let mut names = Reader::new(names.contents);
+        let mut result = Vec::new();
+        while let Some(name) = names.next() {
+            match name.tag {
+                TAG_SAN_DNS => result.push(String::from_utf8_lossy(name.contents).to_string()),
+                TAG_SAN_IP => {
+                    let ip = match name.contents.len() {
+                        4 => IpAddr::from(<[u8; 4]>::try_from(name.contents).ok()?),
+                        16 => IpAddr::from(<[u8; 16]>::try_from(name.contents).ok()?),
+                        _ => continue,
+                    };
+                    result.push(ip.to_string());
+                }
+                _ => {}
+            }
+        }
+        return Some(result);
+    }
+    Some(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time() {
+        let utc = Element { tag: TAG_UTC_TIME, contents: b"491231235959Z", encoded: &[] };
+        assert_eq!(parse_time(&utc), Some("2049-12-31 23:59:59 UTC".to_string()));
+        let utc = Element { tag: TAG_UTC_TIME, contents: b"500101000000Z", encoded: &[] };
+        assert_eq!(parse_time(&utc), Some("1950-01-01 00:00:00 UTC".to_string()));
+        let generalized = Element { tag: TAG_GENERALIZED_TIME, contents: b"21260924072802Z", encoded: &[] };
+        assert_eq!(parse_time(&generalized), Some("2126-09-24 07:28:02 UTC".to_string()));
+        let local = Element { tag: TAG_UTC_TIME, contents: b"260101000000+0100", encoded: &[] };
+        assert_eq!(parse_time(&local), None);
+    }
+
+    #[test]
+    fn test_reader_long_form_length() {
+        let mut der = vec![TAG_OCTET_STRING, 0x81, 0x80];
+        der.extend([7u8; 0x80]);
+        der.push(TAG_SEQUENCE);
+        der.push(0x00);
+        let mut reader = Reader::new(&der);
+        let element = reader.next().unwrap();
+        assert_eq!(element.contents.len(), 0x80);
+        assert_eq!(element.encoded.len(), 0x83);
+        assert!(reader.expect(TAG_SEQUENCE).is_some());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_rejects_truncated_certificate() {
+        assert!(CertificateSummary::from_der(&[TAG_SEQUENCE, 0x82, 0x01]).is_err());
+        assert!(CertificateSummary::from_der(&[TAG_SEQUENCE, 0x05, 0x30, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Result};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// The `Accept-Encoding` sent when compressed responses are wanted: the codings that can be decoded.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+// A middle-of-the-road Brotli quality and the usual 4 MiB window: fast enough for request bodies.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+
+/// A content coding that bodies can be compressed with and decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    /// `deflate`, which HTTP defines as zlib-wrapped DEFLATE.
+    Deflate,
+    /// `br`, i.e. Brotli.
+    Brotli,
+    Zstd,
+}
+
+impl Encoding {
+    /// The `Content-Encoding` token.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            Encoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                encoder.write_all(data)?;
+                encoder.into_inner()
+            }
+            Encoding::Zstd => zstd::stream::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?,
+        })
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        let result = match self {
+            Encoding::Gzip => GzDecoder::new(data).read_to_end(&mut decoded),
+            Encoding::Deflate => ZlibDecoder::new(data).read_to_end(&mut decoded),
+            Encoding::Brotli => brotli::Decompressor::new(data, 4096).read_to_end(&mut decoded),
+            Encoding::Zstd => zstd::stream::read::Decoder::new(data).and_then(|mut decoder| decoder.read_to_end(&mut decoded)),
+        };
+        result.map_err(|e| anyhow!("Failed to decode {} body: {}. Use --raw to keep it as received.", self.name(), e))?;
+        Ok(decoded)
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Ok(Encoding::Gzip),
+            "deflate" => Ok(Encoding::Deflate),
+            "br" | "brotli" => Ok(Encoding::Brotli),
+            "zstd" => Ok(Encoding::Zstd),
+            other => Err(anyhow!("Unsupported content encoding: '{}'. Use gzip, deflate, br or zstd.", other)),
+        }
+    }
+}
+
+/// Whether compressed responses are asked for, and what happens to them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseEncoding {
+    /// Ask for nothing in particular and keep the body as received.
+    #[default]
+    AsIs,
+    /// Ask for compressed bodies and decode them.
+    Decode,
+    /// Ask for compressed bodies but keep them compressed.
+    Raw,
+}
+
+/// Undoes a `Content-Encoding`, whose codings were applied in the order listed.
+///
+/// Returns `None` when a coding is not supported, so the body can be shown as received.
+pub fn decode(content_encoding: &str, body: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut decoded = body.to_vec();
+    for coding in content_encoding.split(',').map(str::trim).rev() {
+        if coding.is_empty() || coding.eq_ignore_ascii_case("identity") {
+            continue;
+        }
+        let Ok(encoding) = coding.parse::<Encoding>() else {
+            return Ok(None);
+        };
+        decoded = encoding.decompress(&decoded)?;
+    }
+    Ok(Some(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = b"varyag varyag varyag varyag varyag";
+        for encoding in [Encoding::Gzip, Encoding::Deflate, Encoding::Brotli, Encoding::Zstd] {
+            let compressed = encoding.compress(text).unwrap();
+            assert_ne!(compressed, text);
+            assert_eq!(encoding.decompress(&compressed).unwrap(), text);
+        }
+        assert!("compress".parse::<Encoding>().is_err());
+    }
+
+    #[test]
+    fn test_decode_applies_codings_in_reverse() {
+        let twice = Encoding::Gzip.compress(&Encoding::Deflate.compress(b"body").unwrap()).unwrap();
+        assert_eq!(decode("deflate, gzip", &twice).unwrap().unwrap(), b"body");
+        assert_eq!(decode("identity", b"body").unwrap().unwrap(), b"body");
+        assert!(decode("compress", b"body").unwrap().is_none());
+        assert!(decode("gzip", b"not gzip").unwrap_err().to_string().contains("--raw"));
+    }
+
+    #[test]
+    fn test_decode_brotli_and_zstd() {
+        let text = b"{\"name\": \"varyag\", \"tags\": [\"http\", \"http\", \"http\"]}";
+        assert_eq!(decode("br", &Encoding::Brotli.compress(text).unwrap()).unwrap().unwrap(), text);
+        assert_eq!(decode("zstd", &Encoding::Zstd.compress(text).unwrap()).unwrap().unwrap(), text);
+        assert_eq!(decode("gzip, zstd", &Encoding::Zstd.compress(&Encoding::Gzip.compress(text).unwrap()).unwrap()).unwrap().unwrap(), text);
+        // `printf varyag | zstd -c`, not produced by the encoder above.
+        let zstd_cli = [0x28, 0xb5, 0x2f, 0xfd, 0x04, 0x58, 0x31, 0x00, 0x00, 0x76, 0x61, 0x72, 0x79, 0x61, 0x67, 0x1b, 0x7b, 0xd5, 0x71];
+        assert_eq!(decode("zstd", &zstd_cli).unwrap().unwrap(), b"varyag");
+        assert!(decode("br", b"not brotli").unwrap_err().to_string().contains("br"));
+        assert!(decode("zstd", b"not zstd").unwrap_err().to_string().contains("zstd"));
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+/// Magic-number prefixes for common binary formats.
+const SIGNATURES: [(&[u8], &str); 9] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// Infers a `Content-Type` for a request body.
+///
+/// The file extension wins when it is known; otherwise the content itself is sniffed.
+pub fn infer_content_type(path: Option<&Path>, bytes: &[u8]) -> String {
+    if let Some(mime) = path.and_then(|p| mime_guess::from_path(p).first()) {
+        return mime.essence_str().to_string();
+    }
+    sniff_content_type(bytes).to_string()
+}
+
+/// Guesses a MIME type from the content alone.
+pub fn sniff_content_type(bytes: &[u8]) -> &'static str {
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+    if serde_json::from_slice::<serde_json::Value>(bytes).is_ok() {
+        "application/json"
+    } else if looks_like_text(bytes) {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Returns `true` if a body should be treated as binary rather than printable text.
+pub fn is_binary(content_type: Option<&str>, bytes: &[u8]) -> bool {
+    if let Some(content_type) = content_type {
+        let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+        if is_textual_mime(&essence) {
+            return false;
+        }
+        if essence.starts_with("image/")
+            || essence.starts_with("audio/")
+            || essence.starts_with("video/")
+            || essence.starts_with("font/")
+        {
+            return true;
+        }
+    }
+    !looks_like_text(bytes)
+}
+
+fn is_textual_mime(essence: &str) -> bool {
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-www-form-urlencoded"
+                | "application/x-ndjson"
+                | "application/graphql"
+        )
+}
+
+/// Valid UTF-8 without NUL bytes is considered text.
+fn looks_like_text(bytes: &[u8]) -> bool {
+    !bytes.contains(&0) && std::str::from_utf8(bytes).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_content_type_from_extension() {
+        assert_eq!(infer_content_type(Some(Path::new("payload.json")), b"not json"), "application/json");
+        assert_eq!(infer_content_type(Some(Path::new("archive.zip")), b""), "application/zip");
+    }
+
+    #[test]
+    fn test_infer_content_type_by_sniffing() {
+        assert_eq!(infer_content_type(Some(Path::new("payload")), br#"{"a": 1}"#), "application/json");
+        assert_eq!(infer_content_type(None, b"\x89PNG\r\n\x1a\n\0\0"), "image/png");
+        assert_eq!(infer_content_type(None, b"hello"), "text/plain");
+        assert_eq!(infer_content_type(None, &[0x08, 0x96, 0x01, 0x00]), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(Some("application/json; charset=utf-8"), b"{}"));
+        assert!(!is_binary(Some("application/problem+json"), b"{}"));
+        assert!(is_binary(Some("image/webp"), b"RIFF"));
+        assert!(!is_binary(Some("image/svg+xml"), b"<svg/>"));
+        assert!(is_binary(Some("application/octet-stream"), b"\xff\xfe\0"));
+        assert!(!is_binary(Some("application/octet-stream"), b"plain"));
+        assert!(is_binary(None, b"\0\x01"));
+    }
+}
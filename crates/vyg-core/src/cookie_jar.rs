@@ -0,0 +1,313 @@
+use anyhow::{anyhow, Result};
+use cookie::{time::OffsetDateTime, Cookie, Expiration};
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use url::{Host, Url};
+
+/// A cookie as kept in a session file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    /// `true` if the cookie only matches `domain` exactly, not its subdomains.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Expiry as a Unix timestamp; `None` for session cookies.
+    pub expires: Option<i64>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else { return false };
+        let host = host.to_ascii_lowercase();
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            host == self.domain || host.ends_with(&format!(".{}", self.domain))
+        };
+        domain_matches && path_matches(url.path(), &self.path) && (!self.secure || url.scheme() == "https")
+    }
+}
+
+/// A simple RFC 6265 cookie jar that can be persisted and exchanged in Netscape format.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CookieJar {
+    pub cookies: Vec<StoredCookie>,
+}
+
+impl CookieJar {
+    /// Stores a cookie received in a `Set-Cookie` header for `url`.
+    ///
+    /// Without a public suffix list, a `Domain` must have at least two labels to be shared
+    /// with subdomains, so `Domain=com` is refused. Cookies from IP addresses are host-only.
+    pub fn store(&mut self, set_cookie: &str, url: &Url) {
+        let Ok(cookie) = Cookie::parse(set_cookie.to_string()) else { return };
+        let Some(host) = url.host_str().map(|h| h.to_ascii_lowercase()) else { return };
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let (domain, host_only) = match cookie.domain().map(|d| d.trim_start_matches('.').to_ascii_lowercase()) {
+            Some(domain) if !domain.is_empty() => {
+                let is_ip = !matches!(url.host(), Some(Host::Domain(_)));
+                if host == domain && (is_ip || !domain.contains('.')) {
+                    (host, true)
+                } else if is_ip || !domain.contains('.') || !host.ends_with(&format!(".{}", domain)) {
+                    // A server may only set cookies for its own domain or a parent of it
+                    // that is more than a top-level domain.
+                    return;
+                } else {
+                    (domain, false)
+                }
+            }
+            _ => (host, true),
+        };
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_string(),
+            _ => default_path(url.path()),
+        };
+        let expires = match (cookie.max_age(), cookie.expires()) {
+            (Some(max_age), _) => Some(now + max_age.whole_seconds()),
+            (None, Some(Expiration::DateTime(at))) => Some(at.unix_timestamp()),
+            _ => None,
+        };
+
+        let stored = StoredCookie {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain,
+            host_only,
+            path,
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            expires,
+        };
+
+        self.cookies.retain(|c| !(c.name == stored.name && c.domain == stored.domain && c.path == stored.path));
+        if !stored.is_expired(now) {
+            self.cookies.push(stored);
+        }
+    }
+
+    /// The `Cookie` header value to send to `url`, if any cookie matches.
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let mut matching: Vec<&StoredCookie> = self.cookies.iter()
+            .filter(|c| !c.is_expired(now) && c.matches(url))
+            .collect();
+        // More specific paths first, as recommended by RFC 6265.
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        if matching.is_empty() {
+            return None;
+        }
+        Some(matching.iter().map(|c| format!("{}={}", c.name, c.value)).collect::<Vec<_>>().join("; "))
+    }
+
+    pub fn remove_expired(&mut self) {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        self.cookies.retain(|c| !c.is_expired(now));
+    }
+
+    /// Merges cookies from another jar, replacing cookies with the same name, domain and path.
+    pub fn merge(&mut self, other: CookieJar) {
+        for cookie in other.cookies {
+            self.cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+            self.cookies.push(cookie);
+        }
+    }
+
+    /// Parses a Netscape/curl `cookies.txt` file.
+    pub fn from_netscape(content: &str) -> Result<Self> {
+        let mut cookies = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 7 {
+                return Err(anyhow!("Invalid cookie jar line {}: expected 7 tab-separated fields", number + 1));
+            }
+            let expires: i64 = fields[4].trim().parse()
+                .map_err(|_| anyhow!("Invalid expiry on cookie jar line {}", number + 1))?;
+            cookies.push(StoredCookie {
+                domain: fields[0].trim_start_matches('.').to_ascii_lowercase(),
+                host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+                path: fields[2].to_string(),
+                secure: fields[3].eq_ignore_ascii_case("TRUE"),
+                expires: if expires == 0 { None } else { Some(expires) },
+                name: fields[5].to_string(),
+                value: fields[6].to_string(),
+                http_only,
+            });
+        }
+        Ok(CookieJar { cookies })
+    }
+
+    /// Renders the jar in Netscape/curl `cookies.txt` format.
+    pub fn to_netscape(&self) -> String {
+        let mut output = String::from("# Netscape HTTP Cookie File\n# Generated by Varyag.\n\n");
+        for cookie in &self.cookies {
+            let domain = if cookie.host_only { cookie.domain.clone() } else { format!(".{}", cookie.domain) };
+            output.push_str(&format!(
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                if cookie.http_only { "#HttpOnly_" } else { "" },
+                domain,
+                if cookie.host_only { "FALSE" } else { "TRUE" },
+                cookie.path,
+                if cookie.secure { "TRUE" } else { "FALSE" },
+                cookie.expires.unwrap_or(0),
+                cookie.name,
+                cookie.value,
+            ));
+        }
+        output
+    }
+
+    pub fn import_netscape(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read cookie jar '{}': {}", path.display(), e))?;
+        Self::from_netscape(&content)
+    }
+
+    pub fn export_netscape(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_netscape())
+            .map_err(|e| anyhow!("Failed to write cookie jar '{}': {}", path.display(), e))
+    }
+}
+
+/// A jar shared with a `reqwest` client, so cookies set during redirects are captured too.
+#[derive(Debug, Default)]
+pub struct SharedCookieJar(Mutex<CookieJar>);
+
+impl SharedCookieJar {
+    pub fn new(jar: CookieJar) -> Self {
+        SharedCookieJar(Mutex::new(jar))
+    }
+
+    pub fn snapshot(&self) -> CookieJar {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl reqwest::cookie::CookieStore for SharedCookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut jar = self.0.lock().unwrap();
+        for header in cookie_headers {
+            if let Ok(value) = header.to_str() {
+                jar.store(value, url);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.0.lock().unwrap().header_for(url).and_then(|value| HeaderValue::from_str(&value).ok())
+    }
+}
+
+/// RFC 6265 section 5.1.4 path matching.
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// RFC 6265 section 5.1.4 default path: the request path up to its last `/`.
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(pos) => request_path[..pos].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_store_and_match_cookies() {
+        let mut jar = CookieJar::default();
+        jar.store("sid=abc; Path=/; HttpOnly", &url("http://app.example.com/login"));
+        jar.store("theme=dark; Domain=example.com; Path=/ui", &url("http://app.example.com/"));
+        jar.store("token=s3cr3t; Secure", &url("https://app.example.com/api/v1/auth"));
+
+        assert_eq!(jar.header_for(&url("http://app.example.com/")), Some("sid=abc".to_string()));
+        assert_eq!(
+            jar.header_for(&url("http://cdn.example.com/ui/index.html")),
+            Some("theme=dark".to_string())
+        );
+        assert_eq!(
+            jar.header_for(&url("https://app.example.com/api/v1/users")),
+            Some("token=s3cr3t; sid=abc".to_string())
+        );
+        assert_eq!(jar.header_for(&url("http://app.example.com/api/v1/users")), Some("sid=abc".to_string()));
+        assert_eq!(jar.header_for(&url("http://other.org/")), None);
+    }
+
+    #[test]
+    fn test_store_rejects_foreign_domain_and_removes_expired() {
+        let mut jar = CookieJar::default();
+        jar.store("evil=1; Domain=bank.com", &url("http://example.com/"));
+        assert!(jar.cookies.is_empty());
+
+        jar.store("sid=abc", &url("http://example.com/"));
+        jar.store("sid=; Max-Age=0", &url("http://example.com/"));
+        assert!(jar.cookies.is_empty());
+    }
+
+    #[test]
+    fn test_store_rejects_top_level_domains_and_keeps_ip_cookies_host_only() {
+        let mut jar = CookieJar::default();
+        jar.store("tld=1; Domain=com", &url("http://example.com/"));
+        jar.store("tld=1; Domain=.com", &url("http://example.com/"));
+        jar.store("ip=1; Domain=0.1", &url("http://10.0.0.1/"));
+        assert!(jar.cookies.is_empty());
+
+        jar.store("ip=1; Domain=10.0.0.1", &url("http://10.0.0.1/"));
+        jar.store("local=1; Domain=localhost", &url("http://localhost/"));
+        assert!(jar.cookies.iter().all(|cookie| cookie.host_only), "{:?}", jar.cookies);
+        assert_eq!(jar.header_for(&url("http://10.0.0.1/")), Some("ip=1".to_string()));
+        assert_eq!(jar.header_for(&url("http://api.localhost/")), None);
+    }
+
+    #[test]
+    fn test_netscape_round_trip() {
+        let content = "# Netscape HTTP Cookie File\n\
+            .example.com\tTRUE\t/\tFALSE\t0\ttheme\tdark\n\
+            #HttpOnly_app.example.com\tFALSE\t/api\tTRUE\t4102444800\tsid\tabc\n";
+        let jar = CookieJar::from_netscape(content).unwrap();
+        assert_eq!(jar.cookies[0], StoredCookie {
+            name: "theme".to_string(),
+            value: "dark".to_string(),
+            domain: "example.com".to_string(),
+            host_only: false,
+            path: "/".to_string(),
+            secure: false,
+            http_only: false,
+            expires: None,
+        });
+        assert!(jar.cookies[1].http_only && jar.cookies[1].host_only && jar.cookies[1].secure);
+        assert_eq!(CookieJar::from_netscape(&jar.to_netscape()).unwrap(), jar);
+    }
+
+    #[test]
+    fn test_netscape_invalid_line() {
+        assert!(CookieJar::from_netscape("example.com\tTRUE\t/\n").is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+/// curl options that take a value, by their short name.
+const SHORT_WITH_VALUE: &str = "XHdufFAxmoeb";
+
+/// curl options that only affect what curl prints, which `varyag send` decides on its own.
+const OUTPUT_OPTIONS: &[&str] = &["-s", "--silent", "-S", "--show-error", "-v", "--verbose", "-i", "--include", "--compressed"];
+
+/// The request a curl command line describes.
+#[derive(Debug, Default)]
+struct CurlRequest {
+    url: Option<String>,
+    method: Option<String>,
+    headers: Vec<String>,
+    data: Vec<String>,
+    /// `--data-binary @FILE` or `-d @FILE`.
+    data_file: Option<String>,
+    /// Send the data in the query string (`-G`).
+    get: bool,
+    /// `-F` and `--form-string` parts, already turned into items.
+    form: Vec<String>,
+    user: Option<String>,
+    digest: bool,
+    follow: bool,
+    /// Options that map to a `varyag send` option one to one.
+    options: Vec<String>,
+}
+
+/// Translates a curl command line into the arguments of an equivalent `varyag send`.
+///
+/// Supports the options needed to replay most copied requests: `-X`, `-H`, `-d`, `--data-binary`,
+/// `-u`, `-F`, `--form-string`, `-k` and their common companions; any other option is an error.
+pub fn send_args(command: &str) -> Result<Vec<String>> {
+    let words = shlex::split(command).ok_or_else(|| anyhow!("Invalid curl command: unbalanced quotes"))?;
+    let mut words = words.into_iter().skip_while(|word| word == "curl").flat_map(split_short_options);
+    let mut request = CurlRequest::default();
+
+    while let Some(word) = words.next() {
+        let mut value = || words.next().ok_or_else(|| anyhow!("Missing value for curl option {}", word));
+        match word.as_str() {
+            "-X" | "--request" => request.method = Some(value()?.to_uppercase()),
+            "-H" | "--header" => request.headers.push(value()?),
+            "-A" | "--user-agent" => request.headers.push(format!("User-Agent: {}", value()?)),
+            "-e" | "--referer" => request.headers.push(format!("Referer: {}", value()?)),
+            "-b" | "--cookie" => request.headers.push(format!("Cookie: {}", value()?)),
+            "-d" | "--data" | "--data-ascii" | "--data-binary" | "--data-raw" => {
+                let data = value()?;
+                match data.strip_prefix('@').filter(|_| word != "--data-raw") {
+                    Some(path) => request.data_file = Some(path.to_string()),
+                    None => request.data.push(data),
+                }
+            }
+            "-G" | "--get" => request.get = true,
+            "-F" | "--form" => request.form.push(form_part(&value()?)?),
+            "--form-string" => request.form.push(literal_form_part(&value()?)?),
+            "-u" | "--user" => request.user = Some(value()?),
+            "--digest" => request.digest = true,
+            "-I" | "--head" => request.method = Some("HEAD".to_string()),
+            "-L" | "--location" => request.follow = true,
+            "--url" => request.url = Some(value()?),
+            "-k" | "--insecure" => request.options.push("--insecure".to_string()),
+            "-f" | "--fail" => request.options.push("--check-status".to_string()),
+            "-4" | "--ipv4" | "-6" | "--ipv6" | "--http1.1" | "--http2" | "--http2-prior-knowledge" => {
+                request.options.push(word.clone())
+            }
+            "--max-redirs" => request.options.extend(["--max-redirects".to_string(), value()?]),
+            "-m" | "--max-time" => request.options.extend(["--timeout".to_string(), value()?]),
+            "-o" | "--output" => request.options.extend(["--output".to_string(), value()?]),
+            "-x" | "--proxy" => request.options.extend(["--proxy".to_string(), value()?]),
+            "--connect-timeout" | "--retry" | "--cacert" | "--cert" | "--key" | "--resolve" | "--connect-to" | "--interface"
+            | "--unix-socket" => request.options.extend([word.clone(), value()?]),
+            word if OUTPUT_OPTIONS.contains(&word) => {}
+            word if word.starts_with('-') && word.len() > 1 => bail!("Unsupported curl option: {}", word),
+            _ if request.url.is_none() => request.url = Some(word),
+            _ => bail!("Unexpected argument '{}': only one URL is supported", word),
+        }
+    }
+    request.into_send_args()
+}
+
+/// Splits grouped short options like `-sSL` and attached values like `-XPOST`.
+fn split_short_options(word: String) -> Vec<String> {
+    let Some(options) = word.strip_prefix('-').filter(|rest| !rest.starts_with('-') && rest.len() > 1) else {
+        return vec![word];
+    };
+    let mut split = Vec::new();
+    for (i, option) in options.char_indices() {
+        split.push(format!("-{}", option));
+        if SHORT_WITH_VALUE.contains(option) {
+            let value = &options[i + option.len_utf8()..];
+            if !value.is_empty() {
+                split.push(value.to_string());
+            }
+            break;
+        }
+    }
+    split
+}
+
+impl CurlRequest {
+    fn into_send_args(self) -> Result<Vec<String>> {
+        let url = self.url.ok_or_else(|| anyhow!("The curl command has no URL"))?;
+        // The method goes first, so body items are not taken for it.
+        let has_body = !self.form.is_empty() || self.data_file.is_some() || (!self.data.is_empty() && !self.get);
+        let method = self.method.clone().unwrap_or_else(|| if has_body { "POST" } else { "GET" }.to_string());
+        let mut args = vec![url, method];
+
+        let content_type = self.headers.iter().find_map(|header| {
+            header.split_once(':').filter(|(name, _)| name.trim().eq_ignore_ascii_case("content-type")).map(|(_, value)| value.trim())
+        });
+        let json = content_type.is_some_and(|value| value.starts_with("application/json"));
+        // The body options below imply the content type.
+        let mut drop_content_type = false;
+        if !self.form.is_empty() {
+            if !self.data.is_empty() || self.data_file.is_some() {
+                bail!("curl cannot combine -F with -d");
+            }
+            args.extend(self.form);
+            args.push("--multipart".to_string());
+            drop_content_type = true;
+        } else if let Some(path) = &self.data_file {
+            if !self.data.is_empty() {
+                bail!("Combining -d @FILE with other -d options is not supported");
+            }
+            args.extend(["--data-file".to_string(), path.clone()]);
+        } else if !self.data.is_empty() {
+            let data = self.data.join("&");
+            if self.get {
+                for (key, value) in url_encoded_pairs(&data) {
+                    args.push(format!("{}=={}", item_key(&key)?, value));
+                }
+            } else if json {
+                args.extend(json_items(&data)?);
+                drop_content_type = true;
+            } else if content_type.is_none() || content_type.is_some_and(|value| value.starts_with("application/x-www-form-urlencoded")) {
+                for (key, value) in url_encoded_pairs(&data) {
+                    args.push(data_item(&key, &value)?);
+                }
+                args.push("--form".to_string());
+                drop_content_type = true;
+            } else {
+                bail!("Cannot convert a {} body given inline; save it to a file and use --data-binary @FILE", content_type.unwrap_or_default());
+            }
+        }
+
+        for header in self.headers {
+            let is_content_type = header.split_once(':').is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"));
+            if !(drop_content_type && is_content_type) {
+                args.extend(["--header".to_string(), header]);
+            }
+        }
+        if let Some(user) = self.user {
+            args.extend(["--auth".to_string(), user]);
+            if self.digest {
+                args.extend(["--auth-type".to_string(), "digest".to_string()]);
+            }
+        }
+        // curl returns redirects unless told to follow them.
+        if !self.follow {
+            args.push("--no-follow".to_string());
+        }
+        args.extend(self.options);
+        Ok(args)
+    }
+}
+
+fn url_encoded_pairs(data: &str) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(data.as_bytes()).into_owned().collect()
+}
+
+/// Turns a JSON object body into data items: strings as `key=value`, everything else as `key:=json`.
+fn json_items(data: &str) -> Result<Vec<String>> {
+    let value: Value = serde_json::from_str(data).map_err(|e| anyhow!("Invalid JSON body in curl command: {}", e))?;
+    let Value::Object(fields) = value else {
+        bail!("Only a JSON object body can be converted; save it to a file and use --data-binary @FILE");
+    };
+    fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(text) => data_item(&key, &text),
+            other => Ok(format!("{}:={}", item_key(&key)?, other)),
+        })
+        .collect()
+}
+
+/// A `key=value` item that sends `value` as given. A value starting with `@` would make
+/// varyag upload a local file instead, so it is written as a JSON string (`key:="@..."`).
+fn data_item(key: &str, value: &str) -> Result<String> {
+    let key = item_key(key)?;
+    Ok(if value.starts_with('@') { format!("{}:={}", key, Value::from(value)) } else { format!("{}={}", key, value) })
+}
+
+/// Rejects field names containing an item separator, which would turn the item into
+/// something else, such as a file upload.
+fn item_key(key: &str) -> Result<&str> {
+    if key.contains(['=', ':', '@']) {
+        bail!("Cannot convert the field name '{}': it contains '=', ':' or '@'", key);
+    }
+    Ok(key)
+}
+
+/// Turns a `-F` part into a data item: `name=value`, `name=@path[;type=...]` for a file and `name=<path` for a file's content.
+fn form_part(part: &str) -> Result<String> {
+    let (name, value) = part.split_once('=').ok_or_else(|| anyhow!("Invalid -F part '{}': expected NAME=VALUE", part))?;
+    let name = item_key(name)?;
+    if let Some(file) = value.strip_prefix('@') {
+        Ok(format!("{}@{}", name, file))
+    } else if let Some(file) = value.strip_prefix('<') {
+        Ok(format!("{}=@{}", name, file))
+    } else {
+        data_item(name, value)
+    }
+}
+
+/// Turns a `--form-string` part, whose value curl never reads from a file, into a data item.
+fn literal_form_part(part: &str) -> Result<String> {
+    let (name, value) = part.split_once('=').ok_or_else(|| anyhow!("Invalid --form-string part '{}': expected NAME=VALUE", part))?;
+    data_item(name, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_args() {
+        let args = send_args(
+            r#"curl -sS -XPUT 'https://api.example.com/users/1' -H 'Content-Type: application/json' -H 'X-Trace: 1'
+               -d '{"name":"Varyag","admin":true}' -u alice:secret -kL"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "https://api.example.com/users/1", "PUT", "admin:=true", "name=Varyag", "--header", "X-Trace: 1", "--auth", "alice:secret",
+                "--insecure",
+            ]
+        );
+
+        let args = send_args("curl https://example.com/upload -F title=Report -F 'file=@report.pdf;type=application/pdf'").unwrap();
+        assert_eq!(args, ["https://example.com/upload", "POST", "title=Report", "file@report.pdf;type=application/pdf", "--multipart", "--no-follow"]);
+
+        let args = send_args("curl -G https://example.com/search -d 'q=a%20b' --data-binary @body.bin").unwrap_err();
+        assert_eq!(args.to_string(), "Combining -d @FILE with other -d options is not supported");
+        let args = send_args("curl -G https://example.com/search -d 'q=a%20b' -d page=2").unwrap();
+        assert_eq!(args, ["https://example.com/search", "GET", "q==a b", "page==2", "--no-follow"]);
+    }
+
+    #[test]
+    fn test_send_args_never_reads_local_files_from_values() {
+        let args = send_args("curl https://example.com/notes -d 'note=@/etc/passwd' -d 'plain=a@b'").unwrap();
+        assert_eq!(args, ["https://example.com/notes", "POST", r#"note:="@/etc/passwd""#, "plain=a@b", "--form", "--no-follow"]);
+        let args = send_args(r#"curl https://example.com/notes -H 'Content-Type: application/json' -d '{"note": "@/etc/passwd"}'"#).unwrap();
+        assert_eq!(args, ["https://example.com/notes", "POST", r#"note:="@/etc/passwd""#, "--no-follow"]);
+        assert_eq!(
+            crate::request_items::parse_request_item(&args[2]).unwrap(),
+            crate::request_items::RequestItem::Data("note".to_string(), Value::from("@/etc/passwd"))
+        );
+        assert!(send_args("curl https://example.com -d 'a@b=c'").is_err());
+        assert!(send_args(r#"curl https://example.com -H 'Content-Type: application/json' -d '{"a@b": 1}'"#).is_err());
+
+        let args = send_args("curl https://example.com/notes --form-string 'note=@/etc/passwd' -F 'title=<x>'").unwrap();
+        assert_eq!(args, ["https://example.com/notes", "POST", r#"note:="@/etc/passwd""#, "title=@x>", "--multipart", "--no-follow"]);
+        assert!(send_args("curl https://example.com -F 'a:b=c'").is_err());
+        assert!(send_args("curl https://example.com -F 'a@b=c'").is_err());
+    }
+
+    #[test]
+    fn test_send_args_rejects_what_it_cannot_translate() {
+        assert_eq!(send_args("curl --trace out.txt https://example.com").unwrap_err().to_string(), "Unsupported curl option: --trace");
+        assert!(send_args("curl -H 'Accept: */*").is_err());
+        assert!(send_args("curl -X POST").unwrap_err().to_string().contains("no URL"));
+    }
+}
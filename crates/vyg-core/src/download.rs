@@ -0,0 +1,296 @@
+use anyhow::{anyhow, Result};
+use futures_util::StreamExt;
+use percent_encoding::percent_decode_str;
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use reqwest::StatusCode;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use url::Url;
+use vyg_display::progress::TransferProgress;
+use crate::compression::ResponseEncoding;
+use crate::http_client::{execute, version_name, Exchange, HttpRequest, SentRequest};
+use crate::redirect::RedirectHop;
+use crate::timing::Timings;
+use crate::tls::TlsDetails;
+use crate::write_out::Transfer;
+
+pub struct DownloadOptions {
+    /// Target file. Derived from `Content-Disposition` or the URL when `None`.
+    pub output: Option<PathBuf>,
+    /// Continue a partial download of `output` with a `Range` request.
+    pub resume: bool,
+}
+
+pub struct DownloadResult {
+    pub version: String,
+    pub status: String,
+    pub headers: HeaderMap,
+    pub path: PathBuf,
+    /// Bytes written by this transfer.
+    pub bytes_written: u64,
+    /// Bytes that were already on disk when the transfer started.
+    pub resumed_from: u64,
+    /// The final URL, after redirects.
+    pub url: String,
+    pub remote_addr: Option<SocketAddr>,
+    /// The TLS connection the response arrived on; `None` for plain HTTP.
+    pub tls: Option<TlsDetails>,
+    pub timings: Timings,
+    pub request: SentRequest,
+    pub redirects: Vec<RedirectHop>,
+}
+
+impl DownloadResult {
+    /// The values `--write-out` templates are rendered from.
+    pub fn transfer(&self) -> Transfer<'_> {
+        Transfer {
+            version: &self.version,
+            status: &self.status,
+            url: &self.url,
+            content_type: self.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()),
+            remote_addr: self.remote_addr,
+            timings: &self.timings,
+        }
+    }
+}
+
+/// Streams a response body to disk, showing a progress bar on stderr.
+pub async fn download(mut request: HttpRequest, options: DownloadOptions) -> Result<DownloadResult> {
+    // Files are saved as served; a compressed transfer would also break byte ranges.
+    request.response_encoding = ResponseEncoding::AsIs;
+    let url = Url::parse(&request.url)?;
+
+    let mut offset = match (&options.output, options.resume) {
+        (Some(path), true) => fs::metadata(path).await.map(|m| m.len()).unwrap_or(0),
+        (None, true) => return Err(anyhow!("Resuming a download requires an output file.")),
+        _ => 0,
+    };
+
+    if offset > 0 {
+        request.headers.push(format!("{}: bytes={}-", RANGE, offset));
+    }
+    let Exchange { response, request, timing, redirects } = execute(request).await?;
+    let version = version_name(response.version());
+    let status = response.status();
+    let headers = response.headers().clone();
+    let effective_url = request.url.clone();
+    let remote_addr = response.remote_addr();
+    let tls = TlsDetails::of_response(&response);
+
+    if offset > 0 && status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file already holds the whole resource.
+        return Ok(DownloadResult {
+            version,
+            status: status.to_string(),
+            headers,
+            path: options.output.unwrap_or_default(),
+            bytes_written: 0,
+            resumed_from: offset,
+            url: effective_url,
+            remote_addr,
+            tls,
+            timings: timing.finish(0),
+            request,
+            redirects,
+        });
+    }
+    if !status.is_success() {
+        return Err(anyhow!("Download failed: server responded with {}", status));
+    }
+    if status != StatusCode::PARTIAL_CONTENT {
+        // The server ignored the range request, so start over.
+        offset = 0;
+    }
+
+    let path = match options.output {
+        Some(path) => path,
+        None => unique_path(derive_filename(&headers, &url)).await,
+    };
+
+    let total = if status == StatusCode::PARTIAL_CONTENT {
+        content_range_total(&headers).or_else(|| response.content_length().map(|len| offset + len))
+    } else {
+        response.content_length()
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(&path)
+        .await
+        .map_err(|e| anyhow!("Failed to open '{}': {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let progress = TransferProgress::new(total, offset);
+    let mut bytes_written = 0;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                progress.abandon();
+                writer.flush().await?;
+                return Err(anyhow!("Download interrupted after {} bytes: {}", offset + bytes_written, e));
+            }
+        };
+        writer.write_all(&chunk).await?;
+        bytes_written += chunk.len() as u64;
+        progress.inc(chunk.len() as u64);
+    }
+    writer.flush().await?;
+    progress.finish();
+
+    Ok(DownloadResult {
+        version,
+        status: status.to_string(),
+        headers,
+        path,
+        bytes_written,
+        resumed_from: offset,
+        url: effective_url,
+        remote_addr,
+        tls,
+        timings: timing.finish(bytes_written),
+        request,
+        redirects,
+    })
+}
+
+/// Picks a local file name from `Content-Disposition`, falling back to the last URL segment.
+fn derive_filename(headers: &HeaderMap, url: &Url) -> PathBuf {
+    let from_header = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(content_disposition_filename);
+
+    let from_url = || {
+        url.path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|segment| percent_decode_str(segment).decode_utf8_lossy().to_string())
+            .and_then(|name| sanitize_filename(&name))
+    };
+
+    let name = from_header.or_else(from_url).unwrap_or_else(|| "index".to_string());
+    let mut path = PathBuf::from(name);
+    if path.extension().is_none() {
+        let extension = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|ct| mime_guess::get_mime_extensions_str(ct.split(';').next().unwrap_or("").trim()))
+            .and_then(|extensions| extensions.first());
+        if let Some(extension) = extension {
+            path.set_extension(extension);
+        }
+    }
+    path
+}
+
+/// Extracts the file name from a `Content-Disposition` value, preferring the
+/// RFC 5987 `filename*` parameter over plain `filename`.
+fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').skip(1) {
+        let Some((key, raw)) = param.trim().split_once('=') else { continue };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "filename*" => {
+                // charset'language'percent-encoded-value
+                let encoded = raw.trim().splitn(3, '\'').nth(2)?;
+                extended = Some(percent_decode_str(encoded).decode_utf8_lossy().to_string());
+            }
+            "filename" => plain = Some(raw.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    extended.or(plain).and_then(|name| sanitize_filename(&name))
+}
+
+/// Strips any directory components so a server cannot write outside the working directory.
+fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Appends `-1`, `-2`, ... to the file stem until the path does not exist yet.
+async fn unique_path(path: PathBuf) -> PathBuf {
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return path;
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut counter = 1;
+    loop {
+        let candidate = parent.join(format!("{}-{}{}", stem, counter, extension));
+        if !fs::try_exists(&candidate).await.unwrap_or(false) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// The full resource size from `Content-Range: bytes start-end/total`.
+fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_content_disposition_filename() {
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="report.pdf""#),
+            Some("report.pdf".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="fallback.txt"; filename*=UTF-8''na%C3%AFve%20file.txt"#),
+            Some("naïve file.txt".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="../../etc/passwd""#),
+            Some("passwd".to_string())
+        );
+        assert_eq!(content_disposition_filename("inline"), None);
+    }
+
+    #[test]
+    fn test_derive_filename_from_url() {
+        let url = Url::parse("https://example.com/files/archive%202024.tar.gz?sig=abc").unwrap();
+        assert_eq!(derive_filename(&HeaderMap::new(), &url), PathBuf::from("archive 2024.tar.gz"));
+    }
+
+    #[test]
+    fn test_derive_filename_adds_extension_from_content_type() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
+        assert_eq!(derive_filename(&headers, &url), PathBuf::from("index.json"));
+    }
+
+    #[test]
+    fn test_content_range_total() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 100-199/1000"));
+        assert_eq!(content_range_total(&headers), Some(1000));
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes 100-199/*"));
+        assert_eq!(content_range_total(&headers), None);
+    }
+}
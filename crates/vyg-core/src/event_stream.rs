@@ -0,0 +1,284 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+use crate::http_client::{self, HttpRequest, Opened, StreamingResponse};
+
+/// The reconnection delay used until the server sets one with a `retry:` field.
+pub const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// A streaming body format, consumed as it arrives rather than after the response ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// `text/event-stream`, i.e. Server-Sent Events.
+    EventStream,
+    /// Newline-delimited JSON, one value per line.
+    Ndjson,
+}
+
+impl StreamFormat {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        match essence.as_str() {
+            "text/event-stream" => Some(StreamFormat::EventStream),
+            "application/x-ndjson" | "application/ndjson" | "application/jsonl" | "application/x-jsonlines" => Some(StreamFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// One dispatched Server-Sent Event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` type; `None` means the default, `message`.
+    pub event: Option<String>,
+    /// The last event ID when the event was dispatched.
+    pub id: Option<String>,
+    pub data: String,
+}
+
+impl SseEvent {
+    /// The event as a single-line JSON object, with `data` embedded as JSON when it is JSON.
+    pub fn to_json(&self) -> String {
+        let data = serde_json::from_str::<Value>(&self.data).unwrap_or_else(|_| Value::String(self.data.clone()));
+        json!({
+            "event": self.event.as_deref().unwrap_or("message"),
+            "id": self.id,
+            "data": data,
+        })
+        .to_string()
+    }
+}
+
+/// An incremental `text/event-stream` parser, following the WHATWG specification.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    line: Vec<u8>,
+    /// A CR ended the last line, so a LF at the start of the next chunk belongs to it.
+    after_cr: bool,
+    event: Option<String>,
+    data: Option<String>,
+    /// The ID set by `id:` lines, which only becomes the last event ID once an event is dispatched.
+    pending_id: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    /// Parses the next chunk of the stream, returning the events it completed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    events.extend(self.process_line(&String::from_utf8_lossy(&line)));
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+        events
+    }
+
+    /// The ID to resume from with `Last-Event-ID` after a reconnection.
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// The reconnection delay last set by the server.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Drops an event left incomplete by a closed connection, including its ID, so that
+    /// the event is sent again after a reconnection.
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.event = None;
+        self.data = None;
+        self.pending_id = self.last_event_id.clone();
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            self.last_event_id = self.pending_id.clone();
+            let event = self.event.take();
+            let data = self.data.take()?;
+            return Some(SseEvent { event, id: self.last_event_id.clone(), data });
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.pending_id = Some(value.to_string()).filter(|id| !id.is_empty()),
+            "retry" => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            // Comments (`: keep-alive`) and unknown fields are ignored.
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Splits a stream into lines as they complete, for NDJSON bodies.
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    partial: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Returns the non-empty lines completed by `chunk`.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.partial.extend_from_slice(chunk);
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+        String::from_utf8_lossy(&complete).lines().map(str::trim_end).filter(|line| !line.is_empty()).map(str::to_string).collect()
+    }
+
+    /// The last line, if the stream ended without a newline after it.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).trim_end().to_string();
+        Some(rest).filter(|rest| !rest.is_empty())
+    }
+}
+
+/// Something that happened while following a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamItem {
+    Event(SseEvent),
+    Line(String),
+    /// The event stream ended or failed; it is reopened after `delay`.
+    Reconnecting { delay: Duration, last_event_id: Option<String>, reason: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// Reopen an event stream when it ends, resuming with `Last-Event-ID`.
+    pub reconnect: bool,
+    /// The reconnection delay until the server sets one.
+    pub retry: Duration,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions { reconnect: false, retry: DEFAULT_RETRY }
+    }
+}
+
+fn status_code(status: &str) -> u16 {
+    status.split_whitespace().next().and_then(|code| code.parse().ok()).unwrap_or(0)
+}
+
+/// Reads `response` to its end, reporting events or lines to `on_item` as they arrive.
+///
+/// With `reconnect`, an event stream is reopened whenever it ends, sending `request` again
+/// with `Last-Event-ID`, until the server answers with 204 No Content or something other
+/// than an event stream.
+pub async fn follow(mut response: StreamingResponse, request: &HttpRequest, options: &StreamOptions, mut on_item: impl FnMut(StreamItem)) -> Result<()> {
+    if response.format == StreamFormat::Ndjson {
+        let mut lines = LineSplitter::default();
+        while let Some(chunk) = response.chunk().await? {
+            lines.feed(&chunk).into_iter().for_each(|line| on_item(StreamItem::Line(line)));
+        }
+        if let Some(line) = lines.finish() {
+            on_item(StreamItem::Line(line));
+        }
+        return Ok(());
+    }
+
+    let mut parser = SseParser::default();
+    loop {
+        let ended = loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => parser.feed(&chunk).into_iter().for_each(|event| on_item(StreamItem::Event(event))),
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        if !options.reconnect {
+            return ended;
+        }
+        parser.reset();
+        let mut reason = match ended {
+            Ok(()) => "The stream ended".to_string(),
+            Err(e) => format!("{:#}", e),
+        };
+        response = loop {
+            let delay = parser.retry().unwrap_or(options.retry);
+            let last_event_id = parser.last_event_id().map(str::to_string);
+            on_item(StreamItem::Reconnecting { delay, last_event_id: last_event_id.clone(), reason });
+            tokio::time::sleep(delay).await;
+
+            let mut next = request.clone();
+            if let Some(id) = last_event_id {
+                next.headers.retain(|header| !header.to_ascii_lowercase().starts_with("last-event-id:"));
+                next.headers.push(format!("Last-Event-ID: {}", id));
+            }
+            match http_client::open_request(next).await {
+                Ok(Opened::Streaming(next)) if next.format == StreamFormat::EventStream && status_code(&next.status) == 200 => break *next,
+                Ok(Opened::Complete(done)) if status_code(&done.status) == 204 => return Ok(()),
+                Ok(Opened::Streaming(other)) => return Err(anyhow!("The server answered the reconnection with {}", other.status)),
+                Ok(Opened::Complete(other)) => return Err(anyhow!("The server answered the reconnection with {}", other.status)),
+                Err(e) => reason = format!("{:#}", e),
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: Option<&str>, id: Option<&str>, data: &str) -> SseEvent {
+        SseEvent { event: event.map(str::to_string), id: id.map(str::to_string), data: data.to_string() }
+    }
+
+    #[test]
+    fn test_sse_parser_across_chunks() {
+        let mut parser = SseParser::default();
+        assert_eq!(parser.feed(b": keep-alive\n\ndata: {\"n\": 1}\r"), vec![]);
+        assert_eq!(parser.feed(b"\nid: 1\r\n\r\nevent: update\ndata:first\ndata: second\n"), vec![event(None, Some("1"), r#"{"n": 1}"#)]);
+        assert_eq!(parser.feed(b"\nretry: 1500\ndata\n\n"), vec![event(Some("update"), Some("1"), "first\nsecond"), event(None, Some("1"), "")]);
+        assert_eq!((parser.last_event_id(), parser.retry()), (Some("1"), Some(Duration::from_millis(1500))));
+
+        // An event cut off by a closed connection is dropped with its ID, so it is resumed from the one before.
+        parser.feed(b"id: 2\ndata: partial");
+        assert_eq!(parser.last_event_id(), Some("1"));
+        parser.reset();
+        assert_eq!(parser.last_event_id(), Some("1"));
+        assert_eq!(parser.feed(b"data: next\n\n"), vec![event(None, Some("1"), "next")]);
+    }
+
+    #[test]
+    fn test_event_json_and_line_splitter() {
+        assert_eq!(event(None, Some("7"), r#"{"n":1}"#).to_json(), r#"{"data":{"n":1},"event":"message","id":"7"}"#);
+        assert_eq!(event(Some("ping"), None, "hi").to_json(), r#"{"data":"hi","event":"ping","id":null}"#);
+
+        let mut lines = LineSplitter::default();
+        assert_eq!(lines.feed(b"{\"a\":1}\r\n{\"a\""), [r#"{"a":1}"#]);
+        assert_eq!(lines.feed(b":2}\n\n{\"a\":3}"), [r#"{"a":2}"#]);
+        assert_eq!(lines.finish().as_deref(), Some(r#"{"a":3}"#));
+        assert_eq!(StreamFormat::from_content_type("text/event-stream; charset=utf-8"), Some(StreamFormat::EventStream));
+        assert_eq!(StreamFormat::from_content_type("application/x-ndjson"), Some(StreamFormat::Ndjson));
+        assert_eq!(StreamFormat::from_content_type("application/json"), None);
+    }
+}
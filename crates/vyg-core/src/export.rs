@@ -0,0 +1,543 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use crate::auth::Auth;
+use crate::compression::{self, ResponseEncoding};
+use crate::http_client::{self, HttpRequest, HttpVersion, SentRequest};
+use crate::proxy::ProxyKind;
+use crate::request_items::{parse_request_items, RequestItem};
+use crate::tls::TlsVersion;
+
+/// Headers every tool derives from the request itself.
+const DERIVED_HEADERS: &[&str] = &["host", "content-length"];
+
+/// A command line tool a request can be exported for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Curl,
+    Wget,
+    Httpie,
+}
+
+impl ExportFormat {
+    fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Curl => "curl",
+            ExportFormat::Wget => "wget",
+            ExportFormat::Httpie => "HTTPie",
+        }
+    }
+}
+
+/// The body of an exported request.
+enum Body {
+    None,
+    Text(String),
+    File(PathBuf),
+    Multipart(Vec<Part>),
+}
+
+enum Part {
+    Field(String, String),
+    File { field: String, path: PathBuf, mime: Option<String> },
+}
+
+/// Renders `request`, with everything varyag would add to it, as an equivalent command for `format`.
+///
+/// Fails for settings the tool has no option for, rather than exporting a different request.
+pub async fn export(request: HttpRequest, format: ExportFormat) -> Result<String> {
+    if request.compress.is_some() {
+        bail!("{} cannot compress request bodies; export without --compress.", format.name());
+    }
+    let sent = http_client::dry_run(request.clone()).await?;
+    let body = body(&request, &sent)?;
+    let command = match format {
+        ExportFormat::Curl => curl(&request, &sent, body)?,
+        ExportFormat::Wget => wget(&request, &sent, body)?,
+        ExportFormat::Httpie => httpie(&request, &sent, body)?,
+    };
+    Ok(command.render())
+}
+
+fn body(request: &HttpRequest, sent: &SentRequest) -> Result<Body> {
+    if let Some(path) = &request.data_file {
+        return Ok(Body::File(path.clone()));
+    }
+    if let Some(bytes) = sent.body.as_deref().filter(|bytes| !bytes.is_empty()) {
+        let text = String::from_utf8(bytes.to_vec())
+            .map_err(|_| anyhow!("The request body is binary and cannot be exported inline; send it with --data-file."))?;
+        return Ok(Body::Text(text));
+    }
+    if !is_multipart(sent) {
+        return Ok(Body::None);
+    }
+    let parts = parse_request_items(&request.body)?
+        .into_iter()
+        .filter_map(|item| match item {
+            RequestItem::Data(field, Value::String(value)) => Some(Part::Field(field, value)),
+            RequestItem::Data(field, value) => Some(Part::Field(field, value.to_string())),
+            RequestItem::File { field, path, mime } => Some(Part::File { field, path, mime }),
+            RequestItem::Query(..) | RequestItem::Header(..) => None,
+        })
+        .collect();
+    Ok(Body::Multipart(parts))
+}
+
+fn is_multipart(sent: &SentRequest) -> bool {
+    header(sent, "content-type").is_some_and(|value| value.starts_with("multipart/"))
+}
+
+fn header<'a>(sent: &'a SentRequest, name: &str) -> Option<&'a str> {
+    sent.headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// The headers to pass explicitly, leaving out those the tool derives or sets through other options.
+fn headers(request: &HttpRequest, sent: &SentRequest, body: &Body, mut skip: Vec<&'static str>) -> Vec<(String, String)> {
+    skip.extend_from_slice(DERIVED_HEADERS);
+    if matches!(request.auth, Some(Auth::Basic { .. })) {
+        skip.push("authorization");
+    }
+    if matches!(body, Body::Multipart(_)) {
+        // The tool picks its own boundary.
+        skip.push("content-type");
+    }
+    http_client::header_pairs(&sent.headers)
+        .into_iter()
+        .filter(|(name, _)| !skip.contains(&name.as_str()))
+        .collect()
+}
+
+/// Whether the `Accept-Encoding` header is the one varyag adds to have responses decoded.
+fn decodes_responses(request: &HttpRequest, sent: &SentRequest) -> bool {
+    request.response_encoding == ResponseEncoding::Decode && header(sent, "accept-encoding") == Some(compression::ACCEPT_ENCODING)
+}
+
+fn unsupported(format: ExportFormat, what: &str) -> anyhow::Error {
+    anyhow!("{} has no equivalent for {}.", format.name(), what)
+}
+
+fn curl(request: &HttpRequest, sent: &SentRequest, body: Body) -> Result<CommandLine> {
+    let transport = &request.transport;
+    let mut command = CommandLine::new("curl");
+    let implied_method = if matches!(body, Body::None) { "GET" } else { "POST" };
+    if sent.method != implied_method {
+        command.option("-X", &sent.method);
+    }
+    command.arg(&sent.url);
+
+    let mut skip = Vec::new();
+    if decodes_responses(request, sent) {
+        skip.push("accept-encoding");
+        command.arg("--compressed");
+    }
+    for (name, value) in headers(request, sent, &body, skip) {
+        command.option("-H", format!("{}: {}", name, value));
+    }
+    match &request.auth {
+        Some(Auth::Basic { username, password }) => command.option("-u", format!("{}:{}", username, password)),
+        Some(Auth::Digest { username, password }) => {
+            command.arg("--digest");
+            command.option("-u", format!("{}:{}", username, password));
+        }
+        Some(Auth::Bearer(_)) | None => {}
+    }
+    match body {
+        Body::None => {}
+        // Unlike --data-binary and -F, these never read a file named by a leading `@` or `<`.
+        Body::Text(text) => command.option("--data-raw", text),
+        Body::File(path) => command.option("--data-binary", format!("@{}", path.display())),
+        Body::Multipart(parts) => {
+            for part in parts {
+                match part {
+                    Part::Field(field, value) => command.option("--form-string", format!("{}={}", field, value)),
+                    Part::File { field, path, mime } => {
+                        let mime = mime.map(|mime| format!(";type={}", mime)).unwrap_or_default();
+                        command.option("-F", format!("{}=@{}{}", field, path.display(), mime));
+                    }
+                }
+            }
+        }
+    }
+
+    if request.redirect.follow {
+        command.arg("-L");
+        command.option("--max-redirs", request.redirect.max.to_string());
+        if request.redirect.preserve_method {
+            command.args(["--post301", "--post302", "--post303"]);
+        }
+    }
+    match request.http_version {
+        HttpVersion::Negotiate => {}
+        HttpVersion::Http1_1 => command.arg("--http1.1"),
+        HttpVersion::Http2 => command.arg("--http2"),
+        HttpVersion::Http2PriorKnowledge => command.arg("--http2-prior-knowledge"),
+    }
+    if let Some(timeout) = transport.timeout {
+        command.option("--max-time", timeout.as_secs_f64().to_string());
+    }
+    if let Some(timeout) = transport.connect_timeout {
+        command.option("--connect-timeout", timeout.as_secs_f64().to_string());
+    }
+    if transport.retry.retries > 0 {
+        command.option("--retry", transport.retry.retries.to_string());
+    }
+
+    let tls = &transport.tls;
+    if tls.insecure {
+        command.arg("-k");
+    }
+    if let Some(path) = &tls.ca_cert {
+        command.option("--cacert", path.display().to_string());
+    }
+    if let Some(path) = &tls.client_cert {
+        command.option("--cert", path.display().to_string());
+    }
+    if let Some(path) = &tls.client_key {
+        command.option("--key", path.display().to_string());
+    }
+    if let Some(version) = tls.min_version {
+        command.arg(format!("--tlsv{}", version));
+    }
+    if !tls.pins.is_empty() {
+        let pins: Vec<String> = tls.pins.iter().map(|pin| format!("sha256//{}", pin)).collect();
+        command.option("--pinnedpubkey", pins.join(";"));
+    }
+
+    let resolve = &transport.resolve;
+    for entry in &resolve.overrides {
+        command.option("--resolve", entry.to_string());
+    }
+    for rule in &resolve.connect_to {
+        command.option("--connect-to", rule.to_string());
+    }
+    if let Some(interface) = &resolve.interface {
+        command.option("--interface", interface);
+    }
+    if let Some(address) = resolve.local_address {
+        command.option("--interface", address.to_string());
+    }
+    match resolve.family {
+        Some(crate::resolve::IpFamily::V4) => command.arg("-4"),
+        Some(crate::resolve::IpFamily::V6) => command.arg("-6"),
+        None => {}
+    }
+    if let Some(proxy) = &transport.proxy {
+        command.option("-x", proxy.url()?.to_string());
+    } else if request.noproxy {
+        command.option("--noproxy", "*");
+    }
+    if let Some(socket) = &transport.unix_socket {
+        command.option("--unix-socket", socket.display().to_string());
+    }
+    Ok(command)
+}
+
+fn wget(request: &HttpRequest, sent: &SentRequest, body: Body) -> Result<CommandLine> {
+    let format = ExportFormat::Wget;
+    let transport = &request.transport;
+    let resolve = &transport.resolve;
+    if transport.unix_socket.is_some() {
+        return Err(unsupported(format, "Unix domain sockets"));
+    }
+    if !resolve.overrides.is_empty() || !resolve.connect_to.is_empty() {
+        return Err(unsupported(format, "--resolve and --connect-to"));
+    }
+    if request.http_version != HttpVersion::Negotiate && request.http_version != HttpVersion::Http1_1 {
+        return Err(unsupported(format, "HTTP/2"));
+    }
+    if !transport.tls.pins.is_empty() {
+        return Err(unsupported(format, "certificate pinning"));
+    }
+    if matches!(request.auth, Some(Auth::Digest { .. })) {
+        return Err(unsupported(format, "forcing Digest authentication"));
+    }
+
+    // Print the response body like the other tools, instead of saving it to a file.
+    let mut command = CommandLine::new("wget");
+    command.args(["-q", "-O", "-"]);
+    if sent.method != "GET" {
+        command.arg(format!("--method={}", sent.method));
+    }
+    let mut skip = Vec::new();
+    if decodes_responses(request, sent) {
+        skip.push("accept-encoding");
+        command.arg("--compression=auto");
+    }
+    for (name, value) in headers(request, sent, &body, skip) {
+        command.arg(format!("--header={}: {}", name, value));
+    }
+    if let Some(Auth::Basic { username, password }) = &request.auth {
+        command.arg(format!("--user={}", username));
+        command.arg(format!("--password={}", password));
+        command.arg("--auth-no-challenge");
+    }
+    match body {
+        Body::None => {}
+        Body::Text(text) => command.arg(format!("--body-data={}", text)),
+        Body::File(path) => command.arg(format!("--body-file={}", path.display())),
+        Body::Multipart(_) => return Err(unsupported(format, "multipart form uploads")),
+    }
+
+    command.arg(format!("--max-redirect={}", if request.redirect.follow { request.redirect.max } else { 0 }));
+    if let Some(timeout) = transport.timeout {
+        command.arg(format!("--read-timeout={}", timeout.as_secs_f64()));
+    }
+    if let Some(timeout) = transport.connect_timeout {
+        command.arg(format!("--connect-timeout={}", timeout.as_secs_f64()));
+    }
+    if transport.retry.retries > 0 {
+        command.arg(format!("--tries={}", transport.retry.retries + 1));
+    }
+
+    let tls = &transport.tls;
+    if tls.insecure {
+        command.arg("--no-check-certificate");
+    }
+    if let Some(path) = &tls.ca_cert {
+        command.arg(format!("--ca-certificate={}", path.display()));
+    }
+    if let Some(path) = &tls.client_cert {
+        command.arg(format!("--certificate={}", path.display()));
+    }
+    if let Some(path) = &tls.client_key {
+        command.arg(format!("--private-key={}", path.display()));
+    }
+    match tls.min_version {
+        None => {}
+        Some(TlsVersion::Tls1_2) => command.arg("--secure-protocol=TLSv1_2"),
+        Some(TlsVersion::Tls1_3) => command.arg("--secure-protocol=TLSv1_3"),
+        Some(_) => return Err(unsupported(format, "a minimum TLS version below 1.2")),
+    }
+
+    if resolve.interface.is_some() {
+        return Err(unsupported(format, "--interface"));
+    }
+    if let Some(address) = resolve.local_address {
+        command.arg(format!("--bind-address={}", address));
+    }
+    match resolve.family {
+        Some(crate::resolve::IpFamily::V4) => command.arg("-4"),
+        Some(crate::resolve::IpFamily::V6) => command.arg("-6"),
+        None => {}
+    }
+    if let Some(proxy) = &transport.proxy {
+        if proxy.kind != ProxyKind::Http {
+            return Err(unsupported(format, "SOCKS5 proxies"));
+        }
+        let url = proxy.url()?;
+        command.args(["-e".to_string(), "use_proxy=yes".to_string()]);
+        command.args(["-e".to_string(), format!("http_proxy={}", url)]);
+        command.args(["-e".to_string(), format!("https_proxy={}", url)]);
+    } else if request.noproxy {
+        command.arg("--no-proxy");
+    }
+    command.arg(&sent.url);
+    Ok(command)
+}
+
+fn httpie(request: &HttpRequest, sent: &SentRequest, body: Body) -> Result<CommandLine> {
+    let format = ExportFormat::Httpie;
+    let transport = &request.transport;
+    let resolve = &transport.resolve;
+    if transport.unix_socket.is_some() {
+        return Err(unsupported(format, "Unix domain sockets"));
+    }
+    if !resolve.overrides.is_empty() || !resolve.connect_to.is_empty() {
+        return Err(unsupported(format, "--resolve and --connect-to"));
+    }
+    if resolve.interface.is_some() || resolve.local_address.is_some() || resolve.family.is_some() {
+        return Err(unsupported(format, "choosing the interface, source address or IP version"));
+    }
+    if request.http_version != HttpVersion::Negotiate && request.http_version != HttpVersion::Http1_1 {
+        return Err(unsupported(format, "HTTP/2"));
+    }
+    if !transport.tls.pins.is_empty() {
+        return Err(unsupported(format, "certificate pinning"));
+    }
+
+    let mut command = CommandLine::new("http");
+    if let Body::Multipart(_) = body {
+        command.arg("--multipart");
+    }
+    if request.redirect.follow {
+        command.arg("--follow");
+        command.arg(format!("--max-redirects={}", request.redirect.max));
+    }
+    if let Some(timeout) = transport.timeout {
+        command.arg(format!("--timeout={}", timeout.as_secs_f64()));
+    }
+    match &request.auth {
+        Some(Auth::Basic { username, password }) => command.arg(format!("--auth={}:{}", username, password)),
+        Some(Auth::Digest { username, password }) => {
+            command.arg(format!("--auth={}:{}", username, password));
+            command.arg("--auth-type=digest");
+        }
+        Some(Auth::Bearer(_)) | None => {}
+    }
+    let tls = &transport.tls;
+    match (&tls.ca_cert, tls.insecure) {
+        (_, true) => command.arg("--verify=no"),
+        (Some(path), false) => command.arg(format!("--verify={}", path.display())),
+        (None, false) => {}
+    }
+    if let Some(path) = &tls.client_cert {
+        command.arg(format!("--cert={}", path.display()));
+    }
+    if let Some(path) = &tls.client_key {
+        command.arg(format!("--cert-key={}", path.display()));
+    }
+    match tls.min_version {
+        None => {}
+        Some(TlsVersion::Tls1_2) => command.arg("--ssl=tls1.2"),
+        Some(TlsVersion::Tls1_3) => command.arg("--ssl=tls1.3"),
+        Some(_) => return Err(unsupported(format, "a minimum TLS version below 1.2")),
+    }
+    if let Some(proxy) = &transport.proxy {
+        let url = proxy.url()?;
+        command.arg(format!("--proxy=http:{}", url));
+        command.arg(format!("--proxy=https:{}", url));
+    }
+    if let Body::Text(text) = &body {
+        command.arg(format!("--raw={}", text));
+    }
+
+    command.arg(&sent.method);
+    command.arg(&sent.url);
+    // HTTPie decodes compressed responses by default.
+    let mut skip = Vec::new();
+    if decodes_responses(request, sent) {
+        skip.push("accept-encoding");
+    }
+    for (name, value) in headers(request, sent, &body, skip) {
+        command.arg(format!("{}:{}", name, value));
+    }
+    match body {
+        Body::None | Body::Text(_) => {}
+        Body::File(path) => command.arg(format!("@{}", path.display())),
+        Body::Multipart(parts) => {
+            for part in parts {
+                match part {
+                    Part::Field(field, value) => command.arg(httpie_field(&field, &value)?),
+                    Part::File { field, path, mime } => {
+                        let mime = mime.map(|mime| format!(";type={}", mime)).unwrap_or_default();
+                        command.arg(format!("{}@{}{}", field, path.display(), mime));
+                    }
+                }
+            }
+        }
+    }
+    Ok(command)
+}
+
+/// An HTTPie `field=value` item. A value starting with `@` becomes a JSON string item
+/// instead, since `field=@value` would read the file `value`.
+fn httpie_field(field: &str, value: &str) -> Result<String> {
+    if value.starts_with('@') {
+        Ok(format!("{}:={}", field, serde_json::to_string(value)?))
+    } else {
+        Ok(format!("{}={}", field, value))
+    }
+}
+
+/// A command being assembled, rendered as one line with shell quoting.
+struct CommandLine(Vec<String>);
+
+impl CommandLine {
+    fn new(program: &str) -> Self {
+        CommandLine(vec![program.to_string()])
+    }
+
+    fn arg(&mut self, arg: impl Into<String>) {
+        self.0.push(arg.into());
+    }
+
+    fn args<S: Into<String>>(&mut self, args: impl IntoIterator<Item = S>) {
+        self.0.extend(args.into_iter().map(Into::into));
+    }
+
+    fn option(&mut self, flag: &str, value: impl Into<String>) {
+        self.0.push(flag.to_string());
+        self.0.push(value.into());
+    }
+
+    fn render(&self) -> String {
+        self.0.iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Quotes `arg` for POSIX shells, unless it is made only of characters no shell treats specially.
+pub fn quote(arg: &str) -> Cow<'_, str> {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::BodyMode;
+    use crate::transport::TransportOptions;
+    use std::time::Duration;
+
+    fn request() -> HttpRequest {
+        HttpRequest {
+            url: "https://api.example.com/users".to_string(),
+            method: "POST".to_string(),
+            headers: vec!["X-Trace: a b".to_string()],
+            body: vec!["name=Varyag".to_string(), "page==2".to_string()],
+            auth: Some(Auth::Basic { username: "alice".to_string(), password: "secret".to_string() }),
+            response_encoding: ResponseEncoding::Decode,
+            transport: TransportOptions { timeout: Some(Duration::from_secs(5)), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_curl() {
+        let command = export(request(), ExportFormat::Curl).await.unwrap();
+        assert_eq!(
+            command,
+            "curl 'https://api.example.com/users?page=2' --compressed -H 'x-trace: a b' -H 'content-type: application/json' \
+             -H 'user-agent: Varyag/0.1.0' -H 'accept: */*' -u alice:secret --data-raw '{\"name\":\"Varyag\"}' \
+             -L --max-redirs 10 --max-time 5"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_wget_and_httpie() {
+        let command = export(request(), ExportFormat::Wget).await.unwrap();
+        assert!(command.starts_with("wget -q -O - --method=POST --compression=auto '--header=x-trace: a b'"), "{}", command);
+        assert!(command.ends_with("--max-redirect=10 --read-timeout=5 'https://api.example.com/users?page=2'"), "{}", command);
+
+        let command = export(request(), ExportFormat::Httpie).await.unwrap();
+        assert!(command.starts_with("http --follow --max-redirects=10 --timeout=5 --auth=alice:secret '--raw={\"name\":\"Varyag\"}' POST"), "{}", command);
+
+        let multipart = HttpRequest { body_mode: BodyMode::Multipart, ..request() };
+        let error = export(multipart, ExportFormat::Wget).await.unwrap_err();
+        assert_eq!(error.to_string(), "wget has no equivalent for multipart form uploads.");
+
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[tokio::test]
+    async fn test_export_never_reads_local_files_from_values() {
+        let text = HttpRequest { body: Vec::new(), raw_body: Some(b"@/etc/passwd".to_vec()), ..request() };
+        let command = export(text, ExportFormat::Curl).await.unwrap();
+        assert!(command.contains(" --data-raw @/etc/passwd "), "{}", command);
+
+        let multipart = HttpRequest {
+            body: vec!["note:=\"@/etc/passwd\"".to_string(), "quote:=\"<motd\"".to_string()],
+            body_mode: BodyMode::Multipart,
+            ..request()
+        };
+        let command = export(multipart.clone(), ExportFormat::Curl).await.unwrap();
+        assert!(command.contains(" --form-string note=@/etc/passwd --form-string 'quote=<motd' "), "{}", command);
+
+        let command = export(multipart, ExportFormat::Httpie).await.unwrap();
+        assert!(command.contains(" 'note:=\"@/etc/passwd\"' 'quote=<motd'"), "{}", command);
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use crate::json_path::JsonPath;
+
+/// A response filter: a JSONPath (`$.items[*].id`) or a jq-style pipeline (`.items[] | .id`).
+///
+/// The jq dialect covers paths (`.a.b`, `."a-b"`, `.[0]`, `.[-1]`, `.[1:3]`, `.[]`), optional
+/// access with `?`, pipes, and the `length` and `keys` built-ins.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    kind: Kind,
+    raw_output: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    JsonPath(JsonPath),
+    Pipeline(Vec<Term>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Path(Vec<(Segment, bool)>),
+    Length,
+    Keys,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Iterate,
+}
+
+impl Filter {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let kind = if expression.starts_with('$') {
+            Kind::JsonPath(JsonPath::parse(expression)?)
+        } else {
+            Kind::Pipeline(split_pipeline(expression).iter().map(|term| parse_term(term.trim())).collect::<Result<_>>()?)
+        };
+        Ok(Filter { kind, raw_output: false })
+    }
+
+    /// Renders string results without quotes, for scripts.
+    pub fn with_raw_output(self, raw_output: bool) -> Self {
+        Filter { raw_output, ..self }
+    }
+
+    pub fn apply(&self, input: &Value) -> Result<Vec<Value>> {
+        match &self.kind {
+            Kind::JsonPath(path) => Ok(path.query(input).into_iter().cloned().collect()),
+            Kind::Pipeline(terms) => terms.iter().try_fold(vec![input.clone()], |values, term| {
+                values.iter().map(|value| apply_term(term, value)).collect::<Result<Vec<_>>>().map(|results| results.concat())
+            }),
+        }
+    }
+
+    /// Applies the filter to a JSON document, rendering each result as pretty JSON, or
+    /// as plain text for strings when raw output is on.
+    pub fn render(&self, document: &[u8]) -> Result<Vec<String>> {
+        let input: Value = serde_json::from_slice(document).map_err(|e| anyhow!("Cannot filter a body that is not JSON: {}", e))?;
+        Ok(self
+            .apply(&input)?
+            .iter()
+            .map(|value| match value {
+                Value::String(text) if self.raw_output => text.clone(),
+                other => serde_json::to_string_pretty(other).expect("JSON values serialize"),
+            })
+            .collect())
+    }
+}
+
+/// Splits on `|` outside of quoted keys.
+fn split_pipeline(expression: &str) -> Vec<String> {
+    let mut terms = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in expression.chars() {
+        match c {
+            '|' if !quoted => terms.push(String::new()),
+            _ => {
+                if c == '"' && !(quoted && escaped) {
+                    quoted = !quoted;
+                }
+                escaped = quoted && !escaped && c == '\\';
+                terms.last_mut().expect("never empty").push(c);
+            }
+        }
+    }
+    terms
+}
+
+fn parse_term(term: &str) -> Result<Term> {
+    match term {
+        "length" => return Ok(Term::Length),
+        "keys" => return Ok(Term::Keys),
+        "" => bail!("Empty filter expression"),
+        _ if !term.starts_with('.') => bail!("Unsupported filter '{}': expected a path such as .items[] or a JSONPath", term),
+        _ => {}
+    }
+
+    let chars: Vec<char> = term.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let segment = match chars[i] {
+            '.' if chars.get(i + 1) == Some(&'"') => {
+                let (key, end) = quoted_key(&chars, i + 1, term)?;
+                i = end;
+                Segment::Key(key)
+            }
+            '.' if chars.get(i + 1).is_some_and(|&c| c.is_alphanumeric() || c == '_') => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                Segment::Key(chars[start..i].iter().collect())
+            }
+            '.' if chars.get(i + 1) == Some(&'[') || i + 1 == chars.len() => {
+                i += 1;
+                continue;
+            }
+            '[' => {
+                let close = chars[i..].iter().position(|&c| c == ']').map(|offset| i + offset).ok_or_else(|| anyhow!("Unclosed '[' in filter '{}'", term))?;
+                let inside: String = chars[i + 1..close].iter().collect();
+                i = close + 1;
+                parse_brackets(inside.trim(), term)?
+            }
+            _ => bail!("Unexpected '{}' in filter '{}'", chars[i], term),
+        };
+        let optional = chars.get(i) == Some(&'?');
+        if optional {
+            i += 1;
+        }
+        segments.push((segment, optional));
+    }
+    Ok(Term::Path(segments))
+}
+
+/// Reads a `"..."` key starting at `start`, returning it and the index after the closing quote.
+fn quoted_key(chars: &[char], start: usize, term: &str) -> Result<(String, usize)> {
+    let mut key = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                key.push(chars[i + 1]);
+                i += 2;
+            }
+            '"' => return Ok((key, i + 1)),
+            c => {
+                key.push(c);
+                i += 1;
+            }
+        }
+    }
+    bail!("Unclosed quote in filter '{}'", term)
+}
+
+fn parse_brackets(inside: &str, term: &str) -> Result<Segment> {
+    let number = |text: &str| -> Result<Option<i64>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        text.parse().map(Some).map_err(|_| anyhow!("Invalid index '{}' in filter '{}'", text, term))
+    };
+    if inside.is_empty() {
+        return Ok(Segment::Iterate);
+    }
+    if let Some(key) = inside.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        return Ok(Segment::Key(key.replace("\\\"", "\"")));
+    }
+    match inside.split_once(':') {
+        Some((start, end)) => Ok(Segment::Slice(number(start)?, number(end)?)),
+        None => Ok(Segment::Index(number(inside)?.expect("not empty"))),
+    }
+}
+
+fn apply_term(term: &Term, input: &Value) -> Result<Vec<Value>> {
+    match term {
+        Term::Length => Ok(vec![match input {
+            Value::Null => Value::from(0),
+            Value::Number(number) if number.is_u64() => Value::Number(number.clone()),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Value::from(integer.unsigned_abs()),
+                None => Value::from(number.as_f64().unwrap_or_default().abs()),
+            },
+            Value::String(text) => Value::from(text.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(fields) => Value::from(fields.len()),
+            Value::Bool(_) => bail!("boolean has no length"),
+        }]),
+        Term::Keys => Ok(vec![match input {
+            Value::Object(fields) => Value::from(fields.keys().cloned().collect::<Vec<_>>()),
+            Value::Array(items) => Value::from((0..items.len()).collect::<Vec<_>>()),
+            other => bail!("{} has no keys", type_name(other)),
+        }]),
+        Term::Path(segments) => segments.iter().try_fold(vec![input.clone()], |values, (segment, optional)| {
+            let mut results = Vec::new();
+            for value in &values {
+                match apply_segment(segment, value) {
+                    Ok(found) => results.extend(found),
+                    Err(_) if *optional => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(results)
+        }),
+    }
+}
+
+fn apply_segment(segment: &Segment, input: &Value) -> Result<Vec<Value>> {
+    Ok(match (segment, input) {
+        (Segment::Iterate, Value::Array(items)) => items.clone(),
+        (Segment::Iterate, Value::Object(fields)) => fields.values().cloned().collect(),
+        (Segment::Iterate, other) => bail!("Cannot iterate over {}", type_name(other)),
+        (_, Value::Null) => vec![Value::Null],
+        (Segment::Key(key), Value::Object(fields)) => vec![fields.get(key).cloned().unwrap_or(Value::Null)],
+        (Segment::Key(key), other) => bail!("Cannot index {} with \"{}\"", type_name(other), key),
+        (Segment::Index(index), Value::Array(items)) => {
+            let position = if *index < 0 { items.len() as i64 + index } else { *index };
+            vec![usize::try_from(position).ok().and_then(|position| items.get(position)).cloned().unwrap_or(Value::Null)]
+        }
+        (Segment::Index(_), other) => bail!("Cannot index {} with a number", type_name(other)),
+        (Segment::Slice(start, end), Value::Array(items)) => {
+            let (start, end) = slice_bounds(*start, *end, items.len());
+            vec![Value::from(items[start..end].to_vec())]
+        }
+        (Segment::Slice(start, end), Value::String(text)) => {
+            let chars: Vec<char> = text.chars().collect();
+            let (start, end) = slice_bounds(*start, *end, chars.len());
+            vec![Value::from(chars[start..end].iter().collect::<String>())]
+        }
+        (Segment::Slice(..), other) => bail!("Cannot slice {}", type_name(other)),
+    })
+}
+
+fn slice_bounds(start: Option<i64>, end: Option<i64>, len: usize) -> (usize, usize) {
+    let resolve = |bound: i64| (if bound < 0 { len as i64 + bound } else { bound }).clamp(0, len as i64) as usize;
+    let start = start.map(resolve).unwrap_or(0);
+    let end = end.map(resolve).unwrap_or(len);
+    (start, end.max(start))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(expression: &str, input: &Value) -> Result<Vec<Value>> {
+        Filter::parse(expression)?.apply(input)
+    }
+
+    #[test]
+    fn test_jq_paths_and_pipes() {
+        let doc = json!({"items": [{"id": 1, "tags": ["a", "b"]}, {"id": 2, "tags": []}], "a-b": {"c|d": true}, "name": "varyag"});
+        assert_eq!(run(".items[] | .id", &doc).unwrap(), [json!(1), json!(2)]);
+        assert_eq!(run(".items[0].tags[-1]", &doc).unwrap(), [json!("b")]);
+        assert_eq!(run(".items[1:]", &doc).unwrap(), [json!([{"id": 2, "tags": []}])]);
+        assert_eq!(run(r#"."a-b"["c|d"]"#, &doc).unwrap(), [json!(true)]);
+        assert_eq!(run(".name[0:3]", &doc).unwrap(), [json!("var")]);
+        assert_eq!(run(".items | length", &doc).unwrap(), [json!(2)]);
+        assert_eq!(run("length", &json!(-5)).unwrap(), [json!(5)]);
+        assert_eq!(run("length", &json!(-2.5)).unwrap(), [json!(2.5)]);
+        assert_eq!(run(".items[0] | keys", &doc).unwrap(), [json!(["id", "tags"])]);
+        assert_eq!(run(".missing.deeper", &doc).unwrap(), [Value::Null]);
+        assert_eq!(run(".", &doc).unwrap(), vec![doc.clone()]);
+        assert_eq!(run("$.items[*].id", &doc).unwrap(), [json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn test_errors_and_optional_access() {
+        let doc = json!({"name": "varyag", "items": [1, {"id": 2}]});
+        assert_eq!(run(".name.first", &doc).unwrap_err().to_string(), r#"Cannot index string with "first""#);
+        assert_eq!(run(".name[]", &doc).unwrap_err().to_string(), "Cannot iterate over string");
+        assert_eq!(run(".items[] | .id?", &doc).unwrap(), [json!(2)]);
+        assert!(Filter::parse("map(.id)").is_err());
+        assert!(Filter::parse(".items[").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let body = br#"{"user": {"name": "Ada", "id": 7}}"#;
+        let filter = Filter::parse(".user.name").unwrap();
+        assert_eq!(filter.render(body).unwrap(), [r#""Ada""#]);
+        assert_eq!(filter.with_raw_output(true).render(body).unwrap(), ["Ada"]);
+        assert_eq!(Filter::parse(".user").unwrap().render(body).unwrap(), ["{\n  \"id\": 7,\n  \"name\": \"Ada\"\n}"]);
+        assert!(Filter::parse(".user").unwrap().render(b"not json").is_err());
+    }
+}
@@ -0,0 +1,466 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+use url::Url;
+use crate::http_client::{BodyMode, HttpRequest};
+use crate::request_items::{build_json_body, is_body_item, parse_request_items, RequestItem};
+
+/// The query sent by `--introspect`, asking for everything needed to print the schema.
+pub const INTROSPECTION_QUERY: &str = r#"query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+    directives {
+      name
+      description
+      isRepeatable
+      locations
+      args { ...InputValue }
+    }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+    isDeprecated
+    deprecationReason
+  }
+  inputFields { ...InputValue }
+  interfaces { ...TypeRef }
+  enumValues(includeDeprecated: true) {
+    name
+    description
+    isDeprecated
+    deprecationReason
+  }
+  possibleTypes { ...TypeRef }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } } } }
+}
+"#;
+
+const BUILT_IN_SCALARS: [&str; 5] = ["String", "Int", "Float", "Boolean", "ID"];
+const BUILT_IN_DIRECTIVES: [&str; 5] = ["include", "skip", "deprecated", "specifiedBy", "oneOf"];
+
+/// A GraphQL operation: the query document, its variables and the operation to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphqlRequest {
+    pub query: String,
+    pub variables: Map<String, Value>,
+    pub operation_name: Option<String>,
+}
+
+impl GraphqlRequest {
+    /// The `--introspect` operation. Without `is_repeatable` it leaves that field out, for
+    /// servers predating the October 2021 spec that reject it.
+    pub fn introspection(is_repeatable: bool) -> Self {
+        let query = if is_repeatable { INTROSPECTION_QUERY.to_string() } else { INTROSPECTION_QUERY.replace("      isRepeatable\n", "") };
+        GraphqlRequest { query, variables: Map::new(), operation_name: Some("IntrospectionQuery".to_string()) }
+    }
+
+    /// Reads the query from a `.graphql`/`.gql` file, or takes `source` as the query itself.
+    pub fn load_query(source: &str) -> Result<String> {
+        let path = Path::new(source);
+        let is_file = matches!(path.extension().and_then(|e| e.to_str()), Some("graphql" | "gql"));
+        if !is_file {
+            return Ok(source.to_string());
+        }
+        fs::read_to_string(path).map_err(|e| anyhow!("Failed to read query file '{}': {}", path.display(), e))
+    }
+
+    /// The `{"query", "variables", "operationName"}` envelope sent in a POST body.
+    pub fn envelope(&self) -> Value {
+        let mut envelope = json!({ "query": self.query });
+        if !self.variables.is_empty() {
+            envelope["variables"] = Value::Object(self.variables.clone());
+        }
+        if let Some(name) = &self.operation_name {
+            envelope["operationName"] = Value::String(name.clone());
+        }
+        envelope
+    }
+
+    /// Turns `request` into this operation. Body items of the request become the variables;
+    /// query and header items are kept. A GET request carries the operation in its URL.
+    pub fn apply(mut self, request: &mut HttpRequest) -> Result<()> {
+        let items = parse_request_items(&request.body)?;
+        if items.iter().any(|item| matches!(item, RequestItem::File { .. })) {
+            bail!("File fields cannot be used as GraphQL variables.");
+        }
+        if let Value::Object(variables) = build_json_body(&items)? {
+            self.variables.extend(variables);
+        }
+        request.body.retain(|item| !is_body_item(item));
+        request.body_mode = BodyMode::Json;
+        request.headers.push("Accept: application/graphql-response+json, application/json".to_string());
+
+        if request.method.eq_ignore_ascii_case("GET") {
+            let mut url = Url::parse(&request.url)?;
+            let envelope = self.envelope();
+            for (name, value) in envelope.as_object().into_iter().flatten() {
+                let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                url.query_pairs_mut().append_pair(name, &value);
+            }
+            request.url = url.to_string();
+            return Ok(());
+        }
+        let has_content_type = request.headers.iter().any(|header| header.to_ascii_lowercase().starts_with("content-type:"))
+            || items.iter().any(|item| matches!(item, RequestItem::Header(name, _) if name.eq_ignore_ascii_case("content-type")));
+        if !has_content_type {
+            request.headers.push("Content-Type: application/json".to_string());
+        }
+        request.raw_body = Some(serde_json::to_vec(&self.envelope())?);
+        request.data_file = None;
+        Ok(())
+    }
+}
+
+/// A GraphQL result, split into its `data` and `errors`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphqlResponse {
+    pub data: Option<Value>,
+    pub errors: Vec<Value>,
+}
+
+impl GraphqlResponse {
+    pub fn parse(body: &[u8]) -> Result<Self> {
+        let value: Value = serde_json::from_slice(body).map_err(|e| anyhow!("The response is not JSON: {}", e))?;
+        let Value::Object(mut result) = value else {
+            bail!("The response is not a GraphQL result object");
+        };
+        if !result.contains_key("data") && !result.contains_key("errors") {
+            bail!("The response has neither data nor errors");
+        }
+        let data = result.remove("data").filter(|data| !data.is_null());
+        let errors = match result.remove("errors") {
+            Some(Value::Array(errors)) => errors,
+            Some(Value::Null) | None => Vec::new(),
+            Some(other) => vec![other],
+        };
+        Ok(GraphqlResponse { data, errors })
+    }
+
+    pub fn data_json(&self) -> Option<String> {
+        self.data.as_ref().map(|data| serde_json::to_string_pretty(data).expect("JSON values serialize"))
+    }
+
+    /// One line per error: the message, where in the query it occurred and the result path.
+    pub fn error_messages(&self) -> Vec<String> {
+        self.errors.iter().map(error_message).collect()
+    }
+
+    /// Whether this is an introspection result refused because the server does not know
+    /// `isRepeatable`, so that the schema can be asked for again without it.
+    pub fn rejects_is_repeatable(&self) -> bool {
+        self.data.is_none() && self.errors.iter().any(|error| error["message"].as_str().is_some_and(|message| message.contains("isRepeatable")))
+    }
+}
+
+fn error_message(error: &Value) -> String {
+    let mut message = error["message"].as_str().map(str::to_string).unwrap_or_else(|| error.to_string());
+    let locations: Vec<String> = error["locations"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|location| format!("{}:{}", location["line"], location["column"]))
+        .collect();
+    if !locations.is_empty() {
+        message.push_str(&format!(" (at {})", locations.join(", ")));
+    }
+    if let Some(path) = error["path"].as_array() {
+        let path: Vec<String> = path.iter().map(|segment| segment.as_str().map(str::to_string).unwrap_or_else(|| segment.to_string())).collect();
+        message.push_str(&format!(" [{}]", path.join(".")));
+    }
+    message
+}
+
+/// Prints the schema from an introspection result's `data` as SDL.
+pub fn schema_sdl(data: &Value) -> Result<String> {
+    let schema = data.get("__schema").ok_or_else(|| anyhow!("The introspection result has no __schema"))?;
+    let root = |key: &str| schema[key]["name"].as_str().map(str::to_string);
+    let roots = [("query", root("queryType")), ("mutation", root("mutationType")), ("subscription", root("subscriptionType"))];
+
+    let mut blocks = Vec::new();
+    let conventional = roots
+        .iter()
+        .all(|(operation, name)| name.as_deref().is_none_or(|name| name.eq_ignore_ascii_case(operation)));
+    if !conventional {
+        let fields: Vec<String> = roots
+            .iter()
+            .filter_map(|(operation, name)| name.as_ref().map(|name| format!("  {}: {}", operation, name)))
+            .collect();
+        blocks.push(format!("schema {{\n{}\n}}", fields.join("\n")));
+    }
+    for directive in schema["directives"].as_array().into_iter().flatten() {
+        let name = directive["name"].as_str().unwrap_or_default();
+        if BUILT_IN_DIRECTIVES.contains(&name) {
+            continue;
+        }
+        let locations: Vec<&str> = directive["locations"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+        blocks.push(format!(
+            "{}directive @{}{}{} on {}",
+            description(&directive["description"], ""),
+            name,
+            arguments(&directive["args"]),
+            if directive["isRepeatable"] == Value::Bool(true) { " repeatable" } else { "" },
+            locations.join(" | ")
+        ));
+    }
+    for kind in schema["types"].as_array().ok_or_else(|| anyhow!("The introspection result has no types"))? {
+        let name = kind["name"].as_str().unwrap_or_default();
+        if name.starts_with("__") || BUILT_IN_SCALARS.contains(&name) {
+            continue;
+        }
+        blocks.push(type_definition(kind)?);
+    }
+    Ok(blocks.join("\n\n") + "\n")
+}
+
+fn type_definition(kind: &Value) -> Result<String> {
+    let name = kind["name"].as_str().unwrap_or_default();
+    let head = description(&kind["description"], "");
+    Ok(match kind["kind"].as_str().unwrap_or_default() {
+        "SCALAR" => format!("{}scalar {}", head, name),
+        "OBJECT" | "INTERFACE" => {
+            let keyword = if kind["kind"] == "OBJECT" { "type" } else { "interface" };
+            let interfaces: Vec<String> = kind["interfaces"].as_array().into_iter().flatten().map(type_reference).collect();
+            let implements = if interfaces.is_empty() { String::new() } else { format!(" implements {}", interfaces.join(" & ")) };
+            let fields: Vec<String> = kind["fields"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|field| {
+                    format!(
+                        "{}  {}{}: {}{}",
+                        description(&field["description"], "  "),
+                        field["name"].as_str().unwrap_or_default(),
+                        arguments(&field["args"]),
+                        type_reference(&field["type"]),
+                        deprecation(field)
+                    )
+                })
+                .collect();
+            format!("{}{} {}{} {{\n{}\n}}", head, keyword, name, implements, fields.join("\n"))
+        }
+        "UNION" => {
+            let members: Vec<String> = kind["possibleTypes"].as_array().into_iter().flatten().map(type_reference).collect();
+            format!("{}union {} = {}", head, name, members.join(" | "))
+        }
+        "ENUM" => {
+            let values: Vec<String> = kind["enumValues"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|value| format!("{}  {}{}", description(&value["description"], "  "), value["name"].as_str().unwrap_or_default(), deprecation(value)))
+                .collect();
+            format!("{}enum {} {{\n{}\n}}", head, name, values.join("\n"))
+        }
+        "INPUT_OBJECT" => {
+            let fields: Vec<String> = kind["inputFields"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|field| format!("{}  {}", description(&field["description"], "  "), input_value(field)))
+                .collect();
+            format!("{}input {} {{\n{}\n}}", head, name, fields.join("\n"))
+        }
+        other => bail!("Unknown kind '{}' of type {}", other, name),
+    })
+}
+
+/// `Name`, `[Name]` or `Name!`, following the nested `ofType` wrappers.
+fn type_reference(reference: &Value) -> String {
+    match reference["kind"].as_str() {
+        Some("NON_NULL") => format!("{}!", type_reference(&reference["ofType"])),
+        Some("LIST") => format!("[{}]", type_reference(&reference["ofType"])),
+        _ => reference["name"].as_str().unwrap_or_default().to_string(),
+    }
+}
+
+fn input_value(value: &Value) -> String {
+    let mut text = format!("{}: {}", value["name"].as_str().unwrap_or_default(), type_reference(&value["type"]));
+    if let Some(default) = value["defaultValue"].as_str() {
+        text.push_str(&format!(" = {}", default));
+    }
+    text
+}
+
+fn arguments(args: &Value) -> String {
+    let args: Vec<String> = args.as_array().into_iter().flatten().map(input_value).collect();
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!("({})", args.join(", "))
+    }
+}
+
+fn deprecation(member: &Value) -> String {
+    if member["isDeprecated"] != Value::Bool(true) {
+        return String::new();
+    }
+    match member["deprecationReason"].as_str() {
+        Some(reason) if reason != "No longer supported" => format!(" @deprecated(reason: {})", Value::String(reason.to_string())),
+        _ => " @deprecated".to_string(),
+    }
+}
+
+/// A description as a block string on the lines before a definition indented by `indent`.
+fn description(text: &Value, indent: &str) -> String {
+    match text.as_str() {
+        Some(text) if !text.is_empty() && !text.contains('\n') => format!("{}{}\n", indent, Value::String(text.to_string())),
+        Some(text) if !text.is_empty() => {
+            let body: Vec<String> = text.replace("\"\"\"", "\\\"\"\"").lines().map(|line| format!("{}{}", indent, line)).collect();
+            format!("{}\"\"\"\n{}\n{}\"\"\"\n", indent, body.join("\n"), indent)
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_post_and_get() {
+        let graphql = GraphqlRequest { query: "query User($id: ID!) { user(id: $id) { name } }".to_string(), variables: Map::new(), operation_name: Some("User".to_string()) };
+        let mut request = HttpRequest {
+            url: "http://api.test/graphql".to_string(),
+            method: "POST".to_string(),
+            body: vec!["id=7".to_string(), "limit:=2".to_string(), "X-Trace:1".to_string(), "debug==true".to_string()],
+            ..Default::default()
+        };
+        graphql.clone().apply(&mut request).unwrap();
+        assert_eq!(request.body, ["X-Trace:1", "debug==true"]);
+        assert!(request.headers.contains(&"Content-Type: application/json".to_string()));
+        let envelope: Value = serde_json::from_slice(request.raw_body.as_deref().unwrap()).unwrap();
+        assert_eq!(envelope, json!({"query": graphql.query, "variables": {"id": "7", "limit": 2}, "operationName": "User"}));
+
+        let mut request = HttpRequest { url: "http://api.test/graphql".to_string(), method: "GET".to_string(), body: vec!["id=7".to_string()], ..Default::default() };
+        graphql.apply(&mut request).unwrap();
+        assert!(request.raw_body.is_none());
+        let url = Url::parse(&request.url).unwrap();
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert_eq!(pairs.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), ["operationName", "query", "variables"]);
+        assert_eq!(pairs[2].1, r#"{"id":"7"}"#);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = GraphqlResponse::parse(
+            br#"{"data": {"user": null}, "errors": [{"message": "Not found", "locations": [{"line": 1, "column": 9}], "path": ["user", 0]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.data, Some(json!({"user": null})));
+        assert_eq!(response.error_messages(), ["Not found (at 1:9) [user.0]"]);
+        assert!(GraphqlResponse::parse(b"[1]").is_err());
+        assert!(GraphqlResponse::parse(br#"{"ok": true}"#).is_err());
+    }
+
+    #[test]
+    fn test_introspection_without_is_repeatable() {
+        assert!(GraphqlRequest::introspection(true).query.contains("isRepeatable"));
+        let legacy = GraphqlRequest::introspection(false);
+        assert!(!legacy.query.contains("isRepeatable"));
+        assert!(legacy.query.contains("      description\n      locations\n"));
+
+        let rejected = GraphqlResponse::parse(br#"{"errors": [{"message": "Cannot query field \"isRepeatable\" on type \"__Directive\"."}]}"#).unwrap();
+        assert!(rejected.rejects_is_repeatable());
+        let other = GraphqlResponse::parse(br#"{"errors": [{"message": "Not authorized"}]}"#).unwrap();
+        assert!(!other.rejects_is_repeatable());
+        let answered = GraphqlResponse::parse(br#"{"data": {"__schema": {}}, "errors": [{"message": "isRepeatable is slow"}]}"#).unwrap();
+        assert!(!answered.rejects_is_repeatable());
+    }
+
+    #[test]
+    fn test_schema_sdl() {
+        let named = |kind: &str, name: &str| json!({"kind": kind, "name": name, "ofType": null});
+        let non_null = |inner: Value| json!({"kind": "NON_NULL", "name": null, "ofType": inner});
+        let data = json!({"__schema": {
+            "queryType": {"name": "Query"},
+            "mutationType": null,
+            "subscriptionType": null,
+            "directives": [
+                {"name": "skip", "locations": ["FIELD"], "args": []},
+                {"name": "tag", "description": null, "isRepeatable": true, "locations": ["FIELD_DEFINITION", "OBJECT"],
+                 "args": [{"name": "name", "type": non_null(named("SCALAR", "String")), "defaultValue": null}]}
+            ],
+            "types": [
+                {"kind": "OBJECT", "name": "Query", "description": null, "interfaces": [], "fields": [
+                    {"name": "user", "description": "Look up a user.", "isDeprecated": false,
+                     "args": [{"name": "id", "type": non_null(named("SCALAR", "ID")), "defaultValue": null}],
+                     "type": named("OBJECT", "User")},
+                    {"name": "users", "description": null, "isDeprecated": true, "deprecationReason": "Use search",
+                     "args": [{"name": "first", "type": named("SCALAR", "Int"), "defaultValue": "10"}],
+                     "type": non_null(json!({"kind": "LIST", "name": null, "ofType": non_null(named("OBJECT", "User"))}))}
+                ]},
+                {"kind": "OBJECT", "name": "User", "description": null, "interfaces": [named("INTERFACE", "Node")], "fields": [
+                    {"name": "id", "args": [], "type": non_null(named("SCALAR", "ID")), "isDeprecated": false}
+                ]},
+                {"kind": "INTERFACE", "name": "Node", "fields": [{"name": "id", "args": [], "type": non_null(named("SCALAR", "ID"))}]},
+                {"kind": "ENUM", "name": "Role", "enumValues": [{"name": "ADMIN"}, {"name": "GUEST", "isDeprecated": true, "deprecationReason": "No longer supported"}]},
+                {"kind": "UNION", "name": "Result", "possibleTypes": [named("OBJECT", "User")]},
+                {"kind": "INPUT_OBJECT", "name": "Filter", "inputFields": [{"name": "role", "type": named("ENUM", "Role"), "defaultValue": "ADMIN"}]},
+                {"kind": "SCALAR", "name": "String"},
+                {"kind": "SCALAR", "name": "DateTime", "description": "An ISO 8601 timestamp.\nIn UTC."},
+                {"kind": "OBJECT", "name": "__Type", "fields": []}
+            ]
+        }});
+        assert_eq!(
+            schema_sdl(&data).unwrap(),
+            r#"directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT
+
+type Query {
+  "Look up a user."
+  user(id: ID!): User
+  users(first: Int = 10): [User!]! @deprecated(reason: "Use search")
+}
+
+type User implements Node {
+  id: ID!
+}
+
+interface Node {
+  id: ID!
+}
+
+enum Role {
+  ADMIN
+  GUEST @deprecated
+}
+
+union Result = User
+
+input Filter {
+  role: Role = ADMIN
+}
+
+"""
+An ISO 8601 timestamp.
+In UTC.
+"""
+scalar DateTime
+"#
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+/// Values below this are counted exactly; above it, each power of two is split into
+/// `SUB_BUCKETS / 2` buckets, which keeps three significant digits (like HdrHistogram).
+const SUB_BUCKETS: u64 = 2048;
+const HALF: u64 = SUB_BUCKETS / 2;
+
+/// A latency histogram with microsecond resolution and a bounded relative error of 0.1%.
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: Duration) {
+        let micros = u64::try_from(value.as_micros()).unwrap_or(u64::MAX);
+        let index = index_of(micros);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.min = if self.count == 0 { micros } else { self.min.min(micros) };
+        self.max = self.max.max(micros);
+        self.count += 1;
+        self.sum += micros as u128;
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.count == 0 {
+            return;
+        }
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.min = if self.count == 0 { other.min } else { self.min.min(other.min) };
+        self.max = self.max.max(other.max);
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.min)
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => Duration::from_micros((self.sum / count as u128) as u64),
+        }
+    }
+
+    /// The value that `percentile`% of the recorded values are at or below, e.g. 99.9.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((percentile / 100.0 * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(highest_equivalent(index).min(self.max));
+            }
+        }
+        self.max()
+    }
+}
+
+fn index_of(value: u64) -> usize {
+    if value < SUB_BUCKETS {
+        return value as usize;
+    }
+    // Shift the value down until it fits in [HALF, SUB_BUCKETS).
+    let shift = (63 - value.leading_zeros()) - (SUB_BUCKETS.trailing_zeros() - 1);
+    (SUB_BUCKETS + (shift as u64 - 1) * HALF + ((value >> shift) - HALF)) as usize
+}
+
+/// The largest value counted in the bucket at `index`.
+fn highest_equivalent(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKETS {
+        return index;
+    }
+    let shift = (index - SUB_BUCKETS) / HALF + 1;
+    let lowest = (HALF + (index - SUB_BUCKETS) % HALF) << shift;
+    lowest + (1 << shift) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_boundaries() {
+        assert_eq!(index_of(2047), 2047);
+        assert_eq!(index_of(2048), 2048);
+        assert_eq!(index_of(2049), 2048);
+        assert_eq!(index_of(2050), 2049);
+        assert_eq!(highest_equivalent(2048), 2049);
+        for value in [0, 1, 2047, 2048, 4095, 4096, 123_456, 1_000_000, 3_600_000_000] {
+            let high = highest_equivalent(index_of(value));
+            assert!(high >= value && (high - value) as f64 <= value as f64 / 1000.0, "{} -> {}", value, high);
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = Histogram::default();
+        for ms in 1..=1000 {
+            histogram.record(Duration::from_millis(ms));
+        }
+        assert_eq!(histogram.count(), 1000);
+        assert_eq!(histogram.min(), Duration::from_millis(1));
+        assert_eq!(histogram.max(), Duration::from_millis(1000));
+        assert_eq!(histogram.mean(), Duration::from_micros(500_500));
+        let p50 = histogram.percentile(50.0).as_micros() as f64;
+        assert!((p50 - 500_000.0).abs() <= 500.0, "{}", p50);
+        let p999 = histogram.percentile(99.9).as_micros() as f64;
+        assert!((p999 - 999_000.0).abs() <= 1000.0, "{}", p999);
+        assert_eq!(histogram.percentile(100.0), Duration::from_millis(1000));
+
+        let mut merged = Histogram::default();
+        merged.merge(&histogram);
+        merged.record(Duration::from_secs(5));
+        assert_eq!((merged.count(), merged.max()), (1001, Duration::from_secs(5)));
+    }
+}
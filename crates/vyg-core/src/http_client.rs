@@ -1,129 +1,749 @@
-use reqwest::{Client, Method, header::HeaderMap};
-use serde_json::{Value, Map};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Version, multipart};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, USER_AGENT as USER_AGENT_HEADER, WWW_AUTHENTICATE};
+use serde_json::Value;
 use anyhow::{anyhow, Result};
+use std::borrow::Cow;
 use std::fs;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use url::Url;
+use crate::auth::{self, Auth};
+use crate::compression::{self, Encoding, ResponseEncoding};
+use crate::content;
+use crate::cookie_jar::SharedCookieJar;
+use crate::event_stream::StreamFormat;
+use crate::request_items::{build_json_body, parse_request_items, RequestItem};
+use crate::redirect::{self, RedirectHop, RedirectOptions};
+use crate::retry;
+use crate::timing::{TimedConnectorLayer, TimedResolver, TimingRecorder, Timings};
+use crate::tls::{self, TlsDetails, TlsOptions};
+use crate::resolve::ResolveOptions;
+use crate::transport::TransportOptions;
+use crate::unix_socket;
+use crate::write_out::Transfer;
 
 const USER_AGENT: &str = "Varyag/0.1.0";
 
+/// How body items are serialized into the request body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BodyMode {
+    /// A JSON object (`application/json`).
+    #[default]
+    Json,
+    /// URL-encoded form fields (`application/x-www-form-urlencoded`).
+    /// Switches to multipart automatically when file fields are present.
+    Form,
+    /// Multipart form data (`multipart/form-data`).
+    Multipart,
+}
+
+/// Which HTTP version to speak.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// HTTP/2 if the server offers it during the TLS handshake, HTTP/1.1 otherwise.
+    #[default]
+    Negotiate,
+    Http1_1,
+    /// HTTP/2 over TLS, negotiated with ALPN.
+    Http2,
+    /// HTTP/2 without negotiation, including cleartext `http://` (h2c).
+    Http2PriorKnowledge,
+}
+
+#[derive(Clone, Default)]
 pub struct HttpRequest {
     pub url: String,
     pub method: String,
     pub headers: Vec<String>,
     pub body: Vec<String>,
     pub data_file: Option<PathBuf>,
+    /// A raw body, e.g. piped on stdin. Ignored when `data_file` is set.
+    pub raw_body: Option<Vec<u8>>,
     pub noproxy: bool,
+    pub body_mode: BodyMode,
+    pub auth: Option<Auth>,
+    /// Cookies to send and update, e.g. from a persistent session.
+    pub cookie_jar: Option<Arc<SharedCookieJar>>,
+    pub transport: TransportOptions,
+    pub redirect: RedirectOptions,
+    pub http_version: HttpVersion,
+    /// Compress the request body with this coding.
+    pub compress: Option<Encoding>,
+    pub response_encoding: ResponseEncoding,
+}
+
+/// A request as it went out on the final attempt, for display.
+#[derive(Debug, Clone, Default)]
+pub struct SentRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HeaderMap,
+    /// `None` when there was no body or it was streamed, e.g. multipart file parts.
+    pub body: Option<Vec<u8>>,
+}
+
+impl SentRequest {
+    /// Captures `request` with the headers the client and connection add by default.
+    fn new(request: &reqwest::Request) -> Self {
+        let mut headers = HeaderMap::new();
+        if let Some(host) = request.url().host_str() {
+            let host = match request.url().port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            headers.extend(HeaderValue::from_str(&host).ok().map(|host| (HOST, host)));
+        }
+        headers.extend(request.headers().clone());
+        add_default_headers(&mut headers);
+        if let Some(length) = request.body().and_then(|body| body.as_bytes()).map(<[u8]>::len).filter(|&len| len > 0) {
+            headers.entry(CONTENT_LENGTH).or_insert(HeaderValue::from(length));
+        }
+        SentRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers,
+            body: request.body().and_then(|body| body.as_bytes()).map(<[u8]>::to_vec),
+        }
+    }
+
+    /// The request line, e.g. `GET /search?q=rust HTTP/1.1`, for a response of version `negotiated`.
+    /// HTTP/1.x requests always go out as HTTP/1.1, whatever the server answers with.
+    pub fn request_line(&self, negotiated: &str) -> String {
+        let version = if negotiated.starts_with("HTTP/1") { "HTTP/1.1" } else { negotiated };
+        format!("{} {} {}", self.method, self.target(), version)
+    }
+
+    /// The request target in origin form, e.g. `/search?q=rust`.
+    fn target(&self) -> String {
+        match Url::parse(&self.url) {
+            Ok(url) => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            Err(_) => self.url.clone(),
+        }
+    }
 }
 
+/// A response whose body has not been read yet, with what was sent to obtain it.
+pub(crate) struct Exchange {
+    pub(crate) response: Response,
+    pub(crate) request: SentRequest,
+    pub(crate) timing: Arc<TimingRecorder>,
+    /// The redirects followed before this response, oldest first.
+    pub(crate) redirects: Vec<RedirectHop>,
+}
+
+#[derive(Debug)]
 pub struct HttpResponse {
+    /// The negotiated protocol, e.g. `HTTP/2`.
+    pub version: String,
     pub status: String,
     pub headers: HeaderMap,
-    pub body: String,
+    pub body: Vec<u8>,
+    /// The final URL, after redirects.
+    pub url: String,
+    pub remote_addr: Option<SocketAddr>,
+    /// The TLS connection the response arrived on; `None` for plain HTTP.
+    pub tls: Option<TlsDetails>,
+    pub timings: Timings,
+    pub request: SentRequest,
+    pub redirects: Vec<RedirectHop>,
+    /// The `Content-Encoding` the body arrived with; its size on the wire is in `timings`.
+    pub content_encoding: Option<String>,
+    /// Whether `body` was decoded from `content_encoding`.
+    pub decoded: bool,
+}
+
+impl HttpResponse {
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok())
+    }
+
+    /// The body decoded as UTF-8, with invalid sequences replaced.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    pub fn is_binary(&self) -> bool {
+        content::is_binary(self.content_type(), &self.body)
+    }
+
+    /// The values `--write-out` templates are rendered from.
+    pub fn transfer(&self) -> Transfer<'_> {
+        Transfer {
+            version: &self.version,
+            status: &self.status,
+            url: &self.url,
+            content_type: self.content_type(),
+            remote_addr: self.remote_addr,
+            timings: &self.timings,
+        }
+    }
 }
 
 pub async fn send_request(request: HttpRequest) -> Result<HttpResponse> {
+    let transport = request.transport.clone();
+    let response_encoding = request.response_encoding;
+    read_response(execute(request).await?, &transport, response_encoding).await
+}
+
+/// Sends `request` like [`send_request`], but leaves the body of an event stream or NDJSON
+/// response unread, so it can be consumed as it arrives.
+pub async fn open_request(request: HttpRequest) -> Result<Opened> {
+    let transport = request.transport.clone();
+    let response_encoding = request.response_encoding;
+    let exchange = execute(request).await?;
+    let headers = exchange.response.headers();
+    let format = headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).and_then(StreamFormat::from_content_type);
+    // Compressed streams are rare, and are read whole so they can be decoded.
+    match format {
+        Some(format) if !headers.contains_key(CONTENT_ENCODING) => Ok(Opened::Streaming(Box::new(StreamingResponse {
+            version: version_name(exchange.response.version()),
+            status: exchange.response.status().to_string(),
+            headers: exchange.response.headers().clone(),
+            tls: TlsDetails::of_response(&exchange.response),
+            format,
+            request: exchange.request,
+            redirects: exchange.redirects,
+            response: exchange.response,
+            transport,
+        }))),
+        _ => Ok(Opened::Complete(Box::new(read_response(exchange, &transport, response_encoding).await?))),
+    }
+}
+
+/// The outcome of [`open_request`].
+pub enum Opened {
+    Complete(Box<HttpResponse>),
+    Streaming(Box<StreamingResponse>),
+}
+
+/// A response whose headers have arrived and whose body streams in, chunk by chunk.
+pub struct StreamingResponse {
+    pub version: String,
+    pub status: String,
+    pub headers: HeaderMap,
+    /// The TLS connection the response arrived on; `None` for plain HTTP.
+    pub tls: Option<TlsDetails>,
+    pub format: StreamFormat,
+    pub request: SentRequest,
+    pub redirects: Vec<RedirectHop>,
+    response: Response,
+    transport: TransportOptions,
+}
+
+impl StreamingResponse {
+    /// The next chunk of the body as it arrived, or `None` once the server has ended it.
+    pub async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        match self.response.chunk().await {
+            Ok(chunk) => Ok(chunk.map(|chunk| chunk.to_vec())),
+            Err(e) => Err(explain_timeout(e.into(), &self.transport)),
+        }
+    }
+}
+
+async fn read_response(exchange: Exchange, transport: &TransportOptions, response_encoding: ResponseEncoding) -> Result<HttpResponse> {
+    let Exchange { response, request, timing, redirects } = exchange;
+    let version = version_name(response.version());
+    let status = response.status().to_string();
+    let headers = response.headers().clone();
+    let url = request.url.clone();
+    let remote_addr = response.remote_addr();
+    let tls = TlsDetails::of_response(&response);
+    let body = response.bytes().await.map_err(|e| explain_timeout(e.into(), transport))?.to_vec();
+    let timings = timing.finish(body.len() as u64);
+
+    let content_encoding = headers.get(CONTENT_ENCODING).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let (body, decoded) = match (&content_encoding, response_encoding) {
+        (Some(encoding), ResponseEncoding::Decode) => match compression::decode(encoding, &body)? {
+            Some(decoded) => (decoded, true),
+            None => (body, false),
+        },
+        _ => (body, false),
+    };
+
+    Ok(HttpResponse { version, status, headers, body, url, remote_addr, tls, timings, request, redirects, content_encoding, decoded })
+}
+
+/// Sends `request` and follows redirects as configured, recording every hop.
+///
+/// The timings of the final response are finished by the caller once the body has been read.
+pub(crate) async fn execute(mut request: HttpRequest) -> Result<Exchange> {
+    use_unix_socket_url(&mut request)?;
+    let mut redirects = Vec::new();
+    loop {
+        let exchange = execute_hop(request.clone()).await?;
+        let url = Url::parse(&exchange.request.url)?;
+        let status = exchange.response.status();
+        let target = match redirect::location(status, exchange.response.headers(), &url) {
+            Some(target) if request.redirect.follow => target?,
+            _ => return Ok(Exchange { redirects, ..exchange }),
+        };
+        if redirects.len() >= request.redirect.max {
+            return Err(anyhow!(
+                "Too many redirects: gave up after {} at {} (use --max-redirects to allow more)",
+                redirects.len(),
+                url,
+            ));
+        }
+        redirects.push(RedirectHop {
+            version: version_name(exchange.response.version()),
+            method: exchange.request.method.clone(),
+            url: url.to_string(),
+            status: status.to_string(),
+            headers: exchange.response.headers().clone(),
+            location: target.to_string(),
+            timings: exchange.timing.finish(0),
+        });
+        request = redirect::next_request(request, status, &url, &target);
+    }
+}
+
+/// Builds `request` exactly as it would be sent, without sending it.
+///
+/// Digest credentials are missing, since they answer a challenge from the server.
+pub async fn dry_run(mut request: HttpRequest) -> Result<SentRequest> {
+    use_unix_socket_url(&mut request)?;
+    let (_, built) = prepare(request, &Arc::new(TimingRecorder::default())).await?;
+    Ok(SentRequest::new(&built))
+}
+
+/// A request built once and sent many times over one client's pooled keep-alive connections.
+///
+/// Redirects are not followed and retries are not made, so every send is a single exchange.
+pub struct RepeatedRequest {
+    sender: Sender,
+    request: reqwest::Request,
+    transport: TransportOptions,
+}
+
+impl RepeatedRequest {
+    pub async fn new(mut request: HttpRequest) -> Result<Self> {
+        if matches!(request.auth, Some(Auth::Digest { .. })) {
+            return Err(anyhow!("Digest authentication cannot be used for repeated requests."));
+        }
+        use_unix_socket_url(&mut request)?;
+        let transport = request.transport.clone();
+        let (sender, built) = prepare(request, &Arc::new(TimingRecorder::default())).await?;
+        if built.try_clone().is_none() {
+            return Err(anyhow!("Cannot repeat a streamed body, such as a multipart upload of files."));
+        }
+        Ok(RepeatedRequest { sender, request: built, transport })
+    }
+
+    pub fn url(&self) -> &Url {
+        self.request.url()
+    }
+
+    /// Sends the request and reads the whole response, returning its status and body size.
+    pub async fn send(&self) -> Result<(u16, usize)> {
+        let exchange = async {
+            let mut request = self.request.try_clone().expect("checked when built");
+            route_port(&mut request, &self.transport.resolve)?;
+            let response = self.sender.execute(request, &self.transport).await?;
+            let status = response.status().as_u16();
+            let body = response.bytes().await?;
+            Ok((status, body.len()))
+        };
+        exchange.await.map_err(|e| explain_timeout(e, &self.transport))
+    }
+}
+
+/// Moves the socket path of an `http+unix://` URL into the transport options.
+fn use_unix_socket_url(request: &mut HttpRequest) -> Result<()> {
+    if let Some(split) = unix_socket::split_url(&request.url) {
+        let (socket, url) = split?;
+        request.transport.unix_socket = Some(socket);
+        request.url = url;
+    }
+    Ok(())
+}
+
+/// Sends a single request, retrying connection failures and retryable statuses as configured.
+async fn execute_hop(request: HttpRequest) -> Result<Exchange> {
+    let policy = request.transport.retry.clone();
+    let mut attempt = 0;
+    loop {
+        let timing = Arc::new(TimingRecorder::default());
+        let result = execute_once(request.clone(), &timing)
+            .await
+            .and_then(|(response, sent)| Ok((check_response_pin(response, &request.transport.tls)?, sent)));
+        let (reason, delay) = match result {
+            Ok((response, _)) if attempt < policy.retries && policy.retries_status(response.status().as_u16()) => (
+                format!("Server responded with {}", response.status()),
+                policy.delay(attempt + 1, retry::retry_after(response.headers())),
+            ),
+            Err(e) if attempt < policy.retries && is_connect_error(&e) => (e.to_string(), policy.backoff(attempt + 1)),
+            result => {
+                return result
+                    .map(|(response, request)| Exchange { response, request, timing, redirects: Vec::new() })
+                    .map_err(|e| explain_timeout(e, &request.transport))
+            }
+        };
+        attempt += 1;
+        retry::wait(&policy, attempt, &reason, delay).await;
+    }
+}
+
+/// Verifies certificate pins against the connection the response actually arrived on.
+///
+/// The handshake already enforces them; this catches a response that did not come over TLS.
+fn check_response_pin(response: Response, options: &TlsOptions) -> Result<Response> {
+    if options.pins.is_empty() {
+        return Ok(response);
+    }
+    let certificate = response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .ok_or_else(|| anyhow!("Cannot check the certificate pin: the response did not arrive over TLS"))?;
+    options.check_pin(certificate)?;
+    Ok(response)
+}
+
+fn is_connect_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_connect())
+}
+
+/// Replaces reqwest's generic message for timeouts with one naming the exceeded limit.
+fn explain_timeout(error: anyhow::Error, transport: &TransportOptions) -> anyhow::Error {
+    let limit = match error.downcast_ref::<reqwest::Error>() {
+        Some(e) if e.is_timeout() && e.is_connect() => transport.connect_timeout.map(|t| ("Connecting", t)),
+        Some(e) if e.is_timeout() => transport.timeout.map(|t| ("Request", t)),
+        _ => None,
+    };
+    match limit {
+        Some((what, limit)) => anyhow!("{} timed out after {:.1}s", what, limit.as_secs_f64()),
+        None => error,
+    }
+}
+
+/// Sends `request` once, answering a Digest authentication challenge if one is configured.
+async fn execute_once(request: HttpRequest, timing: &Arc<TimingRecorder>) -> Result<(Response, SentRequest)> {
+    let transport = request.transport.clone();
+    let Some(Auth::Digest { username, password }) = request.auth.clone() else {
+        let (sender, built) = prepare(request, timing).await?;
+        return send(sender, built, timing, &transport).await;
+    };
+
+    // The first attempt goes out without credentials to obtain the server's challenge.
+    let (sender, built) = prepare(request.clone(), timing).await?;
+    let (response, sent) = send(sender, built, timing, &transport).await?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok((response, sent));
+    }
+    let challenge = auth::find_digest_challenge(
+        response.headers().get_all(WWW_AUTHENTICATE).iter().filter_map(|v| v.to_str().ok()),
+    );
+    let Some(challenge) = challenge else {
+        return Ok((response, sent));
+    };
+
+    let (sender, mut built) = prepare(request, timing).await?;
+    let uri = match built.url().query() {
+        Some(query) => format!("{}?{}", built.url().path(), query),
+        None => built.url().path().to_string(),
+    };
+    let authorization = auth::digest_authorization(
+        &challenge,
+        &username,
+        &password,
+        built.method().as_str(),
+        &uri,
+        &auth::generate_cnonce(),
+    )?;
+    built.headers_mut().insert(AUTHORIZATION, authorization.parse()?);
+    send(sender, built, timing, &transport).await
+}
+
+/// Builds the request described by `request`, compressing its body if asked to.
+///
+/// Cookies from the jar are added here rather than by the client, so they show up in the sent request.
+async fn prepare(request: HttpRequest, timing: &Arc<TimingRecorder>) -> Result<(Sender, reqwest::Request)> {
+    let compress = request.compress;
+    let cookie_jar = request.cookie_jar.clone();
+    let sender_jar = cookie_jar.clone();
+    let (client, built) = build_request(request, timing).await?.build_split();
+    let mut built = built?;
+    if let Some(encoding) = compress {
+        compress_body(&mut built, encoding)?;
+    }
+    if let Some(cookies) = cookie_jar.and_then(|jar| reqwest::cookie::CookieStore::cookies(jar.as_ref(), built.url())) {
+        built.headers_mut().entry(COOKIE).or_insert(cookies);
+    }
+    Ok((Sender { client, cookie_jar: sender_jar }, built))
+}
+
+/// A built client, and the cookie jar it stores into for requests it does not send itself.
+struct Sender {
+    client: Client,
+    cookie_jar: Option<Arc<SharedCookieJar>>,
+}
+
+impl Sender {
+    /// Sends `request` with the client, or over the configured Unix socket.
+    async fn execute(&self, request: reqwest::Request, transport: &TransportOptions) -> Result<Response> {
+        match &transport.unix_socket {
+            Some(socket) => unix_socket::send(socket, request, self.cookie_jar.as_deref(), transport).await,
+            None => Ok(self.client.execute(request).await?),
+        }
+    }
+}
+
+/// Adds the headers the client sends unless the request sets them itself.
+pub(crate) fn add_default_headers(headers: &mut HeaderMap) {
+    headers.entry(USER_AGENT_HEADER).or_insert(HeaderValue::from_static(USER_AGENT));
+    headers.entry(ACCEPT).or_insert(HeaderValue::from_static("*/*"));
+}
+
+fn compress_body(request: &mut reqwest::Request, encoding: Encoding) -> Result<()> {
+    let Some(body) = request.body() else {
+        return Ok(());
+    };
+    let data = body
+        .as_bytes()
+        .ok_or_else(|| anyhow!("Cannot compress a streamed body, such as a multipart upload of files."))?;
+    let compressed = encoding.compress(data)?;
+    *request.body_mut() = Some(compressed.into());
+    request.headers_mut().insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+    Ok(())
+}
+
+/// Executes a built request, recording when it went out and when the response headers arrived.
+async fn send(sender: Sender, mut request: reqwest::Request, timing: &TimingRecorder, transport: &TransportOptions) -> Result<(Response, SentRequest)> {
+    let sent = SentRequest::new(&request);
+    route_port(&mut request, &transport.resolve)?;
+    let bytes_sent = request.body().and_then(|body| body.as_bytes()).map(|bytes| bytes.len() as u64);
+    timing.start(request.url().scheme() == "https", bytes_sent);
+    let response = sender.execute(request, transport).await?;
+    timing.first_byte();
+    Ok((response, sent))
+}
+
+/// Applies a `--connect-to` port change the resolver cannot make.
+///
+/// Connections keep the port of a URL that names one explicitly, whatever the resolver
+/// returns, so such a URL is pointed at the new port while the Host header keeps the old one.
+fn route_port(request: &mut reqwest::Request, resolve: &ResolveOptions) -> Result<()> {
+    let url = request.url_mut();
+    let (Some(host), Some(port)) = (url.host_str().map(str::to_string), url.port()) else {
+        return Ok(());
+    };
+    let (_, routed_port) = resolve.route(&host, port);
+    if routed_port != port {
+        url.set_port(Some(routed_port)).map_err(|_| anyhow!("Cannot change the port of {}", url))?;
+        let authority = HeaderValue::from_str(&format!("{}:{}", host, port))?;
+        request.headers_mut().entry(HOST).or_insert(authority);
+    }
+    Ok(())
+}
+
+/// Builds the client and request described by `request` without sending it.
+async fn build_request(request: HttpRequest, timing: &Arc<TimingRecorder>) -> Result<RequestBuilder> {
+    let mut url = Url::parse(&request.url)?;
+    let resolve = &request.transport.resolve;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    // Redirects are followed by `execute`, so every hop can be shown and controlled.
     let mut client_builder = Client::builder()
-        .user_agent(USER_AGENT);
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(TimedResolver { timing: timing.clone(), options: resolve.clone(), port }))
+        .connector_layer(TimedConnectorLayer(timing.clone()));
 
-    if request.noproxy {
+    if let Some(local_address) = resolve.local_address {
+        client_builder = client_builder.local_address(local_address);
+    }
+    if let Some(interface) = &resolve.interface {
+        client_builder = bind_interface(client_builder, interface)?;
+    }
+
+    if let Some(proxy) = &request.transport.proxy {
+        client_builder = client_builder.proxy(proxy.reqwest_proxy()?);
+    } else if request.noproxy {
         client_builder = client_builder.no_proxy();
     }
 
+    if let Some(jar) = request.cookie_jar {
+        client_builder = client_builder.cookie_provider(jar);
+    }
+
+    if let Some(timeout) = request.transport.timeout {
+        client_builder = client_builder.timeout(timeout);
+    }
+
+    if let Some(connect_timeout) = request.transport.connect_timeout {
+        client_builder = client_builder.connect_timeout(connect_timeout);
+    }
+
+    let alpn: &[&str] = match request.http_version {
+        HttpVersion::Negotiate => &["h2", "http/1.1"],
+        HttpVersion::Http1_1 => {
+            client_builder = client_builder.http1_only();
+            &["http/1.1"]
+        }
+        HttpVersion::Http2 if url.scheme() != "https" => {
+            return Err(anyhow!("HTTP/2 over cleartext needs --http2-prior-knowledge: {}", url));
+        }
+        HttpVersion::Http2 | HttpVersion::Http2PriorKnowledge => {
+            // Over TLS this only offers `h2` in ALPN, so servers without HTTP/2 are refused.
+            client_builder = client_builder.http2_prior_knowledge();
+            &["h2"]
+        }
+    };
+    client_builder = tls::configure_client(client_builder, &request.transport.tls, alpn)?;
+
     let client = client_builder.build()?;
 
     let method = Method::from_bytes(request.method.to_uppercase().as_bytes())?;
 
-    let mut request_builder = client.request(method, &request.url);
+    let items = parse_request_items(&request.body)?;
+    let (body_items, other_items): (Vec<_>, Vec<_>) = items.into_iter().partition(RequestItem::is_body);
+
+    for item in &other_items {
+        if let RequestItem::Query(name, value) = item {
+            url.query_pairs_mut().append_pair(name, value);
+        }
+    }
+
+    let mut request_builder = client.request(method, url);
 
     // Add headers
+    let mut headers = HeaderMap::new();
     for header in request.headers {
         let parts: Vec<&str> = header.splitn(2, ':').collect();
         if parts.len() == 2 {
-            request_builder = request_builder.header(parts[0].trim(), parts[1].trim());
+            headers.append(parts[0].trim().parse::<HeaderName>()?, parts[1].trim().parse()?);
         } else {
             return Err(anyhow!("Invalid header format: {}", header));
         }
     }
+    for item in other_items {
+        if let RequestItem::Header(name, value) = item {
+            headers.append(name.parse::<HeaderName>()?, value.parse()?);
+        }
+    }
+    if request.response_encoding != ResponseEncoding::AsIs {
+        headers.entry(ACCEPT_ENCODING).or_insert(HeaderValue::from_static(compression::ACCEPT_ENCODING));
+    }
+    let has_content_type = headers.contains_key(CONTENT_TYPE);
+    request_builder = request_builder.headers(headers);
+
+    match request.auth {
+        Some(Auth::Basic { username, password }) => {
+            request_builder = request_builder.basic_auth(username, Some(password));
+        }
+        Some(Auth::Bearer(token)) => {
+            request_builder = request_builder.bearer_auth(token);
+        }
+        Some(Auth::Digest { .. }) | None => {}
+    }
 
     // Prepare body
-    let body_content: Option<String> = if let Some(file_path) = request.data_file {
-        Some(fs::read_to_string(file_path)?)
-    } else if !request.body.is_empty() {
-        let mut body_map = Map::new();
-        for item in request.body {
-            let (key, value) = parse_request_item(&item)?;
-            body_map.insert(key, value);
-        }
-        Some(serde_json::to_string(&body_map)?)
-    } else {
-        None
+    let raw_body = match request.data_file {
+        Some(file_path) => {
+            let bytes = fs::read(&file_path)
+                .map_err(|e| anyhow!("Failed to read data file '{}': {}", file_path.display(), e))?;
+            Some((content::infer_content_type(Some(&file_path), &bytes), bytes))
+        }
+        None => request.raw_body.map(|bytes| (content::infer_content_type(None, &bytes), bytes)),
     };
 
-    if let Some(body) = body_content {
-        request_builder = request_builder.header("Content-Type", "application/json");
-        request_builder = request_builder.body(body);
-    }
+    if let Some((content_type, bytes)) = raw_body {
+        if !has_content_type {
+            request_builder = request_builder.header(CONTENT_TYPE, content_type);
+        }
+        request_builder = request_builder.body(bytes);
+    } else if !body_items.is_empty() {
+        let has_files = body_items.iter().any(|item| matches!(item, RequestItem::File { .. }));
 
-    let response = request_builder.send().await?;
-    
-    let status = response.status().to_string();
-    let headers = response.headers().clone();
-    let body = response.text().await?;
+        match request.body_mode {
+            BodyMode::Json if has_files => {
+                return Err(anyhow!("File fields require --form or --multipart."));
+            }
+            BodyMode::Json => {
+                if !has_content_type {
+                    request_builder = request_builder.header(CONTENT_TYPE, "application/json");
+                }
+                request_builder = request_builder.body(serde_json::to_string(&build_json_body(&body_items)?)?);
+            }
+            BodyMode::Form if !has_files => {
+                let fields: Vec<(String, String)> = body_items.into_iter()
+                    .filter_map(|item| match item {
+                        RequestItem::Data(key, value) => Some((key, form_value(&value))),
+                        _ => None,
+                    })
+                    .collect();
+                request_builder = request_builder.form(&fields);
+            }
+            BodyMode::Form | BodyMode::Multipart => {
+                request_builder = request_builder.multipart(build_multipart(body_items).await?);
+            }
+        }
+    }
 
-    Ok(HttpResponse { status, headers, body })
+    Ok(request_builder)
 }
 
-fn parse_request_item(item: &str) -> Result<(String, Value)> {
-    if let Some((key, value)) = item.split_once(":=") {
-        let parsed_value: Value = serde_json::from_str(value)
-            .map_err(|e| anyhow!("Invalid JSON value for key '{}': {}", key, e))?;
-        Ok((key.to_string(), parsed_value))
-    } else if let Some((key, value)) = item.split_once('=') {
-        Ok((key.to_string(), Value::String(value.to_string())))
-    } else {
-        Err(anyhow!("Invalid request item format: '{}'. Use key=value or key:=json_value.", item))
-    }
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_interface(builder: reqwest::ClientBuilder, interface: &str) -> Result<reqwest::ClientBuilder> {
+    Ok(builder.interface(interface))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-
-    #[test]
-    fn test_parse_request_item_string() {
-        let (key, value) = parse_request_item("name=Varyag").unwrap();
-        assert_eq!(key, "name");
-        assert_eq!(value, Value::String("Varyag".to_string()));
-    }
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_interface(_builder: reqwest::ClientBuilder, _interface: &str) -> Result<reqwest::ClientBuilder> {
+    Err(anyhow!("Binding to an interface by name is only supported on Linux; use --local-address instead."))
+}
 
-    #[test]
-    fn test_parse_request_item_json_object() {
-        let (key, value) = parse_request_item(r#"data:={"id":123,"active":true}"#).unwrap();
-        assert_eq!(key, "data");
-        assert_eq!(value, json!({"id": 123, "active": true}));
+/// Names a protocol version the way it appears on a status line, e.g. `HTTP/1.1` or `HTTP/2`.
+pub(crate) fn version_name(version: Version) -> String {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP",
     }
+    .to_string()
+}
 
-    #[test]
-    fn test_parse_request_item_json_array() {
-        let (key, value) = parse_request_item(r#"items:=[1, "two", 3.0]"#).unwrap();
-        assert_eq!(key, "items");
-        assert_eq!(value, json!([1, "two", 3.0]));
-    }
+/// Flattens headers into printable `(name, value)` pairs; non-UTF-8 values become empty.
+pub fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect()
+}
 
-    #[test]
-    fn test_parse_request_item_invalid_json() {
-        let result = parse_request_item(r#"data:={"id":123,"active":true"#); // Missing closing brace
-        assert!(result.is_err());
-        let error = result.unwrap_err();
-        assert!(error.to_string().contains("Invalid JSON value for key 'data'"));
+/// Builds a multipart form, streaming file parts from disk.
+async fn build_multipart(items: Vec<RequestItem>) -> Result<multipart::Form> {
+    let mut form = multipart::Form::new();
+    for item in items {
+        match item {
+            RequestItem::Data(key, value) => {
+                form = form.text(key, form_value(&value));
+            }
+            RequestItem::File { field, path, mime } => {
+                // `Part::file` streams the file and infers its MIME type from the extension.
+                let mut part = multipart::Part::file(&path).await
+                    .map_err(|e| anyhow!("Failed to open file '{}': {}", path.display(), e))?;
+                if let Some(mime) = mime {
+                    part = part.mime_str(&mime)?;
+                }
+                form = form.part(field, part);
+            }
+            RequestItem::Query(..) | RequestItem::Header(..) => {}
+        }
     }
+    Ok(form)
+}
 
-    #[test]
-    fn test_parse_request_item_invalid_format() {
-        let result = parse_request_item("name:Varyag"); // Missing '=' or ':='
-        assert!(result.is_err());
-        let error = result.unwrap_err();
-        assert!(error.to_string().contains("Invalid request item format"));
+/// Renders a body value as a plain form field; non-string JSON is sent as its JSON text.
+fn form_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
     }
 }
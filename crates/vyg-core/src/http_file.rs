@@ -0,0 +1,462 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::http_client::{self, HttpRequest, HttpResponse};
+use crate::json_path::{self, JsonPath};
+
+const METHODS: &[&str] = &["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "TRACE", "CONNECT"];
+
+/// How deeply file variables may refer to each other, to stop cycles.
+const MAX_VARIABLE_DEPTH: usize = 16;
+
+/// A file of requests in the REST Client (`.http`) format: requests separated by `###` lines,
+/// `@name = value` file variables and `{{name}}` references to them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpFile {
+    /// File variables, with references in their values left unexpanded.
+    pub variables: HashMap<String, String>,
+    pub requests: Vec<FileRequest>,
+}
+
+/// One request of an [`HttpFile`], before variables are expanded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileRequest {
+    /// Set with `# @name NAME`, or taken from the text after `###`.
+    pub name: Option<String>,
+    /// The line of the request line, counting from 1.
+    pub line: usize,
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<FileBody>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileBody {
+    Text(String),
+    /// `< path` sends a file as is; `<@ path` expands variables in it first.
+    File { path: PathBuf, expand: bool },
+}
+
+impl FileRequest {
+    /// The name, or the request line when the request has none.
+    pub fn title(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("{} {}", self.method, self.url))
+    }
+}
+
+impl HttpFile {
+    /// Reads and parses `path`; body files are resolved relative to its directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let mut file = HttpFile::parse(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for request in &mut file.requests {
+            if let Some(FileBody::File { path, .. }) = &mut request.body {
+                *path = base.join(&*path);
+            }
+        }
+        Ok(file)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut file = HttpFile::default();
+        let mut block: Vec<(usize, &str)> = Vec::new();
+        let mut title = None;
+        for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+            if let Some(rest) = line.strip_prefix("###") {
+                file.parse_block(&block, title.take())?;
+                block.clear();
+                title = Some(rest.trim().to_string()).filter(|title| !title.is_empty());
+            } else {
+                block.push((number, line));
+            }
+        }
+        file.parse_block(&block, title)?;
+        Ok(file)
+    }
+
+    fn parse_block(&mut self, lines: &[(usize, &str)], title: Option<String>) -> Result<()> {
+        let mut lines = lines.iter().copied().peekable();
+        let mut name = None;
+
+        // Comments, file variables and the request line.
+        let (line, request_line) = loop {
+            let Some((number, line)) = lines.next() else {
+                return Ok(());
+            };
+            let trimmed = line.trim();
+            if let Some(comment) = trimmed.strip_prefix('#').or_else(|| trimmed.strip_prefix("//")) {
+                if let Some(tag) = comment.trim().strip_prefix("@name") {
+                    name = Some(tag.trim().to_string()).filter(|name| !name.is_empty());
+                }
+            } else if let Some(definition) = trimmed.strip_prefix('@') {
+                let (variable, value) = definition
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("line {}: expected '@NAME = VALUE'", number))?;
+                self.variables.insert(variable.trim().to_string(), value.trim().to_string());
+            } else if !trimmed.is_empty() {
+                break (number, trimmed);
+            }
+        };
+        let (method, mut url) = match request_line.split_once(char::is_whitespace) {
+            Some((method, target)) if METHODS.contains(&method) => (method.to_string(), target.trim().to_string()),
+            _ => ("GET".to_string(), request_line.to_string()),
+        };
+        if let Some((target, version)) = url.rsplit_once(' ') {
+            if version.starts_with("HTTP/") {
+                url = target.trim_end().to_string();
+            }
+        }
+        // A query string may continue on the following lines.
+        while let Some((_, part)) = lines.next_if(|(_, line)| line.trim_start().starts_with(['?', '&'])) {
+            url.push_str(part.trim());
+        }
+
+        let mut headers = Vec::new();
+        for (number, line) in lines.by_ref() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                break;
+            }
+            if trimmed.starts_with('#') || trimmed.starts_with("//") {
+                continue;
+            }
+            let (header, value) = trimmed
+                .split_once(':')
+                .ok_or_else(|| anyhow!("line {}: expected a 'Name: value' header, or a blank line before the body", number))?;
+            headers.push((header.trim().to_string(), value.trim().to_string()));
+        }
+
+        let body_lines: Vec<&str> = lines.map(|(_, line)| line).collect();
+        let body = body_lines.join("\n").trim_end().to_string();
+        let body = if let Some(path) = body.strip_prefix("<@") {
+            Some(FileBody::File { path: PathBuf::from(path.trim()), expand: true })
+        } else if let Some(path) = body.strip_prefix('<').filter(|path| path.starts_with(char::is_whitespace)) {
+            Some(FileBody::File { path: PathBuf::from(path.trim()), expand: false })
+        } else {
+            Some(FileBody::Text(body)).filter(|_| !body_lines.iter().all(|line| line.trim().is_empty()))
+        };
+
+        self.requests.push(FileRequest { name: name.or(title), line, method, url, headers, body });
+        Ok(())
+    }
+
+    /// The requests to run, in file order: all of them, or the one called `name` and the named requests it takes values from.
+    pub fn plan(&self, name: Option<&str>) -> Result<Vec<&FileRequest>> {
+        let Some(name) = name else {
+            return Ok(self.requests.iter().collect());
+        };
+        let mut wanted = BTreeSet::from([name.to_string()]);
+        let mut pending = vec![self.named(name)?];
+        while let Some(request) = pending.pop() {
+            let mut references = BTreeSet::new();
+            for text in request_texts(request) {
+                self.references(text, &mut references, 0);
+            }
+            for reference in references {
+                if wanted.insert(reference.clone()) {
+                    pending.push(self.named(&reference)?);
+                }
+            }
+        }
+        Ok(self.requests.iter().filter(|request| request.name.as_ref().is_some_and(|name| wanted.contains(name))).collect())
+    }
+
+    fn named(&self, name: &str) -> Result<&FileRequest> {
+        self.requests.iter().find(|request| request.name.as_deref() == Some(name)).ok_or_else(|| {
+            let names: Vec<&str> = self.requests.iter().filter_map(|request| request.name.as_deref()).collect();
+            anyhow!("No request named '{}'. Named requests: {}", name, if names.is_empty() { "none".to_string() } else { names.join(", ") })
+        })
+    }
+
+    /// Collects the requests whose responses `text` refers to, directly or through file variables.
+    fn references(&self, text: &str, names: &mut BTreeSet<String>, depth: usize) {
+        for reference in references(text) {
+            if let Some((request, _)) = reference.split_once('.').filter(|(request, _)| self.requests.iter().any(|r| r.name.as_deref() == Some(*request))) {
+                names.insert(request.to_string());
+            } else if let Some(value) = self.variables.get(reference).filter(|_| depth < MAX_VARIABLE_DEPTH) {
+                self.references(value, names, depth + 1);
+            }
+        }
+    }
+}
+
+fn request_texts(request: &FileRequest) -> impl Iterator<Item = &str> {
+    let body = match &request.body {
+        Some(FileBody::Text(text)) => Some(text.as_str()),
+        _ => None,
+    };
+    [request.url.as_str()].into_iter().chain(request.headers.iter().map(|(_, value)| value.as_str())).chain(body)
+}
+
+/// The trimmed insides of the `{{...}}` references in `text`.
+fn references(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{").skip(1).filter_map(|part| part.split_once("}}")).map(|(inside, _)| inside.trim())
+}
+
+/// What a run has to expand `{{...}}` references with: file variables, overrides and earlier exchanges.
+#[derive(Debug, Default)]
+pub struct Variables {
+    file: HashMap<String, String>,
+    /// Values given on the command line, which take precedence over file variables.
+    overrides: HashMap<String, String>,
+    /// The exchanges of named requests that have run.
+    exchanges: HashMap<String, Recorded>,
+    /// Every named request in the file, to tell a reference to one that has not run from a typo.
+    requests: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+struct Recorded {
+    request_headers: Vec<(String, String)>,
+    request_body: String,
+    response_headers: Vec<(String, String)>,
+    response_body: String,
+}
+
+impl Variables {
+    pub fn new(file: &HttpFile, overrides: HashMap<String, String>) -> Self {
+        Variables {
+            file: file.variables.clone(),
+            overrides,
+            exchanges: HashMap::new(),
+            requests: file.requests.iter().filter_map(|request| request.name.clone()).collect(),
+        }
+    }
+
+    /// Variables defined outside a request file, e.g. by a test scenario. Like file variables,
+    /// their values may refer to each other.
+    pub fn from_definitions(definitions: HashMap<String, String>) -> Self {
+        Variables { file: definitions, ..Default::default() }
+    }
+
+    /// Sets `name` to `value` as is, taking precedence over definitions.
+    pub fn set(&mut self, name: &str, value: String) {
+        self.overrides.insert(name.to_string(), value);
+    }
+
+    /// Keeps the exchange of the request called `name` for later `{{name.response...}}` references.
+    pub fn record(&mut self, name: &str, response: &HttpResponse) {
+        let recorded = Recorded {
+            request_headers: http_client::header_pairs(&response.request.headers),
+            request_body: String::from_utf8_lossy(response.request.body.as_deref().unwrap_or_default()).into_owned(),
+            response_headers: http_client::header_pairs(&response.headers),
+            response_body: response.text().into_owned(),
+        };
+        self.exchanges.insert(name.to_string(), recorded);
+    }
+
+    /// Replaces every `{{...}}` reference in `text`.
+    pub fn expand(&self, text: &str) -> Result<String> {
+        self.expand_at(text, 0)
+    }
+
+    fn expand_at(&self, text: &str, depth: usize) -> Result<String> {
+        let mut expanded = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..].find("}}").ok_or_else(|| anyhow!("Unclosed '{{{{' in '{}'", text))? + start;
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(&self.resolve(rest[start + 2..end].trim(), depth)?);
+            rest = &rest[end + 2..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    fn resolve(&self, reference: &str, depth: usize) -> Result<String> {
+        if let Some(system) = reference.strip_prefix('$') {
+            return system_variable(system);
+        }
+        if let Some(value) = self.overrides.get(reference) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = self.file.get(reference) {
+            if depth >= MAX_VARIABLE_DEPTH {
+                bail!("Variable '{}' refers to itself", reference);
+            }
+            return self.expand_at(value, depth + 1);
+        }
+        if let Some((request, path)) = reference.split_once('.').filter(|(request, _)| self.requests.contains(*request)) {
+            let recorded = self
+                .exchanges
+                .get(request)
+                .ok_or_else(|| anyhow!("'{{{{{}}}}}' refers to request '{}', which has not run yet", reference, request))?;
+            return recorded.lookup(path).map_err(|e| anyhow!("'{{{{{}}}}}': {}", reference, e));
+        }
+        bail!("Undefined variable '{}'", reference)
+    }
+}
+
+impl Recorded {
+    /// Looks up `(request|response).(body|headers).(*|JSONPATH|HEADER)`.
+    fn lookup(&self, path: &str) -> Result<String> {
+        let mut parts = path.splitn(3, '.');
+        let (message, part, selector) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(message), Some(part), Some(selector)) => (message, part, selector),
+            _ => bail!("expected NAME.(request|response).(body|headers).(*|JSONPATH|HEADER)"),
+        };
+        let (headers, body) = match message {
+            "request" => (&self.request_headers, &self.request_body),
+            "response" => (&self.response_headers, &self.response_body),
+            other => bail!("expected 'request' or 'response', found '{}'", other),
+        };
+        match part {
+            "headers" => headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(selector))
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| anyhow!("no '{}' header in the {}", selector, message)),
+            "body" if selector == "*" => Ok(body.clone()),
+            "body" => {
+                let path = JsonPath::parse(selector)?;
+                let json: Value = serde_json::from_str(body).map_err(|_| anyhow!("the {} body is not JSON", message))?;
+                path.first(&json).map(json_path::to_text).ok_or_else(|| anyhow!("{} matches nothing in the {} body", selector, message))
+            }
+            other => bail!("expected 'body' or 'headers', found '{}'", other),
+        }
+    }
+}
+
+/// `$guid`, `$timestamp`, `$randomInt MIN MAX` and `$processEnv NAME`.
+fn system_variable(reference: &str) -> Result<String> {
+    let mut words = reference.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let arguments: Vec<&str> = words.collect();
+    match (name, arguments.as_slice()) {
+        ("guid", []) => {
+            // A random (version 4) UUID.
+            let bits = (fastrand::u128(..) & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+            let hex = format!("{:032x}", bits);
+            Ok(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+        }
+        ("timestamp", []) => Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs().to_string()),
+        ("randomInt", [min, max]) => {
+            let (min, max) = (min.parse::<i64>()?, max.parse::<i64>()?);
+            if min >= max {
+                bail!("$randomInt needs MIN < MAX");
+            }
+            Ok(fastrand::i64(min..max).to_string())
+        }
+        ("processEnv", [variable]) => std::env::var(variable).map_err(|_| anyhow!("Environment variable {} is not set", variable)),
+        _ => bail!("Unknown system variable '${}'. Use $guid, $timestamp, $randomInt MIN MAX or $processEnv NAME", reference),
+    }
+}
+
+impl FileRequest {
+    /// The request with its variables expanded, based on `template` for everything the file cannot set.
+    pub fn to_http_request(&self, variables: &Variables, template: &HttpRequest) -> Result<HttpRequest> {
+        let url = variables.expand(&self.url)?;
+        let url = if url.contains("://") { url } else { format!("http://{}", url) };
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| Ok(format!("{}: {}", name, variables.expand(value)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let (raw_body, data_file) = match &self.body {
+            None => (None, None),
+            Some(FileBody::Text(text)) => (Some(variables.expand(text)?.into_bytes()), None),
+            Some(FileBody::File { path, expand: false }) => (None, Some(path.clone())),
+            Some(FileBody::File { path, expand: true }) => {
+                let text = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+                (Some(variables.expand(&text)?.into_bytes()), None)
+            }
+        };
+        Ok(HttpRequest { url, method: self.method.clone(), headers, raw_body, data_file, ..template.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+@host = api.example.com
+@base = https://{{host}}/v1
+
+### Log in
+# @name login
+POST {{base}}/login HTTP/1.1
+Content-Type: application/json
+
+{\"user\": \"ada\"}
+
+###
+GET {{base}}/users
+    ?page=2
+    &limit=10
+Authorization: Bearer {{login.response.body.$.token}}
+
+### upload
+PUT {{base}}/files
+// a comment
+Content-Type: text/plain
+
+< ./notes.txt
+";
+
+    #[test]
+    fn test_parse() {
+        let file = HttpFile::parse(FILE).unwrap();
+        assert_eq!(file.variables["base"], "https://{{host}}/v1");
+        assert_eq!(file.requests.len(), 3);
+
+        let login = &file.requests[0];
+        assert_eq!((login.name.as_deref(), login.line, login.method.as_str()), (Some("login"), 6, "POST"));
+        assert_eq!(login.url, "{{base}}/login");
+        assert_eq!(login.body, Some(FileBody::Text("{\"user\": \"ada\"}".to_string())));
+
+        let users = &file.requests[1];
+        assert_eq!((users.name.as_deref(), users.method.as_str()), (None, "GET"));
+        assert_eq!(users.url, "{{base}}/users?page=2&limit=10");
+        assert_eq!(users.headers, [("Authorization".to_string(), "Bearer {{login.response.body.$.token}}".to_string())]);
+        assert_eq!(users.body, None);
+
+        let upload = &file.requests[2];
+        assert_eq!(upload.name.as_deref(), Some("upload"));
+        assert_eq!(upload.body, Some(FileBody::File { path: PathBuf::from("./notes.txt"), expand: false }));
+
+        assert!(HttpFile::parse("GET /\nnot a header").unwrap_err().to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_plan_includes_referenced_requests() {
+        let file = HttpFile::parse(&format!("{}\n### me\n# @name me\nGET {{{{base}}}}/me\nAuthorization: {{{{login.response.headers.X-Token}}}}", FILE)).unwrap();
+        let titles: Vec<String> = file.plan(Some("me")).unwrap().iter().map(|request| request.title()).collect();
+        assert_eq!(titles, ["login", "me"]);
+        assert_eq!(file.plan(None).unwrap().len(), 4);
+        assert_eq!(file.plan(Some("nope")).unwrap_err().to_string(), "No request named 'nope'. Named requests: login, upload, me");
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let file = HttpFile::parse(FILE).unwrap();
+        let overrides = HashMap::from([("host".to_string(), "localhost:8080".to_string())]);
+        let mut variables = Variables::new(&file, overrides);
+        assert_eq!(variables.expand("{{base}}/login").unwrap(), "https://localhost:8080/v1/login");
+        assert!(variables.expand("{{login.response.body.$.token}}").unwrap_err().to_string().contains("has not run yet"));
+        assert_eq!(variables.expand("{{missing}}").unwrap_err().to_string(), "Undefined variable 'missing'");
+
+        variables.exchanges.insert(
+            "login".to_string(),
+            Recorded {
+                request_headers: vec![],
+                request_body: String::new(),
+                response_headers: vec![("x-token".to_string(), "t1".to_string())],
+                response_body: r#"{"token": "abc", "user": {"id": 7}}"#.to_string(),
+            },
+        );
+        assert_eq!(variables.expand("Bearer {{ login.response.body.$.token }}").unwrap(), "Bearer abc");
+        assert_eq!(variables.expand("{{login.response.body.$.user}}").unwrap(), r#"{"id":7}"#);
+        assert_eq!(variables.expand("{{login.response.headers.X-Token}}").unwrap(), "t1");
+
+        let guid = variables.expand("{{$guid}}").unwrap();
+        assert_eq!((guid.len(), &guid[14..15]), (36, "4"));
+        let looping = HttpFile::parse("@a = {{b}}\n@b = {{a}}").unwrap();
+        assert!(Variables::new(&looping, HashMap::new()).expand("{{a}}").unwrap_err().to_string().contains("refers to itself"));
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+
+/// A compiled JSONPath expression, e.g. `$.users[0].name` or `$..id`.
+///
+/// Supports the root `$`, `.name` and `['name']` children, `[n]` indexes (negative from the end),
+/// `[start:end]` slices, `*` wildcards and `..` recursive descent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Child(Selector),
+    /// The selector applied to the value and all of its descendants.
+    Descendant(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self> {
+        let invalid = |reason: &str| anyhow!("Invalid JSONPath '{}': {}", path, reason);
+        let rest = path.trim().strip_prefix('$').ok_or_else(|| invalid("it must start with '$'"))?;
+        let mut chars = rest.chars().peekable();
+        let mut segments = Vec::new();
+        while let Some(c) = chars.next() {
+            let descendant = c == '.' && chars.peek() == Some(&'.');
+            if descendant {
+                chars.next();
+            }
+            let selector = match (c, chars.peek()) {
+                ('.', Some('[')) if descendant => {
+                    chars.next();
+                    bracket(&mut chars).map_err(|e| invalid(&e.to_string()))?
+                }
+                ('.', Some('*')) => {
+                    chars.next();
+                    Selector::Wildcard
+                }
+                ('.', _) => {
+                    let mut name = String::new();
+                    while let Some(&c) = chars.peek().filter(|&&c| c != '.' && c != '[') {
+                        name.push(c);
+                        chars.next();
+                    }
+                    if name.is_empty() {
+                        return Err(invalid("expected a name after '.'"));
+                    }
+                    Selector::Name(name)
+                }
+                ('[', _) => bracket(&mut chars).map_err(|e| invalid(&e.to_string()))?,
+                (other, _) => return Err(invalid(&format!("unexpected '{}'", other))),
+            };
+            segments.push(if descendant { Segment::Descendant(selector) } else { Segment::Child(selector) });
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// The values the path selects in `root`, in document order.
+    pub fn query<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            current = match segment {
+                Segment::Child(selector) => current.into_iter().flat_map(|value| select(selector, value)).collect(),
+                Segment::Descendant(selector) => current
+                    .into_iter()
+                    .flat_map(descendants)
+                    .flat_map(|value| select(selector, value))
+                    .collect(),
+            };
+        }
+        current
+    }
+
+    /// The first value the path selects in `root`.
+    pub fn first<'a>(&self, root: &'a Value) -> Option<&'a Value> {
+        self.query(root).into_iter().next()
+    }
+}
+
+/// Parses the inside of `[...]`, after the opening bracket.
+fn bracket(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Selector> {
+    let mut inside = String::new();
+    let mut quote = None;
+    let mut quoted = false;
+    loop {
+        match chars.next() {
+            None => bail!("unclosed '['"),
+            Some(c) if Some(c) == quote => quote = None,
+            Some(c @ ('\'' | '"')) if quote.is_none() => {
+                quote = Some(c);
+                quoted = true;
+            }
+            Some(']') if quote.is_none() => break,
+            Some(c) => inside.push(c),
+        }
+    }
+    if quoted {
+        return Ok(Selector::Name(inside));
+    }
+    let raw = inside.trim();
+    let parse_index = |text: &str| text.trim().parse::<i64>().map_err(|_| anyhow!("invalid index '{}'", text));
+    Ok(if raw == "*" {
+        Selector::Wildcard
+    } else if let Some((start, end)) = raw.split_once(':') {
+        let bound = |text: &str| if text.trim().is_empty() { Ok(None) } else { parse_index(text).map(Some) };
+        Selector::Slice(bound(start)?, bound(end)?)
+    } else {
+        Selector::Index(parse_index(raw)?)
+    })
+}
+
+fn select<'a>(selector: &Selector, value: &'a Value) -> Vec<&'a Value> {
+    match (selector, value) {
+        (Selector::Name(name), Value::Object(fields)) => fields.get(name).into_iter().collect(),
+        (Selector::Index(index), Value::Array(items)) => resolve_index(*index, items.len()).and_then(|i| items.get(i)).into_iter().collect(),
+        (Selector::Slice(start, end), Value::Array(items)) => {
+            let len = items.len();
+            let start = start.map_or(0, |start| resolve_index(start, len).unwrap_or(if start < 0 { 0 } else { len }));
+            let end = end.map_or(len, |end| resolve_index(end, len).unwrap_or(if end < 0 { 0 } else { len }));
+            items.get(start..end.max(start)).unwrap_or_default().iter().collect()
+        }
+        (Selector::Wildcard, Value::Array(items)) => items.iter().collect(),
+        (Selector::Wildcard, Value::Object(fields)) => fields.values().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// An index counted from the end when negative, if it is within `len`.
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    usize::try_from(resolved).ok().filter(|&i| i < len)
+}
+
+/// `value` followed by all values nested in it, depth first.
+fn descendants(value: &Value) -> Vec<&Value> {
+    let mut all = vec![value];
+    match value {
+        Value::Array(items) => all.extend(items.iter().flat_map(descendants)),
+        Value::Object(fields) => all.extend(fields.values().flat_map(descendants)),
+        _ => {}
+    }
+    all
+}
+
+/// A selected value as text: strings without quotes, everything else as JSON.
+pub fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(path: &str, value: &Value) -> Vec<Value> {
+        JsonPath::parse(path).unwrap().query(value).into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let doc = json!({
+            "token": "abc",
+            "users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Lin", "tags": ["x"]}],
+            "a.b": {"id": 3}
+        });
+        assert_eq!(query("$.token", &doc), [json!("abc")]);
+        assert_eq!(query("$.users[-1].name", &doc), [json!("Lin")]);
+        assert_eq!(query("$['users'][0]['name']", &doc), [json!("Ada")]);
+        assert_eq!(query("$.users[*].id", &doc), [json!(1), json!(2)]);
+        assert_eq!(query("$.users[1:].tags[0]", &doc), [json!("x")]);
+        assert_eq!(query("$..id", &doc), [json!(3), json!(1), json!(2)]);
+        assert_eq!(query("$['a.b'].id", &doc), [json!(3)]);
+        assert_eq!(query("$", &doc), std::slice::from_ref(&doc));
+        assert!(query("$.missing[0]", &doc).is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(JsonPath::parse("users[0]").unwrap_err().to_string().contains("must start with '$'"));
+        assert!(JsonPath::parse("$.users[0").is_err());
+        assert!(JsonPath::parse("$.").is_err());
+        assert_eq!(to_text(&json!("abc")), "abc");
+        assert_eq!(to_text(&json!({"a": 1})), r#"{"a":1}"#);
+    }
+}
@@ -1,5 +1,33 @@
+pub mod auth;
+pub mod bench;
+pub mod certificate;
+pub mod compression;
+pub mod content;
+pub mod cookie_jar;
+pub mod curl_import;
+pub mod download;
+pub mod event_stream;
+pub mod export;
+pub mod filter;
+pub mod graphql;
+pub mod histogram;
 pub mod http_client;
+pub mod http_file;
+pub mod json_path;
 pub mod net_client;
 pub mod net_listener;
+pub mod proxy;
+pub mod redirect;
+pub mod request_items;
+pub mod resolve;
+pub mod retry;
+pub mod scenario;
+pub mod session;
+pub mod test_report;
+pub mod timing;
+pub mod tls;
+pub mod transport;
 pub mod tunnel_client;
+pub mod unix_socket;
 pub mod ws_client;
+pub mod write_out;
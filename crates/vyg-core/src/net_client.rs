@@ -1,14 +1,46 @@
-use tokio::net::{TcpStream, UdpSocket};
-use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
 use anyhow::{anyhow, Result};
 use vyg_display::logger;
+use crate::retry::retry;
+use crate::transport::{connect_tcp, connect_udp, with_timeout, TransportOptions};
+use crate::unix_socket;
 
-pub async fn send_tcp_request(address: &str, data: Vec<u8>, interactive: bool) -> Result<()> {
-    let stream = TcpStream::connect(address).await?;
+/// The most of a TCP reply that [`request_tcp`] reads; anything beyond is left unread.
+const MAX_REPLY: usize = 16 * 1024 * 1024;
+
+/// Without a timeout, how long [`request_tcp`] waits for more of a reply once part of it arrived.
+const REPLY_IDLE: Duration = Duration::from_secs(1);
+
+/// Sends `data` and prints the reply. The timeout applies to waiting for the reply,
+/// not to interactive sessions.
+pub async fn send_tcp_request(address: &str, data: Vec<u8>, interactive: bool, options: &TransportOptions) -> Result<()> {
+    let stream = connect_tcp(address, options).await?;
     logger::info(&format!("Connected to {}", address));
+    exchange(stream, data, interactive, options).await
+}
+
+/// Like [`send_tcp_request`], over the Unix domain stream socket at `path`.
+#[cfg(unix)]
+pub async fn send_unix_request(path: &Path, data: Vec<u8>, interactive: bool, options: &TransportOptions) -> Result<()> {
+    let stream = unix_socket::connect(path, options).await?;
+    logger::info(&format!("Connected to {}", path.display()));
+    exchange(stream, data, interactive, options).await
+}
+
+#[cfg(not(unix))]
+pub async fn send_unix_request(path: &Path, _data: Vec<u8>, _interactive: bool, _options: &TransportOptions) -> Result<()> {
+    Err(unix_socket::unsupported(path))
+}
 
+async fn exchange<S>(stream: S, data: Vec<u8>, interactive: bool, options: &TransportOptions) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
     if interactive {
-        let (mut reader, mut writer) = stream.into_split();
+        let (mut reader, mut writer) = io::split(stream);
         
         let write_task = tokio::spawn(async move {
             io::copy(&mut io::stdin(), &mut writer).await
@@ -32,50 +64,72 @@ pub async fn send_tcp_request(address: &str, data: Vec<u8>, interactive: bool) -
         }
 
     } else {
-        let mut stream = stream;
-        if !data.is_empty() {
-            stream.write_all(&data).await?;
-        }
-        
-        let mut buffer = vec![0; 1024];
-        match stream.read(&mut buffer).await {
-            Ok(0) => {
-                // Connection closed
-            },
-            Ok(n) => {
-                io::stdout().write_all(&buffer[..n]).await?;
-            },
-            Err(e) => {
-                return Err(e.into());
-            }
-        }
+        let reply = reply(&mut Box::pin(stream), data, options).await?;
+        io::stdout().write_all(&reply).await?;
     }
 
     Ok(())
 }
 
-pub async fn send_udp_request(address: &str, data: Vec<u8>) -> Result<()> {
-    // We need a local address to bind to. 0.0.0.0:0 is a good default.
-    let socket = UdpSocket::bind("0.0.0.0:0").await?;
-    socket.connect(address).await?;
-    
-    if data.is_empty() {
-        return Err(anyhow!("UDP mode requires data to send."));
+/// Connects to `address`, sends `data` and returns the reply.
+///
+/// Unlike `send`, which prints the first chunk, this reads until the peer closes the connection,
+/// the timeout elapses or `MAX_REPLY` bytes arrived; without a timeout, also until the peer
+/// pauses for `REPLY_IDLE`.
+pub async fn request_tcp(address: &str, data: Vec<u8>, options: &TransportOptions) -> Result<Vec<u8>> {
+    let mut stream = connect_tcp(address, options).await?;
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut reply = reply(&mut stream, data, options).await?;
+    let mut buffer = vec![0; 8192];
+    while !reply.is_empty() && reply.len() < MAX_REPLY {
+        let wait = deadline.map_or(REPLY_IDLE, |deadline| deadline.saturating_duration_since(Instant::now()));
+        match tokio::time::timeout(wait, stream.read(&mut buffer)).await {
+            Ok(Ok(0)) | Err(_) => break,
+            Ok(Ok(n)) => reply.extend_from_slice(&buffer[..n]),
+            Ok(Err(e)) => return Err(e.into()),
+        }
     }
+    reply.truncate(MAX_REPLY);
+    Ok(reply)
+}
 
-    socket.send(&data).await?;
+/// Sends `data` and waits for the first chunk of the reply, which is empty when the
+/// peer closed the connection without replying.
+async fn reply<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, data: Vec<u8>, options: &TransportOptions) -> Result<Vec<u8>> {
+    if !data.is_empty() {
+        stream.write_all(&data).await?;
+    }
 
     let mut buffer = vec![0; 1024];
-    match socket.recv(&mut buffer).await {
-        Ok(n) => {
-            io::stdout().write_all(&buffer[..n]).await?;
-        }
-        Err(e) => {
-            // This can be a timeout, which is not necessarily an error for UDP.
-            // For now, we'll print it.
-            logger::warn(&format!("Error receiving UDP response: {}", e));
-        }
+    let n = with_timeout(options.timeout, "Waiting for a TCP reply", async {
+        Ok(stream.read(&mut buffer).await?)
+    })
+    .await?;
+    buffer.truncate(n);
+    Ok(buffer)
+}
+
+/// Sends a datagram and prints the reply. With retries enabled, the datagram is
+/// sent again whenever no reply arrives within the timeout.
+pub async fn send_udp_request(address: &str, data: Vec<u8>, options: &TransportOptions) -> Result<()> {
+    if data.is_empty() {
+        return Err(anyhow!("UDP mode requires data to send."));
     }
 
+    let socket = connect_udp(address, options).await?;
+
+    let reply = retry(&options.retry, || async {
+        socket.send(&data).await?;
+        with_timeout(options.timeout, "Waiting for a UDP reply", async {
+            let mut buffer = vec![0; 1024];
+            let n = socket.recv(&mut buffer).await?;
+            buffer.truncate(n);
+            Ok(buffer)
+        })
+        .await
+    })
+    .await?;
+    io::stdout().write_all(&reply).await?;
+
     Ok(())
 }
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::StatusCode;
+use url::Url;
+use crate::http_client::HttpRequest;
+use crate::request_items::{is_body_item, parse_request_item, RequestItem};
+use crate::timing::Timings;
+
+/// Headers that must not follow a redirect to another origin.
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "cookie2"];
+
+/// How redirect responses are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedirectOptions {
+    /// Follow redirects at all, rather than returning the 3xx response.
+    pub follow: bool,
+    /// Give up after this many hops.
+    pub max: usize,
+    /// Resend the method and body on 301, 302 and 303 too, not only on 307 and 308.
+    pub preserve_method: bool,
+}
+
+impl Default for RedirectOptions {
+    fn default() -> Self {
+        RedirectOptions { follow: true, max: 10, preserve_method: false }
+    }
+}
+
+/// A redirect response that was followed.
+#[derive(Debug, Clone)]
+pub struct RedirectHop {
+    pub version: String,
+    pub method: String,
+    pub url: String,
+    pub status: String,
+    pub headers: HeaderMap,
+    /// The absolute URL the hop pointed to.
+    pub location: String,
+    pub timings: Timings,
+}
+
+/// The absolute redirect target of a response, or `None` if it is not a redirect to follow.
+pub(crate) fn location(status: StatusCode, headers: &HeaderMap, url: &Url) -> Option<Result<Url>> {
+    if !matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = headers.get(LOCATION)?;
+    Some(
+        location
+            .to_str()
+            .map_err(anyhow::Error::from)
+            .and_then(|location| Ok(url.join(location)?))
+            .map_err(|e| anyhow!("Invalid redirect location from {}: {}", url, e)),
+    )
+}
+
+/// Turns `request` into the request for the next hop of a redirect from `from` to `to`.
+///
+/// Like browsers and curl, 303 switches to GET, and so do POSTs answered with 301 or 302,
+/// unless `preserve_method` is set. Credentials and cookies set by hand are dropped when the
+/// redirect leaves the original origin; the cookie jar still applies its own rules.
+pub(crate) fn next_request(mut request: HttpRequest, status: StatusCode, from: &Url, to: &Url) -> HttpRequest {
+    let method = request.method.to_uppercase();
+    let to_get = match status.as_u16() {
+        303 => method != "HEAD",
+        301 | 302 => method == "POST",
+        _ => false,
+    } && !request.redirect.preserve_method;
+    let cross_origin = from.origin() != to.origin();
+
+    // Query items already are part of `to`, so they must not be appended again.
+    request.body.retain(|item| {
+        if is_body_item(item) {
+            return !to_get;
+        }
+        match parse_request_item(item) {
+            Ok(RequestItem::Query(..)) => false,
+            Ok(RequestItem::Header(name, _)) => !(cross_origin && is_sensitive(&name)),
+            _ => true,
+        }
+    });
+    request.headers.retain(|header| {
+        let name = header.split(':').next().unwrap_or_default().trim();
+        let dropped = (cross_origin && is_sensitive(name)) || (to_get && name.eq_ignore_ascii_case("content-type"));
+        !dropped
+    });
+    if to_get {
+        request.method = "GET".to_string();
+        request.data_file = None;
+        request.raw_body = None;
+    }
+    if cross_origin {
+        request.auth = None;
+    }
+    request.url = to.to_string();
+    request
+}
+
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_HEADERS.iter().any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+
+    fn post() -> HttpRequest {
+        HttpRequest {
+            url: "http://a.test/form".to_string(),
+            method: "POST".to_string(),
+            headers: vec!["Content-Type: text/plain".to_string(), "Authorization: Bearer x".to_string()],
+            body: vec!["name=varyag".to_string(), "page==2".to_string(), "X-Trace:1".to_string()],
+            raw_body: None,
+            auth: Some(Auth::Bearer("x".to_string())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_request_switches_to_get() {
+        let from = Url::parse("http://a.test/form?page=2").unwrap();
+        let to = Url::parse("http://a.test/done").unwrap();
+        let next = next_request(post(), StatusCode::FOUND, &from, &to);
+        assert_eq!(next.method, "GET");
+        assert_eq!(next.url, "http://a.test/done");
+        assert_eq!(next.body, ["X-Trace:1"]);
+        assert_eq!(next.headers, ["Authorization: Bearer x"]);
+        assert!(next.auth.is_some());
+    }
+
+    #[test]
+    fn test_next_request_preserves_method_on_307() {
+        let from = Url::parse("http://a.test/form").unwrap();
+        let to = Url::parse("https://b.test/form").unwrap();
+        let next = next_request(post(), StatusCode::TEMPORARY_REDIRECT, &from, &to);
+        assert_eq!(next.method, "POST");
+        assert_eq!(next.body, ["name=varyag", "X-Trace:1"]);
+        assert_eq!(next.headers, ["Content-Type: text/plain"]);
+        assert!(next.auth.is_none());
+
+        let mut request = post();
+        request.redirect.preserve_method = true;
+        assert_eq!(next_request(request, StatusCode::SEE_OTHER, &from, &to).method, "POST");
+    }
+
+    #[test]
+    fn test_location_resolves_relative_targets() {
+        let url = Url::parse("http://a.test/a/b").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, "../c?x=1".parse().unwrap());
+        let target = location(StatusCode::MOVED_PERMANENTLY, &headers, &url).unwrap().unwrap();
+        assert_eq!(target.as_str(), "http://a.test/c?x=1");
+        assert!(location(StatusCode::NOT_MODIFIED, &headers, &url).is_none());
+        assert!(location(StatusCode::FOUND, &HeaderMap::new(), &url).is_none());
+    }
+}
@@ -0,0 +1,405 @@
+use serde_json::{Map, Value};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// A single parsed positional request item.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestItem {
+    /// `key=value`, `key:=json_value`, `key=@file.txt` or `key:=@file.json`.
+    /// The key may be a bracket path such as `user[tags][]`, see [`parse_key_path`].
+    Data(String, Value),
+    /// `field@path/to/file[;type=mime/type]`, uploaded as a multipart file part.
+    File {
+        field: String,
+        path: PathBuf,
+        mime: Option<String>,
+    },
+    /// `name==value`, appended to the URL query string.
+    Query(String, String),
+    /// `Header:value`, sent as a request header.
+    Header(String, String),
+}
+
+impl RequestItem {
+    /// Returns `true` for items that contribute to the request body.
+    pub fn is_body(&self) -> bool {
+        matches!(self, RequestItem::Data(..) | RequestItem::File { .. })
+    }
+}
+
+/// One step of a bracket key path: `user[tags][]` is `Key("user"), Key("tags"), Append`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+    Append,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Separator {
+    JsonFile,
+    Json,
+    Query,
+    DataFile,
+    Data,
+    File,
+    Header,
+}
+
+/// Item separators. When several match at the same position the longest one wins,
+/// so `:=@` beats `:=`, which beats `:`. Equal-length ties go to the earlier entry.
+const SEPARATORS: [(&str, Separator); 7] = [
+    (":=@", Separator::JsonFile),
+    (":=", Separator::Json),
+    ("==", Separator::Query),
+    ("=@", Separator::DataFile),
+    ("=", Separator::Data),
+    ("@", Separator::File),
+    (":", Separator::Header),
+];
+
+/// Splits an item at its first separator. Values may therefore contain other
+/// separators (e.g. `email=user@example.com` or `Referer:http://example.com`).
+fn split_item(item: &str) -> Option<(&str, Separator, &str)> {
+    SEPARATORS.iter()
+        .enumerate()
+        .filter_map(|(index, (token, sep))| item.find(token).map(|pos| (pos, index, *token, *sep)))
+        .min_by_key(|(pos, index, token, _)| (*pos, std::cmp::Reverse(token.len()), *index))
+        .map(|(pos, _, token, sep)| (&item[..pos], sep, &item[pos + token.len()..]))
+}
+
+fn read_item_file(key: &str, path: &str) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read file '{}' for key '{}': {}", path, key, e))
+}
+
+/// Returns `true` if the raw item would end up in the request body.
+/// Malformed items are reported later by [`parse_request_item`].
+pub fn is_body_item(item: &str) -> bool {
+    matches!(
+        split_item(item),
+        Some((
+            _,
+            Separator::JsonFile | Separator::Json | Separator::DataFile | Separator::Data | Separator::File,
+            _
+        ))
+    )
+}
+
+pub fn parse_request_item(item: &str) -> Result<RequestItem> {
+    match split_item(item) {
+        Some((key, Separator::Json, value)) => {
+            let parsed_value: Value = serde_json::from_str(value)
+                .map_err(|e| anyhow!("Invalid JSON value for key '{}': {}", key, e))?;
+            Ok(RequestItem::Data(key.to_string(), parsed_value))
+        }
+        Some((key, Separator::JsonFile, path)) => {
+            let contents = read_item_file(key, path)?;
+            let parsed_value: Value = serde_json::from_str(&contents)
+                .map_err(|e| anyhow!("Invalid JSON in file '{}' for key '{}': {}", path, key, e))?;
+            Ok(RequestItem::Data(key.to_string(), parsed_value))
+        }
+        Some((key, Separator::Data, value)) => {
+            Ok(RequestItem::Data(key.to_string(), Value::String(value.to_string())))
+        }
+        Some((key, Separator::DataFile, path)) => {
+            Ok(RequestItem::Data(key.to_string(), Value::String(read_item_file(key, path)?)))
+        }
+        Some((name, Separator::Query, value)) => {
+            if name.is_empty() {
+                return Err(anyhow!("Invalid query parameter: '{}'. Use name==value.", item));
+            }
+            Ok(RequestItem::Query(name.to_string(), value.to_string()))
+        }
+        Some((name, Separator::Header, value)) => {
+            if name.trim().is_empty() {
+                return Err(anyhow!("Invalid header item: '{}'. Use Header:value.", item));
+            }
+            Ok(RequestItem::Header(name.trim().to_string(), value.trim().to_string()))
+        }
+        Some((field, Separator::File, spec)) => {
+            let (path, mime) = match spec.split_once(";type=") {
+                Some((path, mime)) => (path, Some(mime.to_string())),
+                None => (spec, None),
+            };
+            if field.is_empty() || path.is_empty() {
+                return Err(anyhow!("Invalid file field: '{}'. Use field@path/to/file.", item));
+            }
+            Ok(RequestItem::File { field: field.to_string(), path: PathBuf::from(path), mime })
+        }
+        None => Err(anyhow!(
+            "Invalid request item format: '{}'. Use key=value, key:=json_value, field@file, name==value or Header:value.",
+            item
+        )),
+    }
+}
+
+pub fn parse_request_items(items: &[String]) -> Result<Vec<RequestItem>> {
+    items.iter().map(|item| parse_request_item(item)).collect()
+}
+
+/// Parses a body key such as `user[tags][]` or `points[0][x]` into path segments.
+pub fn parse_key_path(key: &str) -> Result<Vec<PathSegment>> {
+    let invalid = || anyhow!("Invalid key path: '{}'. Use name, name[key], name[0] or name[].", key);
+
+    let (head, mut rest) = match key.find('[') {
+        Some(pos) => (&key[..pos], &key[pos..]),
+        None => (key, ""),
+    };
+    if head.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut path = vec![PathSegment::Key(head.to_string())];
+    while !rest.is_empty() {
+        let inner_end = rest.find(']').ok_or_else(invalid)?;
+        if !rest.starts_with('[') {
+            return Err(invalid());
+        }
+        let inner = &rest[1..inner_end];
+        path.push(if inner.is_empty() {
+            PathSegment::Append
+        } else if let Ok(index) = inner.parse::<usize>() {
+            PathSegment::Index(index)
+        } else {
+            PathSegment::Key(inner.to_string())
+        });
+        rest = &rest[inner_end + 1..];
+    }
+    Ok(path)
+}
+
+/// How many nulls an index past the end of an array may pad it with, so that a typo like
+/// `a[999999999999]` is an error rather than an attempt to allocate that many.
+const MAX_INDEX_GAP: usize = 1000;
+
+/// Deep-merges `Data` items into a single JSON object, honouring bracket key paths.
+pub fn build_json_body(items: &[RequestItem]) -> Result<Value> {
+    let mut body = Value::Object(Map::new());
+    for item in items {
+        if let RequestItem::Data(key, value) = item {
+            let path = parse_key_path(key)?;
+            set_path(&mut body, &path, value.clone())
+                .map_err(|e| anyhow!("Cannot set '{}': {}", key, e))?;
+        }
+    }
+    Ok(body)
+}
+
+fn set_path(target: &mut Value, path: &[PathSegment], value: Value) -> Result<()> {
+    let Some((segment, rest)) = path.split_first() else {
+        *target = value;
+        return Ok(());
+    };
+
+    let slot = match segment {
+        PathSegment::Key(key) => {
+            if target.is_null() {
+                *target = Value::Object(Map::new());
+            }
+            let found = type_name(target);
+            let object = target.as_object_mut()
+                .ok_or_else(|| anyhow!("expected an object for key '{}', found {}", key, found))?;
+            object.entry(key.clone()).or_insert(Value::Null)
+        }
+        PathSegment::Index(_) | PathSegment::Append => {
+            if target.is_null() {
+                *target = Value::Array(Vec::new());
+            }
+            let found = type_name(target);
+            let array = target.as_array_mut()
+                .ok_or_else(|| anyhow!("expected an array, found {}", found))?;
+            let index = match segment {
+                PathSegment::Index(index) => *index,
+                _ => array.len(),
+            };
+            if index.saturating_sub(array.len()) > MAX_INDEX_GAP {
+                return Err(anyhow!("index {} is too far past the end of the array ({} items)", index, array.len()));
+            }
+            if array.len() <= index {
+                array.resize(index + 1, Value::Null);
+            }
+            &mut array[index]
+        }
+    };
+    set_path(slot, rest, value)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_request_item_string() {
+        let item = parse_request_item("name=Varyag").unwrap();
+        assert_eq!(item, RequestItem::Data("name".to_string(), Value::String("Varyag".to_string())));
+    }
+
+    #[test]
+    fn test_parse_request_item_json_object() {
+        let item = parse_request_item(r#"data:={"id":123,"active":true}"#).unwrap();
+        assert_eq!(item, RequestItem::Data("data".to_string(), json!({"id": 123, "active": true})));
+    }
+
+    #[test]
+    fn test_parse_request_item_json_array() {
+        let item = parse_request_item(r#"items:=[1, "two", 3.0]"#).unwrap();
+        assert_eq!(item, RequestItem::Data("items".to_string(), json!([1, "two", 3.0])));
+    }
+
+    #[test]
+    fn test_parse_request_item_invalid_json() {
+        let result = parse_request_item(r#"data:={"id":123,"active":true"#); // Missing closing brace
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert!(error.to_string().contains("Invalid JSON value for key 'data'"));
+    }
+
+    #[test]
+    fn test_parse_request_item_invalid_format() {
+        let result = parse_request_item("Varyag"); // No separator at all
+        assert!(result.is_err());
+        let error = result.unwrap_err();
+        assert!(error.to_string().contains("Invalid request item format"));
+    }
+
+    #[test]
+    fn test_parse_request_item_value_with_at_sign() {
+        let item = parse_request_item("email=user@example.com").unwrap();
+        assert_eq!(item, RequestItem::Data("email".to_string(), json!("user@example.com")));
+    }
+
+    #[test]
+    fn test_parse_request_item_file() {
+        let item = parse_request_item("avatar@./images/me.png").unwrap();
+        assert_eq!(item, RequestItem::File {
+            field: "avatar".to_string(),
+            path: PathBuf::from("./images/me.png"),
+            mime: None,
+        });
+    }
+
+    #[test]
+    fn test_parse_request_item_file_with_type() {
+        let item = parse_request_item("doc@report.bin;type=application/pdf").unwrap();
+        assert_eq!(item, RequestItem::File {
+            field: "doc".to_string(),
+            path: PathBuf::from("report.bin"),
+            mime: Some("application/pdf".to_string()),
+        });
+    }
+
+    #[test]
+    fn test_parse_request_item_query() {
+        let item = parse_request_item("q==rust lang=en").unwrap();
+        assert_eq!(item, RequestItem::Query("q".to_string(), "rust lang=en".to_string()));
+        assert!(!item.is_body());
+    }
+
+    #[test]
+    fn test_parse_request_item_header() {
+        let item = parse_request_item("Referer: http://example.com/?a=b").unwrap();
+        assert_eq!(item, RequestItem::Header("Referer".to_string(), "http://example.com/?a=b".to_string()));
+        assert!(!item.is_body());
+    }
+
+    #[test]
+    fn test_parse_request_item_embedded_files() {
+        let dir = std::env::temp_dir().join(format!("varyag-items-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("note.txt");
+        let json = dir.join("fixture.json");
+        fs::write(&text, "hello").unwrap();
+        fs::write(&json, r#"{"ids": [1, 2]}"#).unwrap();
+
+        let item = parse_request_item(&format!("note=@{}", text.display())).unwrap();
+        assert_eq!(item, RequestItem::Data("note".to_string(), json!("hello")));
+        let item = parse_request_item(&format!("fixture:=@{}", json.display())).unwrap();
+        assert_eq!(item, RequestItem::Data("fixture".to_string(), json!({"ids": [1, 2]})));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_request_item_missing_embedded_file() {
+        let error = parse_request_item("note=@/definitely/not/here.txt").unwrap_err();
+        assert!(error.to_string().contains("Failed to read file"));
+    }
+
+    #[test]
+    fn test_parse_key_path() {
+        assert_eq!(parse_key_path("name").unwrap(), vec![PathSegment::Key("name".to_string())]);
+        assert_eq!(parse_key_path("user[tags][]").unwrap(), vec![
+            PathSegment::Key("user".to_string()),
+            PathSegment::Key("tags".to_string()),
+            PathSegment::Append,
+        ]);
+        assert_eq!(parse_key_path("points[1][x]").unwrap(), vec![
+            PathSegment::Key("points".to_string()),
+            PathSegment::Index(1),
+            PathSegment::Key("x".to_string()),
+        ]);
+        assert!(parse_key_path("[name]").is_err());
+        assert!(parse_key_path("user[name").is_err());
+        assert!(parse_key_path("user[name]x").is_err());
+    }
+
+    #[test]
+    fn test_build_json_body_deep_merge() {
+        let items = parse_request_items(&[
+            "user[name]=x".to_string(),
+            "user[tags][]=a".to_string(),
+            "user[tags][]=b".to_string(),
+            "user[age]:=30".to_string(),
+            "points[1][x]:=5".to_string(),
+            "active:=true".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(build_json_body(&items).unwrap(), json!({
+            "user": {"name": "x", "tags": ["a", "b"], "age": 30},
+            "points": [null, {"x": 5}],
+            "active": true,
+        }));
+    }
+
+    #[test]
+    fn test_build_json_body_type_conflict() {
+        let items = parse_request_items(&["user=x".to_string(), "user[name]=y".to_string()]).unwrap();
+        let error = build_json_body(&items).unwrap_err();
+        assert!(error.to_string().contains("Cannot set 'user[name]'"));
+    }
+
+    #[test]
+    fn test_build_json_body_rejects_far_indexes() {
+        let items = parse_request_items(&["a[999999999999]:=1".to_string()]).unwrap();
+        let error = build_json_body(&items).unwrap_err();
+        assert!(error.to_string().contains("too far past the end"), "{}", error);
+
+        let items = parse_request_items(&["a[1000]:=1".to_string()]).unwrap();
+        assert_eq!(build_json_body(&items).unwrap()["a"].as_array().unwrap().len(), 1001);
+    }
+
+    #[test]
+    fn test_is_body_item() {
+        assert!(is_body_item("name=Varyag"));
+        assert!(is_body_item("count:=3"));
+        assert!(is_body_item("file@notes.txt"));
+        assert!(is_body_item("note=@notes.txt"));
+        assert!(is_body_item("fixture:=@fixture.json"));
+        assert!(!is_body_item("page==2"));
+        assert!(!is_body_item("X-Api-Key:secret"));
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// Restricts connections to one address family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub fn matches(self, addr: &IpAddr) -> bool {
+        match self {
+            IpFamily::V4 => addr.is_ipv4(),
+            IpFamily::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl fmt::Display for IpFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpFamily::V4 => "IPv4",
+            IpFamily::V6 => "IPv6",
+        })
+    }
+}
+
+/// A fixed address for a host and port, as in curl's `--resolve host:port:addr[,addr]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveOverride {
+    pub host: String,
+    pub port: u16,
+    pub addrs: Vec<IpAddr>,
+}
+
+impl FromStr for ResolveOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid --resolve value: '{}'. Use HOST:PORT:ADDRESS[,ADDRESS...].", s);
+        let Some(&[host, port, addrs]) = split_fields(s, 3).as_deref() else {
+            return Err(invalid());
+        };
+        let addrs = addrs
+            .split(',')
+            .map(|addr| unbracket(addr.trim()).parse::<IpAddr>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        if host.is_empty() || addrs.is_empty() {
+            return Err(invalid());
+        }
+        Ok(ResolveOverride { host: unbracket(host).to_ascii_lowercase(), port: port.parse().map_err(|_| invalid())?, addrs })
+    }
+}
+
+impl fmt::Display for ResolveOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addrs: Vec<String> = self.addrs.iter().map(|addr| bracket(&addr.to_string())).collect();
+        write!(f, "{}:{}:{}", bracket(&self.host), self.port, addrs.join(","))
+    }
+}
+
+/// Connects to another endpoint in place of a host and port, as in curl's
+/// `--connect-to HOST1:PORT1:HOST2:PORT2`. Empty fields match any host or port,
+/// or keep the original one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectTo {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub to_host: Option<String>,
+    pub to_port: Option<u16>,
+}
+
+impl FromStr for ConnectTo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid --connect-to value: '{}'. Use HOST1:PORT1:HOST2:PORT2 (any part may be empty).", s);
+        let Some(&[host, port, to_host, to_port]) = split_fields(s, 4).as_deref() else {
+            return Err(invalid());
+        };
+        let parse_host = |h: &str| (!h.is_empty()).then(|| unbracket(h).to_ascii_lowercase());
+        let parse_port = |p: &str| if p.is_empty() { Ok(None) } else { p.parse().map(Some).map_err(|_| invalid()) };
+        Ok(ConnectTo {
+            host: parse_host(host),
+            port: parse_port(port)?,
+            to_host: parse_host(to_host),
+            to_port: parse_port(to_port)?,
+        })
+    }
+}
+
+impl fmt::Display for ConnectTo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let host = |host: &Option<String>| host.as_deref().map(bracket).unwrap_or_default();
+        let port = |port: Option<u16>| port.map(|p| p.to_string()).unwrap_or_default();
+        write!(f, "{}:{}:{}:{}", host(&self.host), port(self.port), host(&self.to_host), port(self.to_port))
+    }
+}
+
+impl ConnectTo {
+    fn matches(&self, host: &str, port: u16) -> bool {
+        self.host.as_deref().is_none_or(|h| h.eq_ignore_ascii_case(host)) && self.port.is_none_or(|p| p == port)
+    }
+}
+
+/// Where and how outgoing connections are made.
+#[derive(Debug, Clone, Default)]
+pub struct ResolveOptions {
+    pub overrides: Vec<ResolveOverride>,
+    pub connect_to: Vec<ConnectTo>,
+    pub family: Option<IpFamily>,
+    /// Source address to bind to.
+    pub local_address: Option<IpAddr>,
+    /// Network interface to bind to (Linux only).
+    pub interface: Option<String>,
+}
+
+impl ResolveOptions {
+    /// The host and port to actually connect to for `host:port`, after `--connect-to`.
+    pub fn route(&self, host: &str, port: u16) -> (String, u16) {
+        let host = unbracket(host);
+        match self.connect_to.iter().find(|rule| rule.matches(host, port)) {
+            Some(rule) => (rule.to_host.clone().unwrap_or_else(|| host.to_string()), rule.to_port.unwrap_or(port)),
+            None => (host.to_string(), port),
+        }
+    }
+
+    /// Resolves `host:port` to the addresses to try, honoring `--connect-to`, `--resolve` and `-4/-6`.
+    pub async fn lookup(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let (host, port) = self.route(host, port);
+        let addrs: Vec<SocketAddr> = match self.overrides.iter().find(|o| o.port == port && o.host.eq_ignore_ascii_case(&host)) {
+            Some(entry) => entry.addrs.iter().map(|ip| SocketAddr::new(*ip, port)).collect(),
+            None => tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| anyhow!("Failed to resolve {}: {}", host, e))?
+                .collect(),
+        };
+        let addrs: Vec<SocketAddr> = addrs
+            .into_iter()
+            .filter(|addr| self.family.is_none_or(|family| family.matches(&addr.ip())))
+            .filter(|addr| self.local_address.is_none_or(|local| local.is_ipv4() == addr.is_ipv4()))
+            .collect();
+        if addrs.is_empty() {
+            let family = self.family.or(match self.local_address {
+                Some(IpAddr::V4(_)) => Some(IpFamily::V4),
+                Some(IpAddr::V6(_)) => Some(IpFamily::V6),
+                None => None,
+            });
+            return Err(match family {
+                Some(family) => anyhow!("No {} address found for {}", family, host),
+                None => anyhow!("No address found for {}", host),
+            });
+        }
+        Ok(addrs)
+    }
+}
+
+/// Splits `host:port` or `[v6]:port` into its parts.
+pub fn split_host_port(address: &str) -> Result<(&str, u16)> {
+    let invalid = || anyhow!("Invalid address: '{}'. Use HOST:PORT.", address);
+    let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+    Ok((unbracket(host), port.parse().map_err(|_| invalid())?))
+}
+
+/// Splits `s` on colons outside `[...]` into exactly `count` fields.
+fn split_fields(s: &str, count: usize) -> Option<Vec<&str>> {
+    let mut fields = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ':' if depth == 0 && fields.len() + 1 < count => {
+                fields.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&s[start..]);
+    (fields.len() == count).then_some(fields)
+}
+
+/// Puts IPv6 addresses in brackets, so they can be told apart from the port.
+fn bracket(host: &str) -> String {
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolve_override() {
+        let entry: ResolveOverride = "API.example.com:443:10.0.0.1,[::1]".parse().unwrap();
+        assert_eq!(entry.host, "api.example.com");
+        assert_eq!(entry.port, 443);
+        assert_eq!(entry.addrs, ["10.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+        assert!("example.com:443".parse::<ResolveOverride>().is_err());
+        assert!("example.com:https:10.0.0.1".parse::<ResolveOverride>().is_err());
+        assert_eq!(entry.to_string(), "api.example.com:443:10.0.0.1,[::1]");
+    }
+
+    #[test]
+    fn test_connect_to_routes_matching_endpoints() {
+        let options = ResolveOptions {
+            connect_to: vec!["example.com:443:lb.internal:8443".parse().unwrap(), "::[::1]:".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(options.route("example.com", 443), ("lb.internal".to_string(), 8443));
+        assert_eq!(options.route("example.com", 80), ("::1".to_string(), 80));
+        assert!("a:b:c".parse::<ConnectTo>().is_err());
+        assert_eq!(options.connect_to[1].to_string(), "::[::1]:");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_applies_overrides_and_family() {
+        let options = ResolveOptions {
+            overrides: vec!["lb.internal:8443:127.0.0.1,::1".parse().unwrap()],
+            connect_to: vec!["example.com:443:lb.internal:8443".parse().unwrap()],
+            family: Some(IpFamily::V6),
+            ..Default::default()
+        };
+        assert_eq!(options.lookup("example.com", 443).await.unwrap(), ["[::1]:8443".parse().unwrap()]);
+
+        let options = ResolveOptions { family: Some(IpFamily::V6), ..Default::default() };
+        let error = options.lookup("127.0.0.1", 80).await.unwrap_err();
+        assert_eq!(error.to_string(), "No IPv6 address found for 127.0.0.1");
+    }
+}
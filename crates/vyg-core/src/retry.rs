@@ -0,0 +1,148 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+use vyg_display::logger;
+
+/// HTTP statuses retried by default: rate limiting and transient gateway errors.
+pub const DEFAULT_RETRY_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+/// How often and how patiently a failed attempt is repeated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts made after the first one fails.
+    pub retries: u32,
+    /// HTTP statuses that are treated as a failed attempt.
+    pub statuses: Vec<u16>,
+    /// Delay before the first retry; it doubles with every further attempt.
+    pub base_delay: Duration,
+    /// Upper bound for a single delay, including one requested via `Retry-After`.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn retries_status(&self, status: u16) -> bool {
+        self.statuses.contains(&status)
+    }
+
+    /// Exponential backoff for retry number `attempt` (starting at 1), with jitter:
+    /// a random delay between half and all of the exponential value.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let half = self.base_delay.saturating_mul(factor).min(self.max_delay) / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    /// The delay before retry `attempt`, preferring the server's `Retry-After` value.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(delay) => delay.min(self.max_delay),
+            None => self.backoff(attempt),
+        }
+    }
+}
+
+/// Parses a `Retry-After` header given as delay-seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Runs `operation` until it succeeds or the policy's retries are used up.
+pub async fn retry<T, E, F, Fut>(policy: &RetryPolicy, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(e) if attempt < policy.retries => {
+                attempt += 1;
+                wait(policy, attempt, &e.to_string(), policy.backoff(attempt)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Reports a failed attempt and sleeps for `delay` before retry number `attempt`.
+pub(crate) async fn wait(policy: &RetryPolicy, attempt: u32, reason: &(dyn Display + Sync), delay: Duration) {
+    logger::warn(&format!(
+        "{}. Retrying in {:.1}s ({}/{})",
+        reason,
+        delay.as_secs_f64(),
+        attempt,
+        policy.retries,
+    ));
+    tokio::time::sleep(delay).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_backoff_grows_exponentially_within_jitter() {
+        let policy = RetryPolicy { max_delay: Duration::from_secs(5), ..Default::default() };
+        for (attempt, full) in [(1, 500), (2, 1000), (3, 2000), (4, 4000), (8, 5000)] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= Duration::from_millis(full / 2), "attempt {}: {:?}", attempt, delay);
+            assert!(delay <= Duration::from_millis(full), "attempt {}: {:?}", attempt, delay);
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(3600))), policy.max_delay);
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_after_success() {
+        let policy = RetryPolicy { retries: 3, base_delay: Duration::from_millis(1), ..Default::default() };
+        let mut calls = 0;
+        let result: Result<u32, String> = retry(&policy, || {
+            calls += 1;
+            let outcome = if calls < 3 { Err("refused".to_string()) } else { Ok(calls) };
+            async move { outcome }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+
+        let mut calls = 0;
+        let result: Result<(), String> = retry(&policy, || {
+            calls += 1;
+            async { Err("refused".to_string()) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+    }
+}
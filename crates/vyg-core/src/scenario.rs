@@ -0,0 +1,542 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use yaml_rust2::{Yaml, YamlLoader};
+use crate::compression::ResponseEncoding;
+use crate::cookie_jar::{CookieJar, SharedCookieJar};
+use crate::http_client::{self, HttpRequest};
+use crate::http_file::Variables;
+use crate::json_path::{self, JsonPath};
+use crate::transport::TransportOptions;
+use crate::{net_client, ws_client};
+
+/// The operators a matcher object may use, e.g. `{gte: 200, lt: 300}`.
+const OPERATORS: &[&str] = &["equals", "not_equals", "contains", "exists", "lt", "lte", "gt", "gte", "length"];
+
+/// A test scenario: steps run in order, each making one request and checking the reply.
+///
+/// Strings anywhere in a step may use `{{name}}` references to `variables`, to values captured
+/// by earlier steps and to `$guid`, `$timestamp`, `$randomInt MIN MAX` and `$processEnv NAME`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, Value>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    pub name: Option<String>,
+    pub http: Option<HttpStep>,
+    pub ws: Option<WsStep>,
+    pub tcp: Option<TcpStep>,
+    /// Variables to set from the reply: `status`, `body`, `headers.NAME` or a JSONPath into the body.
+    #[serde(default)]
+    pub capture: BTreeMap<String, String>,
+    #[serde(default, rename = "assert")]
+    pub assertions: Assertions,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpStep {
+    /// Defaults to POST with a body and GET otherwise.
+    pub method: Option<String>,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    /// A JSON body, sent with `Content-Type: application/json` unless the headers say otherwise.
+    pub json: Option<Value>,
+    /// A body sent as is.
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WsStep {
+    pub url: String,
+    pub send: Option<String>,
+    /// How many messages to wait for; assertions and captures see the last one.
+    #[serde(default = "one")]
+    pub messages: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TcpStep {
+    pub address: String,
+    pub send: Option<String>,
+}
+
+fn one() -> usize {
+    1
+}
+
+/// Expectations about a reply. Each expected value is either the value itself or a matcher
+/// object using [`OPERATORS`], e.g. `{contains: "json"}`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Assertions {
+    pub status: Option<Value>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Value>,
+    /// The whole body as text.
+    pub body: Option<Value>,
+    /// JSONPath expressions into the body, e.g. `$.user.id: 7`.
+    #[serde(default)]
+    pub json: BTreeMap<String, Value>,
+    pub max_latency_ms: Option<u64>,
+}
+
+impl Step {
+    /// The name, or a summary of the request.
+    pub fn title(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match (&self.http, &self.ws, &self.tcp) {
+            (Some(http), _, _) => format!("{} {}", http.method.as_deref().unwrap_or(if http.json.is_some() || http.body.is_some() { "POST" } else { "GET" }), http.url),
+            (_, Some(ws), _) => format!("WebSocket {}", ws.url),
+            (_, _, Some(tcp)) => format!("TCP {}", tcp.address),
+            _ => "empty step".to_string(),
+        }
+    }
+
+    /// The step with `{{...}}` references in all its strings expanded.
+    fn expand(&self, variables: &Variables) -> Result<Step> {
+        let value = expand_strings(serde_json::to_value(self)?, variables)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+fn expand_strings(value: Value, variables: &Variables) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => Value::String(variables.expand(&text)?),
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| expand_strings(item, variables)).collect::<Result<_>>()?),
+        Value::Object(fields) => Value::Object(
+            fields.into_iter().map(|(key, value)| Ok((key, expand_strings(value, variables)?))).collect::<Result<_>>()?,
+        ),
+        other => other,
+    })
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Scenario::parse(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let documents = YamlLoader::load_from_str(text).map_err(|e| anyhow!("Invalid YAML: {}", e))?;
+        let document = documents.into_iter().next().ok_or_else(|| anyhow!("The scenario is empty"))?;
+        let scenario: Scenario = serde_json::from_value(yaml_to_json(document)?)?;
+        for (i, step) in scenario.steps.iter().enumerate() {
+            let kinds = [step.http.is_some(), step.ws.is_some(), step.tcp.is_some()];
+            if kinds.iter().filter(|&&kind| kind).count() != 1 {
+                bail!("step {}: expected exactly one of http, ws or tcp", i + 1);
+            }
+        }
+        Ok(scenario)
+    }
+}
+
+fn yaml_to_json(yaml: Yaml) -> Result<Value> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(value) => Value::Bool(value),
+        Yaml::Integer(value) => Value::from(value),
+        Yaml::Real(text) => text
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| anyhow!("Invalid number '{}'", text))?,
+        Yaml::String(text) => Value::String(text),
+        Yaml::Array(items) => Value::Array(items.into_iter().map(yaml_to_json).collect::<Result<_>>()?),
+        Yaml::Hash(entries) => {
+            let mut object = Map::new();
+            for (key, value) in entries {
+                let key = match yaml_to_json(key)? {
+                    Value::String(key) => key,
+                    Value::Object(_) | Value::Array(_) => bail!("Mapping keys must be scalars"),
+                    other => other.to_string(),
+                };
+                object.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(object)
+        }
+        Yaml::Alias(_) => bail!("YAML aliases are not supported"),
+        Yaml::BadValue => bail!("Invalid YAML value"),
+    })
+}
+
+/// What a scenario run is given besides the scenario.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Values overriding the scenario's variables.
+    pub variables: HashMap<String, String>,
+    pub transport: TransportOptions,
+    /// Skip the remaining steps after the first failure.
+    pub fail_fast: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub name: String,
+    pub duration: Duration,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The reply did not meet the expectations.
+    Failed(Vec<String>),
+    /// The request could not be made.
+    Error(String),
+    Skipped,
+}
+
+impl StepResult {
+    pub fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Passed | Outcome::Skipped)
+    }
+}
+
+/// A reply from any kind of step, reduced to what assertions and captures look at.
+struct Reply {
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    body: String,
+    latency: Duration,
+}
+
+/// Runs the steps in order, calling `on_step` as each one finishes.
+pub async fn run(scenario: &Scenario, options: &RunOptions, mut on_step: impl FnMut(&StepResult)) -> Vec<StepResult> {
+    let definitions = scenario.variables.iter().map(|(name, value)| (name.clone(), json_path::to_text(value))).collect();
+    let mut variables = Variables::from_definitions(definitions);
+    for (name, value) in &options.variables {
+        variables.set(name, value.clone());
+    }
+    // Cookies set by one step are sent by the following ones, like in a browser session.
+    let cookie_jar = Arc::new(SharedCookieJar::new(CookieJar::default()));
+
+    let mut results = Vec::new();
+    let mut failed = false;
+    for step in &scenario.steps {
+        let started = Instant::now();
+        let outcome = if failed && options.fail_fast {
+            Outcome::Skipped
+        } else {
+            match run_step(step, &mut variables, options, &cookie_jar).await {
+                Ok(failures) if failures.is_empty() => Outcome::Passed,
+                Ok(failures) => Outcome::Failed(failures),
+                Err(e) => Outcome::Error(format!("{:#}", e)),
+            }
+        };
+        let result = StepResult { name: step.title(), duration: started.elapsed(), outcome };
+        failed |= !result.passed();
+        on_step(&result);
+        results.push(result);
+    }
+    results
+}
+
+/// Makes the step's request and returns the failed expectations.
+async fn run_step(step: &Step, variables: &mut Variables, options: &RunOptions, cookie_jar: &Arc<SharedCookieJar>) -> Result<Vec<String>> {
+    let step = step.expand(variables)?;
+    let reply = if let Some(http) = &step.http {
+        send_http(http, options, cookie_jar).await?
+    } else if let Some(ws) = &step.ws {
+        let started = Instant::now();
+        let messages = ws_client::request_ws(&ws.url, ws.send.clone(), ws.messages, &options.transport).await?;
+        let latency = started.elapsed();
+        if messages.len() < ws.messages {
+            bail!("Expected {} WebSocket message(s), the connection closed after {}", ws.messages, messages.len());
+        }
+        Reply { status: None, headers: Vec::new(), body: messages.last().cloned().unwrap_or_default(), latency }
+    } else if let Some(tcp) = &step.tcp {
+        let started = Instant::now();
+        let data = tcp.send.clone().unwrap_or_default().into_bytes();
+        let reply = net_client::request_tcp(&tcp.address, data, &options.transport).await?;
+        Reply { status: None, headers: Vec::new(), body: String::from_utf8_lossy(&reply).into_owned(), latency: started.elapsed() }
+    } else {
+        bail!("expected one of http, ws or tcp");
+    };
+
+    let json: Option<Value> = serde_json::from_str(&reply.body).ok();
+    let mut failures = check_assertions(&step.assertions, &reply, json.as_ref());
+    for (name, source) in &step.capture {
+        match capture(source, &reply, json.as_ref()) {
+            Ok(value) => variables.set(name, value),
+            Err(e) => failures.push(format!("capture {}: {}", name, e)),
+        }
+    }
+    Ok(failures)
+}
+
+async fn send_http(http: &HttpStep, options: &RunOptions, cookie_jar: &Arc<SharedCookieJar>) -> Result<Reply> {
+    let mut headers: Vec<String> = http.headers.iter().map(|(name, value)| format!("{}: {}", name, json_path::to_text(value))).collect();
+    let raw_body = match (&http.json, &http.body) {
+        (Some(_), Some(_)) => bail!("An HTTP step takes either json or body, not both"),
+        (Some(json), None) => {
+            if !http.headers.keys().any(|name| name.eq_ignore_ascii_case("content-type")) {
+                headers.push("Content-Type: application/json".to_string());
+            }
+            Some(json.to_string().into_bytes())
+        }
+        (None, body) => body.clone().map(String::into_bytes),
+    };
+    let method = http.method.clone().unwrap_or_else(|| if raw_body.is_some() { "POST" } else { "GET" }.to_string());
+    let request = HttpRequest {
+        url: if http.url.contains("://") { http.url.clone() } else { format!("http://{}", http.url) },
+        method: method.to_uppercase(),
+        headers,
+        raw_body,
+        cookie_jar: Some(cookie_jar.clone()),
+        transport: options.transport.clone(),
+        response_encoding: ResponseEncoding::Decode,
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let response = http_client::send_request(request).await?;
+    Ok(Reply {
+        status: response.status.split_whitespace().next().and_then(|code| code.parse().ok()),
+        headers: http_client::header_pairs(&response.headers),
+        body: response.text().into_owned(),
+        latency: started.elapsed(),
+    })
+}
+
+fn check_assertions(assertions: &Assertions, reply: &Reply, json: Option<&Value>) -> Vec<String> {
+    let mut failures = Vec::new();
+    if let Some(expected) = &assertions.status {
+        match reply.status {
+            Some(status) => check("status", expected, Some(&Value::from(status)), &mut failures),
+            None => failures.push("status: only HTTP steps have a status".to_string()),
+        }
+    }
+    for (name, expected) in &assertions.headers {
+        let actual = reply.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| Value::String(value.clone()));
+        check(&format!("header {}", name), expected, actual.as_ref(), &mut failures);
+    }
+    if let Some(expected) = &assertions.body {
+        check("body", expected, Some(&Value::String(reply.body.clone())), &mut failures);
+    }
+    for (path, expected) in &assertions.json {
+        let Some(json) = json else {
+            failures.push(format!("{}: the body is not JSON", path));
+            break;
+        };
+        match JsonPath::parse(path) {
+            Ok(compiled) => check(path, expected, compiled.first(json), &mut failures),
+            Err(e) => failures.push(e.to_string()),
+        }
+    }
+    if let Some(max) = assertions.max_latency_ms {
+        let latency = reply.latency.as_millis();
+        if latency > u128::from(max) {
+            failures.push(format!("latency: {} ms exceeds {} ms", latency, max));
+        }
+    }
+    failures
+}
+
+fn capture(source: &str, reply: &Reply, json: Option<&Value>) -> Result<String> {
+    if source == "status" {
+        return reply.status.map(|status| status.to_string()).ok_or_else(|| anyhow!("only HTTP steps have a status"));
+    }
+    if source == "body" {
+        return Ok(reply.body.clone());
+    }
+    if let Some(name) = source.strip_prefix("headers.") {
+        return reply
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+            .ok_or_else(|| anyhow!("no '{}' header", name));
+    }
+    let path = JsonPath::parse(source)?;
+    let json = json.ok_or_else(|| anyhow!("the body is not JSON"))?;
+    path.first(json).map(json_path::to_text).ok_or_else(|| anyhow!("{} matches nothing", source))
+}
+
+/// Compares `actual` with an expected value or matcher object, recording a failure for each unmet condition.
+fn check(what: &str, expected: &Value, actual: Option<&Value>, failures: &mut Vec<String>) {
+    let matcher = match expected {
+        Value::Object(ops) if !ops.is_empty() && ops.keys().all(|op| OPERATORS.contains(&op.as_str())) => ops.clone(),
+        other => Map::from_iter([("equals".to_string(), other.clone())]),
+    };
+    for (op, operand) in &matcher {
+        if op == "exists" {
+            if operand.as_bool() != Some(actual.is_some()) {
+                failures.push(format!("{}: expected {}", what, if actual.is_some() { "to be absent" } else { "to exist" }));
+            }
+            continue;
+        }
+        let Some(actual) = actual else {
+            failures.push(format!("{}: missing", what));
+            return;
+        };
+        let met = match op.as_str() {
+            "equals" => loosely_equal(actual, operand),
+            "not_equals" => !loosely_equal(actual, operand),
+            "contains" => match actual {
+                Value::Array(items) => items.iter().any(|item| loosely_equal(item, operand)),
+                other => json_path::to_text(other).contains(&json_path::to_text(operand)),
+            },
+            "length" => {
+                let length = match actual {
+                    Value::Array(items) => Some(items.len()),
+                    Value::Object(fields) => Some(fields.len()),
+                    Value::String(text) => Some(text.chars().count()),
+                    _ => None,
+                };
+                length.is_some_and(|length| operand.as_u64() == Some(length as u64))
+            }
+            comparison => match (number(actual), number(operand)) {
+                (Some(actual), Some(operand)) => match comparison {
+                    "lt" => actual < operand,
+                    "lte" => actual <= operand,
+                    "gt" => actual > operand,
+                    _ => actual >= operand,
+                },
+                _ => false,
+            },
+        };
+        if !met {
+            let expectation = if op == "equals" { operand.to_string() } else { format!("{} {}", op.replace('_', " "), operand) };
+            failures.push(format!("{}: expected {}, got {}", what, expectation, actual));
+        }
+    }
+}
+
+/// Equality that lets a number or boolean match the same value as text, e.g. a header
+/// against a YAML number or a JSON number against an expanded `{{variable}}`.
+fn loosely_equal(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Number(_), Value::Number(_)) => number(actual) == number(expected),
+        (Value::String(text), Value::Number(_) | Value::Bool(_)) => text.parse::<Value>().is_ok_and(|value| loosely_equal(&value, expected)),
+        (Value::Number(_) | Value::Bool(_), Value::String(text)) => text.parse::<Value>().is_ok_and(|value| loosely_equal(actual, &value)),
+        _ => actual == expected,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SCENARIO: &str = r#"
+name: Users API
+variables:
+  base: http://localhost:8080
+  retries: 3
+steps:
+  - name: Log in
+    http:
+      method: POST
+      url: "{{base}}/login"
+      json: {user: ada, remember: true}
+    capture:
+      token: $.token
+    assert:
+      status: 200
+      headers:
+        content-type: {contains: json}
+      json:
+        $.token: {exists: true}
+      max_latency_ms: 500
+  - ws:
+      url: ws://localhost:9000
+      send: "hello {{token}}"
+  - tcp:
+      address: localhost:6379
+      send: "PING\r\n"
+    assert:
+      body: "+PONG\r\n"
+"#;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        assert_eq!(scenario.name.as_deref(), Some("Users API"));
+        assert_eq!(scenario.variables["retries"], json!(3));
+        let titles: Vec<String> = scenario.steps.iter().map(Step::title).collect();
+        assert_eq!(titles, ["Log in", "WebSocket ws://localhost:9000", "TCP localhost:6379"]);
+        let login = &scenario.steps[0];
+        assert_eq!(login.http.as_ref().unwrap().json, Some(json!({"user": "ada", "remember": true})));
+        assert_eq!(login.assertions.json["$.token"], json!({"exists": true}));
+        assert_eq!(scenario.steps[1].ws.as_ref().unwrap().messages, 1);
+
+        let error = Scenario::parse("steps:\n  - http: {url: x}\n    asert: {status: 200}").unwrap_err();
+        assert!(error.to_string().contains("unknown field `asert`"), "{}", error);
+        let error = Scenario::parse("steps:\n  - name: nothing").unwrap_err();
+        assert_eq!(error.to_string(), "step 1: expected exactly one of http, ws or tcp");
+    }
+
+    #[test]
+    fn test_check_matchers() {
+        let mut failures = Vec::new();
+        check("status", &json!(200), Some(&json!(200)), &mut failures);
+        check("status", &json!({"gte": 200, "lt": 300}), Some(&json!(204)), &mut failures);
+        check("header content-length", &json!(12), Some(&json!("12")), &mut failures);
+        check("$.tags", &json!({"contains": "x", "length": 2}), Some(&json!(["x", "y"])), &mut failures);
+        check("$.deleted", &json!({"exists": false}), None, &mut failures);
+        check("$.id", &json!("7"), Some(&json!(7)), &mut failures);
+        assert!(failures.is_empty(), "{:?}", failures);
+
+        check("status", &json!(200), Some(&json!(404)), &mut failures);
+        check("$.name", &json!({"contains": "Ada"}), Some(&json!("Lin")), &mut failures);
+        check("$.id", &json!(7), None, &mut failures);
+        check("$.count", &json!({"lt": 10}), Some(&json!(10)), &mut failures);
+        assert_eq!(
+            failures,
+            [
+                "status: expected 200, got 404",
+                "$.name: expected contains \"Ada\", got \"Lin\"",
+                "$.id: missing",
+                "$.count: expected lt 10, got 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_and_expand() {
+        let reply = Reply {
+            status: Some(201),
+            headers: vec![("location".to_string(), "/users/7".to_string())],
+            body: r#"{"user": {"id": 7}}"#.to_string(),
+            latency: Duration::from_millis(3),
+        };
+        let json: Value = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(capture("$.user.id", &reply, Some(&json)).unwrap(), "7");
+        assert_eq!(capture("headers.Location", &reply, Some(&json)).unwrap(), "/users/7");
+        assert_eq!(capture("status", &reply, Some(&json)).unwrap(), "201");
+        assert!(capture("$.missing", &reply, Some(&json)).is_err());
+
+        let mut variables = Variables::from_definitions(HashMap::from([("base".to_string(), "http://api".to_string())]));
+        variables.set("id", "7".to_string());
+        let step = Scenario::parse("steps:\n  - http: {url: \"{{base}}/users/{{id}}\"}\n    assert: {json: {$.id: \"{{id}}\"}}").unwrap().steps.remove(0);
+        let step = step.expand(&variables).unwrap();
+        assert_eq!(step.http.unwrap().url, "http://api/users/7");
+        assert_eq!(step.assertions.json["$.id"], json!("7"));
+    }
+}
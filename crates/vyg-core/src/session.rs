@@ -0,0 +1,224 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use url::Url;
+use crate::auth::Auth;
+use crate::cookie_jar::CookieJar;
+use crate::http_client::HttpRequest;
+use crate::request_items::{parse_request_item, RequestItem};
+
+/// Headers that describe a single request and are therefore never remembered.
+const TRANSIENT_HEADER_PREFIXES: [&str; 4] = ["content-", "if-", "cookie", "range"];
+
+/// State persisted between `send` invocations: custom headers, credentials and cookies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// Headers and credentials by the `host:port` they were used with, so they are
+    /// never sent to another server.
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostState>,
+    #[serde(default)]
+    pub cookies: CookieJar,
+}
+
+/// What a session remembers for one host.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostState {
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth: Option<Auth>,
+}
+
+impl Session {
+    /// Resolves a session name to its file. Names containing a path separator or ending
+    /// in `.json` are used as paths; plain names live in the user's config directory.
+    pub fn path_for(name: &str) -> Result<PathBuf> {
+        if name.contains(['/', '\\']) || name.ends_with(".json") {
+            return Ok(PathBuf::from(name));
+        }
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.') {
+            return Err(anyhow!("Invalid session name: '{}'. Use letters, digits, '-', '_' and '.'.", name));
+        }
+        let config_dir = dirs::config_dir().ok_or_else(|| anyhow!("Could not determine the user config directory."))?;
+        Ok(config_dir.join("varyag").join("sessions").join(format!("{}.json", name)))
+    }
+
+    /// Loads a session file, starting a fresh session if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Session::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read session '{}': {}", path.display(), e))?;
+        let mut session: Session = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid session file '{}': {}", path.display(), e))?;
+        session.cookies.remove_expired();
+        Ok(session)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let write_error = |e: std::io::Error| anyhow!("Failed to write session '{}': {}", path.display(), e);
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Sessions may hold credentials, so keep them private to the user, from the start.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(write_error)?;
+        // An existing file keeps its mode when opened, so tighten it before writing.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(write_error)?;
+        }
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes()).map_err(write_error)?;
+        Ok(())
+    }
+
+    /// Adds the headers and credentials remembered for the request's host to `request`.
+    /// Anything set explicitly on the request takes precedence.
+    pub fn apply(&self, request: &mut HttpRequest) {
+        let Some(state) = host_key(&request.url).and_then(|host| self.hosts.get(&host)) else {
+            return;
+        };
+        let explicit = explicit_header_names(request);
+        let mut headers: Vec<String> = state.headers.iter()
+            .filter(|(name, _)| !explicit.contains(&name.to_ascii_lowercase()))
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        headers.append(&mut request.headers);
+        request.headers = headers;
+
+        if request.auth.is_none() {
+            request.auth = state.auth.clone();
+        }
+    }
+
+    /// Remembers the custom headers and credentials used by `request`, for its host.
+    pub fn remember(&mut self, request: &HttpRequest) {
+        let Some(host) = host_key(&request.url) else {
+            return;
+        };
+        let from_items = request.body.iter().filter_map(|item| match parse_request_item(item) {
+            Ok(RequestItem::Header(name, value)) => Some((name, value)),
+            _ => None,
+        });
+        let from_flags = request.headers.iter().filter_map(|header| {
+            header.split_once(':').map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        });
+
+        let mut state = self.hosts.get(&host).cloned().unwrap_or_default();
+        for (name, value) in from_flags.chain(from_items) {
+            let lower = name.to_ascii_lowercase();
+            if TRANSIENT_HEADER_PREFIXES.iter().any(|prefix| lower.starts_with(prefix)) {
+                continue;
+            }
+            state.headers.retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+            state.headers.insert(name, value);
+        }
+        if request.auth.is_some() {
+            state.auth = request.auth.clone();
+        }
+        if state != HostState::default() {
+            self.hosts.insert(host, state);
+        }
+    }
+}
+
+/// `host:port` of a URL, with the scheme's default port filled in.
+fn host_key(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(format!("{}:{}", url.host_str()?, url.port_or_known_default()?))
+}
+
+/// Lowercased names of headers given via `-H` or `Header:value` items.
+fn explicit_header_names(request: &HttpRequest) -> Vec<String> {
+    let from_flags = request.headers.iter().filter_map(|h| h.split_once(':').map(|(name, _)| name.trim().to_string()));
+    let from_items = request.body.iter().filter_map(|item| match parse_request_item(item) {
+        Ok(RequestItem::Header(name, _)) => Some(name),
+        _ => None,
+    });
+    from_flags.chain(from_items).map(|name| name.to_ascii_lowercase()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(Session::path_for("./api.json").unwrap(), PathBuf::from("./api.json"));
+        let path = Session::path_for("staging-api").unwrap();
+        assert!(path.ends_with("varyag/sessions/staging-api.json"));
+        assert!(Session::path_for("bad name").is_err());
+    }
+
+    #[test]
+    fn test_remember_and_apply() {
+        let mut session = Session::default();
+        session.remember(&HttpRequest {
+            url: "https://api.a.com/login".to_string(),
+            headers: vec!["X-Api-Key: one".to_string(), "Content-Type: text/plain".to_string()],
+            body: vec!["x-tenant:acme".to_string(), "name=Varyag".to_string()],
+            auth: Some(Auth::Bearer("token".to_string())),
+            ..Default::default()
+        });
+        let state = &session.hosts["api.a.com:443"];
+        assert_eq!(state.headers.len(), 2);
+        assert_eq!(state.headers["X-Api-Key"], "one");
+        assert_eq!(state.headers["x-tenant"], "acme");
+
+        let mut request = HttpRequest {
+            url: "https://api.a.com/profile".to_string(),
+            headers: vec!["x-api-key: two".to_string()],
+            ..Default::default()
+        };
+        session.apply(&mut request);
+        assert_eq!(request.headers, vec!["x-tenant: acme".to_string(), "x-api-key: two".to_string()]);
+        assert_eq!(request.auth, Some(Auth::Bearer("token".to_string())));
+    }
+
+    #[test]
+    fn test_headers_and_credentials_stay_with_their_host() {
+        let mut session = Session::default();
+        session.remember(&HttpRequest {
+            url: "https://api.a.com/".to_string(),
+            headers: vec!["X-Api-Key: secret".to_string()],
+            auth: Some(Auth::Bearer("token".to_string())),
+            ..Default::default()
+        });
+        for url in ["https://evil.example.com/", "http://api.a.com/", "https://api.a.com:8443/"] {
+            let mut request = HttpRequest { url: url.to_string(), ..Default::default() };
+            session.apply(&mut request);
+            assert_eq!((request.headers.len(), request.auth), (0, None), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("varyag-session-{}", std::process::id()))
+            .join("session.json");
+        let mut session = Session::default();
+        session.hosts.entry("example.com:80".to_string()).or_default().headers.insert("X-Api-Key".to_string(), "secret".to_string());
+        session.cookies.store("sid=abc", &url::Url::parse("http://example.com/").unwrap());
+        session.save(&path).unwrap();
+
+        assert_eq!(Session::load(&path).unwrap(), session);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
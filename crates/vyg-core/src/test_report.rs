@@ -0,0 +1,111 @@
+use crate::scenario::{Outcome, StepResult};
+
+/// Renders the results of a scenario as a JUnit XML report with one test case per step.
+pub fn junit(suite: &str, results: &[StepResult]) -> String {
+    let count = |f: fn(&Outcome) -> bool| results.iter().filter(|result| f(&result.outcome)).count();
+    let total: f64 = results.iter().map(|result| result.duration.as_secs_f64()).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites>\n  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape(suite),
+        results.len(),
+        count(|outcome| matches!(outcome, Outcome::Failed(_))),
+        count(|outcome| matches!(outcome, Outcome::Error(_))),
+        count(|outcome| matches!(outcome, Outcome::Skipped)),
+        total,
+    ));
+    for result in results {
+        let open = format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape(&result.name),
+            escape(suite),
+            result.duration.as_secs_f64()
+        );
+        match &result.outcome {
+            Outcome::Passed => xml.push_str(&format!("{}/>\n", open)),
+            Outcome::Skipped => xml.push_str(&format!("{}>\n      <skipped/>\n    </testcase>\n", open)),
+            Outcome::Failed(failures) => xml.push_str(&format!(
+                "{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                open,
+                escape(&failures[0]),
+                escape(&failures.join("\n"))
+            )),
+            Outcome::Error(error) => xml.push_str(&format!(
+                "{}>\n      <error message=\"{}\">{}</error>\n    </testcase>\n",
+                open,
+                escape(error),
+                escape(error)
+            )),
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Renders the results as a TAP version 13 stream, with failures as YAML diagnostics.
+pub fn tap(results: &[StepResult]) -> String {
+    let mut tap = format!("TAP version 13\n1..{}\n", results.len());
+    for (i, result) in results.iter().enumerate() {
+        let number = i + 1;
+        let name = result.name.replace('#', "\\#");
+        match &result.outcome {
+            Outcome::Passed => tap.push_str(&format!("ok {} - {}\n", number, name)),
+            Outcome::Skipped => tap.push_str(&format!("ok {} - {} # SKIP after an earlier failure\n", number, name)),
+            Outcome::Failed(messages) => {
+                tap.push_str(&format!("not ok {} - {}\n  ---\n  failures:\n", number, name));
+                for message in messages {
+                    tap.push_str(&format!("    - {}\n", yaml_string(message)));
+                }
+                tap.push_str("  ...\n");
+            }
+            Outcome::Error(error) => {
+                tap.push_str(&format!("not ok {} - {}\n  ---\n  error: {}\n  ...\n", number, name, yaml_string(error)));
+            }
+        }
+    }
+    tap
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+/// A double-quoted YAML scalar.
+fn yaml_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\r', "\\r"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn results() -> Vec<StepResult> {
+        let result = |name: &str, outcome| StepResult { name: name.to_string(), duration: Duration::from_millis(250), outcome };
+        vec![
+            result("Log in", Outcome::Passed),
+            result("Get <me>", Outcome::Failed(vec!["status: expected 200, got 401".to_string(), "$.id: missing".to_string()])),
+            result("Ping", Outcome::Error("Connection refused".to_string())),
+            result("Log out", Outcome::Skipped),
+        ]
+    }
+
+    #[test]
+    fn test_junit() {
+        let xml = junit("Users API", &results());
+        assert!(xml.contains(r#"<testsuite name="Users API" tests="4" failures="1" errors="1" skipped="1" time="1.000">"#), "{}", xml);
+        assert!(xml.contains(r#"<testcase name="Log in" classname="Users API" time="0.250"/>"#));
+        assert!(xml.contains("<testcase name=\"Get &lt;me&gt;\" classname=\"Users API\" time=\"0.250\">\n      <failure message=\"status: expected 200, got 401\">status: expected 200, got 401\n$.id: missing</failure>"));
+        assert!(xml.contains(r#"<error message="Connection refused">Connection refused</error>"#));
+        assert!(xml.contains("<skipped/>"));
+    }
+
+    #[test]
+    fn test_tap() {
+        assert_eq!(
+            tap(&results()),
+            "TAP version 13\n1..4\nok 1 - Log in\nnot ok 2 - Get <me>\n  ---\n  failures:\n    - \"status: expected 200, got 401\"\n    - \"$.id: missing\"\n  ...\n\
+             not ok 3 - Ping\n  ---\n  error: \"Connection refused\"\n  ...\nok 4 - Log out # SKIP after an earlier failure\n"
+        );
+    }
+}
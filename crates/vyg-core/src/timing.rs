@@ -0,0 +1,236 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use crate::resolve::ResolveOptions;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Where the time of one HTTP exchange went, as offsets from the moment the request was sent.
+///
+/// Name resolution and connecting are only timed when the exchange opened a new connection;
+/// `connect` covers the TCP handshake together with the TLS handshake for `https`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    /// When the client started resolving and connecting.
+    pub connect_start: Option<Duration>,
+    /// When name resolution finished. `None` for IP literals.
+    pub dns: Option<Duration>,
+    /// When the connection was ready to carry the request.
+    pub connect: Option<Duration>,
+    /// Whether `connect` includes a TLS handshake.
+    pub tls: bool,
+    /// When the response headers arrived.
+    pub first_byte: Duration,
+    /// When the response body had been read completely.
+    pub total: Duration,
+    /// Size of the request body, when it was known up front.
+    pub bytes_sent: Option<u64>,
+    /// Size of the response body as received.
+    pub bytes_received: u64,
+}
+
+impl Timings {
+    /// The consecutive phases of the exchange as `(label, start, end)`.
+    pub fn phases(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let mut phases = Vec::new();
+        let mut cursor = Duration::ZERO;
+        if let Some(start) = self.connect_start {
+            cursor = start;
+        }
+        if let Some(dns) = self.dns {
+            phases.push(("DNS lookup", cursor, dns));
+            cursor = dns;
+        }
+        if let Some(connect) = self.connect {
+            let label = if self.tls { "TCP + TLS handshake" } else { "TCP connect" };
+            phases.push((label, cursor, connect));
+            cursor = connect;
+        }
+        phases.push(("Server processing", cursor, self.first_byte));
+        phases.push(("Content transfer", self.first_byte, self.total));
+        phases
+    }
+
+    /// Average download speed in bytes per second.
+    pub fn download_speed(&self) -> f64 {
+        let seconds = self.total.as_secs_f64();
+        if seconds > 0.0 { self.bytes_received as f64 / seconds } else { 0.0 }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Marks {
+    start: Option<Instant>,
+    connect_start: Option<Instant>,
+    dns: Option<Instant>,
+    connect: Option<Instant>,
+    first_byte: Option<Instant>,
+    tls: bool,
+    bytes_sent: Option<u64>,
+}
+
+/// Collects timestamps from the resolver, the connector and the request loop of one exchange.
+///
+/// Only the first connection is recorded, so a Digest retry or redirect to another host
+/// does not overwrite the phases of the original connection.
+#[derive(Debug, Default)]
+pub(crate) struct TimingRecorder(Mutex<Marks>);
+
+impl TimingRecorder {
+    fn update(&self, f: impl FnOnce(&mut Marks)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+
+    /// Marks the request as sent; later calls only update the body size.
+    pub(crate) fn start(&self, tls: bool, bytes_sent: Option<u64>) {
+        self.update(|marks| {
+            marks.start.get_or_insert_with(Instant::now);
+            marks.tls = tls;
+            marks.bytes_sent = bytes_sent;
+        });
+    }
+
+    pub(crate) fn first_byte(&self) {
+        self.update(|marks| marks.first_byte = Some(Instant::now()));
+    }
+
+    fn connecting(&self) {
+        self.update(|marks| {
+            marks.connect_start.get_or_insert_with(Instant::now);
+        });
+    }
+
+    fn resolved(&self) {
+        self.update(|marks| {
+            marks.dns.get_or_insert_with(Instant::now);
+        });
+    }
+
+    fn connected(&self) {
+        self.update(|marks| {
+            marks.connect.get_or_insert_with(Instant::now);
+        });
+    }
+
+    /// Produces the timings of an exchange whose body has just been read.
+    pub(crate) fn finish(&self, bytes_received: u64) -> Timings {
+        let now = Instant::now();
+        let marks = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let start = marks.start.unwrap_or(now);
+        let offset = |mark: Option<Instant>| mark.map(|instant| instant.saturating_duration_since(start));
+        Timings {
+            connect_start: offset(marks.connect_start),
+            // The resolver runs inside the connector, so only count lookups made for this connection.
+            dns: offset(marks.dns.filter(|_| marks.connect_start.is_some())),
+            connect: offset(marks.connect),
+            tls: marks.tls,
+            first_byte: offset(marks.first_byte).unwrap_or_default(),
+            total: now.saturating_duration_since(start),
+            bytes_sent: marks.bytes_sent,
+            bytes_received,
+        }
+    }
+}
+
+/// Resolves names for connections to `port`, applying address overrides and the address
+/// family, and records when the lookup finished.
+pub(crate) struct TimedResolver {
+    pub(crate) timing: Arc<TimingRecorder>,
+    pub(crate) options: ResolveOptions,
+    pub(crate) port: u16,
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let timing = self.timing.clone();
+        let options = self.options.clone();
+        let port = self.port;
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = options.lookup(name.as_str(), port).await?;
+            timing.resolved();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// A connector layer recording when a connection was requested and when it was ready.
+#[derive(Clone)]
+pub(crate) struct TimedConnectorLayer(pub(crate) Arc<TimingRecorder>);
+
+impl<S> Layer<S> for TimedConnectorLayer {
+    type Service = TimedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimedConnector { inner, timing: self.0.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct TimedConnector<S> {
+    inner: S,
+    timing: Arc<TimingRecorder>,
+}
+
+impl<S, R> Service<R> for TimedConnector<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.timing.connecting();
+        let timing = self.timing.clone();
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let connection = connecting.await?;
+            timing.connected();
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_cover_the_exchange() {
+        let ms = Duration::from_millis;
+        let timings = Timings {
+            connect_start: Some(ms(0)),
+            dns: Some(ms(5)),
+            connect: Some(ms(20)),
+            tls: true,
+            first_byte: ms(50),
+            total: ms(60),
+            ..Default::default()
+        };
+        assert_eq!(
+            timings.phases(),
+            vec![
+                ("DNS lookup", ms(0), ms(5)),
+                ("TCP + TLS handshake", ms(5), ms(20)),
+                ("Server processing", ms(20), ms(50)),
+                ("Content transfer", ms(50), ms(60)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_phases_on_reused_connection() {
+        let timings = Timings { first_byte: Duration::from_millis(3), total: Duration::from_millis(4), ..Default::default() };
+        let labels: Vec<_> = timings.phases().into_iter().map(|(label, ..)| label).collect();
+        assert_eq!(labels, ["Server processing", "Content transfer"]);
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::{anyhow, Result};
+use reqwest::{ClientBuilder, Response};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, OtherError, RootCertStore, SignatureScheme};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use crate::certificate::{spki_sha256, CertificateSummary};
+
+/// The lowest TLS version a client accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls1_0,
+    Tls1_1,
+    Tls1_2,
+    Tls1_3,
+}
+
+impl FromStr for TlsVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim_start_matches("TLSv").trim_start_matches("tls") {
+            "1.0" | "1" => Ok(TlsVersion::Tls1_0),
+            "1.1" => Ok(TlsVersion::Tls1_1),
+            "1.2" => Ok(TlsVersion::Tls1_2),
+            "1.3" => Ok(TlsVersion::Tls1_3),
+            _ => Err(anyhow!("Invalid TLS version: '{}'. Use 1.0, 1.1, 1.2 or 1.3.", s)),
+        }
+    }
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TlsVersion::Tls1_0 => "1.0",
+            TlsVersion::Tls1_1 => "1.1",
+            TlsVersion::Tls1_2 => "1.2",
+            TlsVersion::Tls1_3 => "1.3",
+        })
+    }
+}
+
+/// Trust and identity settings for TLS connections.
+///
+/// Connections use the platform's native TLS stack and trust store. Requiring
+/// TLS 1.3 or pinning switches to rustls, which trusts the system CA bundle plus
+/// `ca_cert` and checks pins during the handshake, before anything is sent.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    /// PEM bundle of additional trusted CA certificates.
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate chain for mutual TLS.
+    pub client_cert: Option<PathBuf>,
+    /// PEM private key for `client_cert`. Defaults to the certificate file itself.
+    pub client_key: Option<PathBuf>,
+    /// Skip certificate and host name verification.
+    pub insecure: bool,
+    pub min_version: Option<TlsVersion>,
+    /// Accepted base64 SHA-256 digests of the server's public key (SubjectPublicKeyInfo).
+    pub pins: Vec<String>,
+}
+
+impl TlsOptions {
+    fn uses_rustls(&self) -> bool {
+        self.min_version == Some(TlsVersion::Tls1_3) || !self.pins.is_empty()
+    }
+
+    /// Fails unless the certificate's public key matches one of the configured pins.
+    pub fn check_pin(&self, certificate_der: &[u8]) -> Result<()> {
+        if self.pins.is_empty() {
+            return Ok(());
+        }
+        self.check_pin_hash(&spki_sha256(certificate_der)?)
+    }
+
+    /// Like [`TlsOptions::check_pin`], for an already computed public key hash.
+    pub fn check_pin_hash(&self, actual: &str) -> Result<()> {
+        if self.pins.is_empty() || self.pins.iter().any(|pin| pin.trim_start_matches("sha256//") == actual) {
+            Ok(())
+        } else {
+            Err(anyhow!("Certificate pin mismatch: the server's public key hash is sha256//{}", actual))
+        }
+    }
+}
+
+/// What was negotiated during a TLS handshake, as far as the TLS stack reports it.
+/// The native stack does not report the protocol version or cipher.
+#[derive(Debug, Clone, Default)]
+pub struct TlsDetails {
+    pub protocol: Option<String>,
+    pub cipher: Option<String>,
+    pub peer_certificate: Option<CertificateSummary>,
+}
+
+impl TlsDetails {
+    /// The details of the connection `response` arrived on, or `None` if it was not TLS.
+    pub(crate) fn of_response(response: &Response) -> Option<TlsDetails> {
+        let info = response.extensions().get::<reqwest::tls::TlsInfo>()?;
+        Some(TlsDetails {
+            peer_certificate: info.peer_certificate().and_then(|der| CertificateSummary::from_der(der).ok()),
+            ..Default::default()
+        })
+    }
+
+    fn of_rustls(connection: &rustls::ClientConnection) -> Result<TlsDetails> {
+        let peer_certificate = match connection.peer_certificates().and_then(|chain| chain.first()) {
+            Some(certificate) => Some(CertificateSummary::from_der(certificate)?),
+            None => None,
+        };
+        Ok(TlsDetails {
+            protocol: connection.protocol_version().and_then(|v| v.as_str()).map(|v| v.replace('_', ".")),
+            cipher: connection.negotiated_cipher_suite().and_then(|s| s.suite().as_str()).map(str::to_string),
+            peer_certificate,
+        })
+    }
+}
+
+/// Applies `options` to a `reqwest` client offering the `alpn` protocols, e.g. `h2`.
+///
+/// The native TLS stack gets its ALPN list from the client's HTTP version preference;
+/// a preconfigured rustls config has to carry its own.
+pub(crate) fn configure_client(mut builder: ClientBuilder, options: &TlsOptions, alpn: &[&str]) -> Result<ClientBuilder> {
+    // Keeps the server's certificate with each response, to check pins and report it.
+    builder = builder.tls_info(true);
+    if options.uses_rustls() {
+        let mut config = rustls_config(options)?;
+        config.alpn_protocols = alpn.iter().map(|protocol| protocol.as_bytes().to_vec()).collect();
+        return Ok(builder.use_preconfigured_tls(config));
+    }
+
+    if let Some(path) = &options.ca_cert {
+        for certificate in reqwest::Certificate::from_pem_bundle(&read(path)?)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if let Some((certificate, key)) = read_identity_pem(options)? {
+        let identity = reqwest::Identity::from_pkcs8_pem(&certificate, &key)
+            .map_err(|e| anyhow!("Invalid client certificate or key (the key must be PKCS#8 PEM): {}", e))?;
+        builder = builder.identity(identity);
+    }
+    if options.insecure {
+        builder = builder.danger_accept_invalid_certs(true).danger_accept_invalid_hostnames(true);
+    }
+    if let Some(version) = options.min_version {
+        builder = builder.min_tls_version(match version {
+            TlsVersion::Tls1_0 => reqwest::tls::Version::TLS_1_0,
+            TlsVersion::Tls1_1 => reqwest::tls::Version::TLS_1_1,
+            TlsVersion::Tls1_2 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => reqwest::tls::Version::TLS_1_3,
+        });
+    }
+    Ok(builder)
+}
+
+/// A bidirectional byte stream, plain or encrypted.
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+/// Performs a TLS handshake with `host` over `stream`, enforcing certificate pins, and
+/// returns the encrypted stream with what was negotiated.
+pub async fn connect(stream: TcpStream, host: &str, options: &TlsOptions) -> Result<(Box<dyn Stream>, TlsDetails)> {
+    if options.uses_rustls() {
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(rustls_config(options)?));
+        let stream = connector.connect(server_name(host)?, stream).await?;
+        let details = TlsDetails::of_rustls(stream.get_ref().1)?;
+        return Ok((Box::new(stream), details));
+    }
+
+    let mut builder = native_tls::TlsConnector::builder();
+    if let Some(path) = &options.ca_cert {
+        for certificate in read_certificates(path)? {
+            builder.add_root_certificate(native_tls::Certificate::from_der(&certificate)?);
+        }
+    }
+    if let Some((certificate, key)) = read_identity_pem(options)? {
+        let identity = native_tls::Identity::from_pkcs8(&certificate, &key)
+            .map_err(|e| anyhow!("Invalid client certificate or key (the key must be PKCS#8 PEM): {}", e))?;
+        builder.identity(identity);
+    }
+    builder.danger_accept_invalid_certs(options.insecure);
+    builder.danger_accept_invalid_hostnames(options.insecure);
+    builder.min_protocol_version(options.min_version.map(|version| match version {
+        TlsVersion::Tls1_0 => native_tls::Protocol::Tlsv10,
+        TlsVersion::Tls1_1 => native_tls::Protocol::Tlsv11,
+        _ => native_tls::Protocol::Tlsv12,
+    }));
+
+    let connector = tokio_native_tls::TlsConnector::from(builder.build()?);
+    let stream = connector.connect(host.trim_start_matches('[').trim_end_matches(']'), stream).await?;
+    let peer_certificate = match stream.get_ref().peer_certificate()? {
+        Some(certificate) => Some(CertificateSummary::from_der(&certificate.to_der()?)?),
+        None => None,
+    };
+    Ok((Box::new(stream), TlsDetails { peer_certificate, ..Default::default() }))
+}
+
+/// Builds a rustls configuration that verifies the server as `options` say, pins included.
+fn rustls_config(options: &TlsOptions) -> Result<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let versions: &[&rustls::SupportedProtocolVersion] = if options.min_version == Some(TlsVersion::Tls1_3) {
+        &[&rustls::version::TLS13]
+    } else {
+        rustls::ALL_VERSIONS
+    };
+    let builder = ClientConfig::builder_with_provider(provider.clone()).with_protocol_versions(versions)?;
+
+    let verifier: Arc<dyn ServerCertVerifier> = if !options.insecure {
+        WebPkiServerVerifier::builder_with_provider(Arc::new(root_store(options)?), provider).build()?
+    } else {
+        Arc::new(AcceptAnyCertificate(provider))
+    };
+    let verifier = if !options.pins.is_empty() {
+        Arc::new(PinnedCertificate { inner: verifier, options: TlsOptions { pins: options.pins.clone(), ..Default::default() } })
+    } else {
+        verifier
+    };
+    let builder = builder.dangerous().with_custom_certificate_verifier(verifier);
+
+    let config = match read_identity_pem(options)? {
+        Some((certificate, key)) => {
+            let chain = CertificateDer::pem_slice_iter(&certificate).collect::<Result<Vec<_>, _>>()?;
+            let key = PrivateKeyDer::from_pem_slice(&key)
+                .map_err(|e| anyhow!("Invalid client key: {}", e))?;
+            builder.with_client_auth_cert(chain, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(config)
+}
+
+/// The system CA bundle plus any certificates from `--cacert`.
+fn root_store(options: &TlsOptions) -> Result<RootCertStore> {
+    let mut store = RootCertStore::empty();
+    if let Some(bundle) = openssl_probe::probe().cert_file {
+        store.add_parsable_certificates(read_certificates(&bundle)?);
+    }
+    if let Some(path) = &options.ca_cert {
+        store.add_parsable_certificates(read_certificates(path)?);
+    }
+    if store.is_empty() {
+        return Err(anyhow!("No trusted CA certificates found. Pass a CA bundle with --cacert."));
+    }
+    Ok(store)
+}
+
+fn server_name(host: &str) -> Result<ServerName<'static>> {
+    ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']').to_string())
+        .map_err(|_| anyhow!("Invalid TLS server name: '{}'", host))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| anyhow!("Failed to read '{}': {}", path.display(), e))
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let certificates = CertificateDer::pem_slice_iter(&read(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Invalid certificate file '{}': {}", path.display(), e))?;
+    if certificates.is_empty() {
+        return Err(anyhow!("No PEM certificates found in '{}'", path.display()));
+    }
+    Ok(certificates)
+}
+
+/// Reads the client certificate and key as PEM, if a client certificate is configured.
+fn read_identity_pem(options: &TlsOptions) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let Some(certificate_path) = &options.client_cert else { return Ok(None) };
+    let certificate = read(certificate_path)?;
+    let key = match &options.client_key {
+        Some(key_path) => read(key_path)?,
+        None => certificate.clone(),
+    };
+    Ok(Some((certificate, key)))
+}
+
+/// Accepts any server certificate while still checking handshake signatures.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Verifies the server certificate as `inner` does, then requires its public key to match a pin.
+#[derive(Debug)]
+struct PinnedCertificate {
+    inner: Arc<dyn ServerCertVerifier>,
+    options: TlsOptions,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)?;
+        self.options.check_pin(end_entity).map_err(|e| {
+            rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(Arc::new(std::io::Error::other(e.to_string())))))
+        })?;
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tls_version() {
+        assert_eq!("1.2".parse::<TlsVersion>().unwrap(), TlsVersion::Tls1_2);
+        assert_eq!("TLSv1.3".parse::<TlsVersion>().unwrap(), TlsVersion::Tls1_3);
+        assert!("1.4".parse::<TlsVersion>().is_err());
+        assert!(TlsVersion::Tls1_3 > TlsVersion::Tls1_2);
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use socket2::{Domain, Socket, Type};
+use std::fmt::Display;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::{TcpSocket, TcpStream, UdpSocket};
+use crate::proxy::{Proxy, ProxyKind};
+use crate::resolve::{split_host_port, ResolveOptions};
+use crate::retry::{retry, RetryPolicy};
+use crate::tls::TlsOptions;
+
+/// Connection settings shared by the HTTP, TCP, UDP and WebSocket clients.
+#[derive(Debug, Clone, Default)]
+pub struct TransportOptions {
+    /// Limit for a whole HTTP exchange, or for waiting on a reply over TCP, UDP and WebSocket.
+    pub timeout: Option<Duration>,
+    /// Limit for establishing a connection.
+    pub connect_timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+    pub tls: TlsOptions,
+    pub resolve: ResolveOptions,
+    /// Proxy for HTTP requests, and to tunnel TCP and WebSocket connections through.
+    pub proxy: Option<Proxy>,
+    /// Send HTTP requests over this Unix domain socket instead of TCP.
+    pub unix_socket: Option<PathBuf>,
+}
+
+/// Awaits `future`, failing with "`what` timed out" once `limit` has passed.
+pub async fn with_timeout<T>(limit: Option<Duration>, what: impl Display, future: impl Future<Output = Result<T>>) -> Result<T> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, future)
+            .await
+            .map_err(|_| anyhow!("{} timed out after {:.1}s", what, limit.as_secs_f64()))?,
+        None => future.await,
+    }
+}
+
+/// Opens a TCP connection to `host:port`, honoring address overrides, source binding,
+/// the proxy and the connect timeout, and retrying failed attempts.
+pub async fn connect_tcp(address: &str, options: &TransportOptions) -> Result<TcpStream> {
+    let (host, port) = split_host_port(address)?;
+    retry(&options.retry, || {
+        with_timeout(options.connect_timeout, format!("Connecting to {}", address), async {
+            let Some(proxy) = &options.proxy else {
+                return connect_direct(address, host, port, &options.resolve).await;
+            };
+            let mut stream = connect_direct(&proxy.address(), &proxy.host, proxy.port, &options.resolve).await?;
+            // Only a plain SOCKS5 proxy needs the target resolved here; the others take the name.
+            let (target, port) = match proxy.kind {
+                ProxyKind::Socks5 => {
+                    let addr = options.resolve.lookup(host, port).await?[0];
+                    (addr.ip().to_string(), addr.port())
+                }
+                ProxyKind::Http | ProxyKind::Socks5h => options.resolve.route(host, port),
+            };
+            proxy.tunnel(&mut stream, &target, port).await?;
+            Ok(stream)
+        })
+    })
+    .await
+}
+
+async fn connect_direct(address: &str, host: &str, port: u16, options: &ResolveOptions) -> Result<TcpStream> {
+    let mut last_error = None;
+    for addr in options.lookup(host, port).await? {
+        match connect_from(addr, options).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let error = last_error.map_or_else(|| "no addresses".to_string(), |e| e.to_string());
+    Err(anyhow!("Failed to connect to {}: {}", address, error))
+}
+
+async fn connect_from(addr: SocketAddr, options: &ResolveOptions) -> io::Result<TcpStream> {
+    let socket = bound_socket(addr, Type::STREAM, options)?;
+    TcpSocket::from_std_stream(socket.into()).connect(addr).await
+}
+
+/// Opens a UDP socket connected to `host:port`, bound to the configured source address or
+/// interface, or else to any local address of the peer's family.
+pub async fn connect_udp(address: &str, options: &TransportOptions) -> Result<UdpSocket> {
+    if let Some(proxy) = &options.proxy {
+        return Err(anyhow!("UDP cannot be sent through the proxy {}", proxy));
+    }
+    let (host, port) = split_host_port(address)?;
+    let peer = options.resolve.lookup(host, port).await?[0];
+    let socket = UdpSocket::from_std(bound_socket(peer, Type::DGRAM, &options.resolve)?.into())?;
+    socket.connect(peer).await.map_err(|e| anyhow!("Failed to connect to {}: {}", address, e))?;
+    Ok(socket)
+}
+
+/// Creates a non-blocking socket for talking to `peer`, bound to the configured interface
+/// and source address. UDP sockets are always bound, to a wildcard address by default.
+fn bound_socket(peer: SocketAddr, kind: Type, options: &ResolveOptions) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(peer), kind, None)?;
+    if let Some(interface) = &options.interface {
+        bind_device(&socket, interface)?;
+    }
+    let local = options.local_address.or((kind == Type::DGRAM).then_some(match peer {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    }));
+    if let Some(local) = local {
+        socket.bind(&SocketAddr::new(local, 0).into())?;
+    }
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Binds a socket to a network interface by name (`SO_BINDTODEVICE`).
+#[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+fn bind_device(socket: &Socket, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(any(target_os = "android", target_os = "fuchsia", target_os = "linux")))]
+fn bind_device(_socket: &Socket, _interface: &str) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "binding to an interface by name is only supported on Linux"))
+}
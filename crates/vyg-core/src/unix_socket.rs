@@ -0,0 +1,102 @@
+use anyhow::{anyhow, bail, Result};
+use hyper::header::{HOST, SET_COOKIE};
+use hyper_util::rt::TokioIo;
+use percent_encoding::percent_decode_str;
+use std::path::{Path, PathBuf};
+use crate::cookie_jar::SharedCookieJar;
+use crate::http_client;
+use crate::retry::retry;
+use crate::transport::{with_timeout, TransportOptions};
+
+#[cfg(unix)]
+pub use tokio::net::UnixStream;
+
+/// The host requests over a Unix socket are addressed to, as curl does.
+const SOCKET_HOST: &str = "localhost";
+
+/// Splits an `http+unix://` URL, whose host is the percent-encoded socket path
+/// (e.g. `http+unix://%2Fvar%2Frun%2Fdocker.sock/containers/json`), into the socket path
+/// and the `http://localhost` URL to request over it. Returns `None` for other URLs.
+pub fn split_url(url: &str) -> Option<Result<(PathBuf, String)>> {
+    let rest = url.strip_prefix("http+unix://")?;
+    let (socket, target) = rest.split_at(rest.find(['/', '?', '#']).unwrap_or(rest.len()));
+    let socket = percent_decode_str(socket).decode_utf8_lossy();
+    if socket.is_empty() {
+        return Some(Err(anyhow!("Missing socket path in '{}'. Encode it as the host, e.g. http+unix://%2Fvar%2Frun%2Fdocker.sock/", url)));
+    }
+    let target = if target.starts_with('/') { target.to_string() } else { format!("/{}", target) };
+    Some(Ok((PathBuf::from(socket.as_ref()), format!("http://{}{}", SOCKET_HOST, target))))
+}
+
+/// Connects to the Unix socket at `path`, honoring the connect timeout and retrying failed attempts.
+#[cfg(unix)]
+pub async fn connect(path: &Path, options: &TransportOptions) -> Result<UnixStream> {
+    retry(&options.retry, || {
+        with_timeout(options.connect_timeout, format!("Connecting to {}", path.display()), async {
+            UnixStream::connect(path).await.map_err(|e| anyhow!("Failed to connect to {}: {}", path.display(), e))
+        })
+    })
+    .await
+}
+
+/// Sends `request` over a fresh HTTP/1.1 connection to the Unix socket at `path`, with the
+/// client's default headers, storing response cookies in `cookie_jar` as the client would.
+/// The timeout covers the exchange up to the response headers.
+#[cfg(unix)]
+pub(crate) async fn send(path: &Path, mut request: reqwest::Request, cookie_jar: Option<&SharedCookieJar>, options: &TransportOptions) -> Result<reqwest::Response> {
+    if request.url().scheme() != "http" {
+        bail!("Cannot send a {} request over the Unix socket {}: only plain HTTP is supported", request.url().scheme(), path.display());
+    }
+    let url = request.url().clone();
+    http_client::add_default_headers(request.headers_mut());
+    let stream = connect(path, options).await?;
+    let response = with_timeout(options.timeout, "Request", async {
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(connection);
+
+        let mut request: hyper::Request<reqwest::Body> = request.try_into()?;
+        // Over a connection of our own, the request line carries only the path.
+        let origin_form = request.uri().path_and_query().map_or("/", |p| p.as_str()).parse()?;
+        *request.uri_mut() = origin_form;
+        request.headers_mut().entry(HOST).or_insert(SOCKET_HOST.parse()?);
+
+        let response = sender.send_request(request).await?;
+        Ok(reqwest::Response::from(response.map(reqwest::Body::wrap)))
+    })
+    .await?;
+    if let Some(jar) = cookie_jar {
+        let mut cookies = response.headers().get_all(SET_COOKIE).iter().peekable();
+        if cookies.peek().is_some() {
+            reqwest::cookie::CookieStore::set_cookies(jar, &mut cookies, &url);
+        }
+    }
+    Ok(response)
+}
+
+#[cfg(not(unix))]
+pub(crate) async fn send(path: &Path, _request: reqwest::Request, _cookie_jar: Option<&SharedCookieJar>, _options: &TransportOptions) -> Result<reqwest::Response> {
+    Err(unsupported(path))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn unsupported(path: &Path) -> anyhow::Error {
+    anyhow!("Cannot connect to {}: Unix domain sockets are not supported on this platform", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_url() {
+        let (socket, url) = split_url("http+unix://%2Fvar%2Frun%2Fdocker.sock/containers/json?all=1").unwrap().unwrap();
+        assert_eq!(socket, Path::new("/var/run/docker.sock"));
+        assert_eq!(url, "http://localhost/containers/json?all=1");
+
+        let (socket, url) = split_url("http+unix://.%2Fapi.sock").unwrap().unwrap();
+        assert_eq!((socket.as_path(), url.as_str()), (Path::new("./api.sock"), "http://localhost/"));
+
+        assert!(split_url("http+unix:///containers/json").unwrap().is_err());
+        assert!(split_url("http://localhost/").is_none());
+    }
+}
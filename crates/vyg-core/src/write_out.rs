@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+use std::time::Duration;
+use crate::timing::Timings;
+
+/// Variables understood by `--write-out`, named after their curl counterparts.
+pub const VARIABLES: &[&str] = &[
+    "http_code",
+    "response_code",
+    "http_version",
+    "url_effective",
+    "content_type",
+    "remote_ip",
+    "remote_port",
+    "time_namelookup",
+    "time_connect",
+    "time_appconnect",
+    "time_starttransfer",
+    "time_total",
+    "size_download",
+    "size_upload",
+    "speed_download",
+];
+
+/// The facts about a finished exchange a template can refer to.
+#[derive(Debug, Clone)]
+pub struct Transfer<'a> {
+    /// The protocol, e.g. `HTTP/2`.
+    pub version: &'a str,
+    /// The status line, e.g. `200 OK`.
+    pub status: &'a str,
+    pub url: &'a str,
+    pub content_type: Option<&'a str>,
+    pub remote_addr: Option<SocketAddr>,
+    pub timings: &'a Timings,
+}
+
+/// Checks a template for unknown variables and unterminated `%{`.
+pub fn validate(template: &str) -> Result<()> {
+    let transfer = Transfer { version: "", status: "", url: "", content_type: None, remote_addr: None, timings: &Timings::default() };
+    render(template, &transfer).map(|_| ())
+}
+
+/// Expands `%{variable}` references and `\n`, `\r`, `\t` and `\\` escapes in `template`.
+///
+/// Times are in seconds and, as in curl, measured from the start of the exchange:
+/// `time_connect` is when the connection was ready, so for HTTPS it equals `time_appconnect`.
+pub fn render(template: &str, transfer: &Transfer) -> Result<String> {
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(anyhow!("Unterminated variable in --write-out: '%{{{}'", name)),
+                    }
+                }
+                output.push_str(&variable(&name, transfer)?);
+            }
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                output.push('%');
+            }
+            '\\' => match chars.next() {
+                Some('n') => output.push('\n'),
+                Some('r') => output.push('\r'),
+                Some('t') => output.push('\t'),
+                Some(other) => output.push(other),
+                None => output.push('\\'),
+            },
+            c => output.push(c),
+        }
+    }
+    Ok(output)
+}
+
+fn variable(name: &str, transfer: &Transfer) -> Result<String> {
+    let timings = transfer.timings;
+    let seconds = |duration: Option<Duration>| format!("{:.6}", duration.unwrap_or_default().as_secs_f64());
+    Ok(match name {
+        "http_code" | "response_code" => transfer.status.split_whitespace().next().unwrap_or("000").to_string(),
+        "http_version" => transfer.version.trim_start_matches("HTTP/").to_string(),
+        "url_effective" => transfer.url.to_string(),
+        "content_type" => transfer.content_type.unwrap_or_default().to_string(),
+        "remote_ip" => transfer.remote_addr.map(|addr| addr.ip().to_string()).unwrap_or_default(),
+        "remote_port" => transfer.remote_addr.map(|addr| addr.port().to_string()).unwrap_or_default(),
+        "time_namelookup" => seconds(timings.dns),
+        "time_connect" => seconds(timings.connect),
+        "time_appconnect" => seconds(timings.connect.filter(|_| timings.tls)),
+        "time_starttransfer" => seconds(Some(timings.first_byte)),
+        "time_total" => seconds(Some(timings.total)),
+        "size_download" => timings.bytes_received.to_string(),
+        "size_upload" => timings.bytes_sent.unwrap_or(0).to_string(),
+        "speed_download" => format!("{:.0}", timings.download_speed()),
+        _ => return Err(anyhow!("Unknown --write-out variable: '{}'. Use one of: {}", name, VARIABLES.join(", "))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables_and_escapes() {
+        let timings = Timings {
+            dns: Some(Duration::from_millis(2)),
+            connect: Some(Duration::from_millis(12)),
+            first_byte: Duration::from_millis(40),
+            total: Duration::from_millis(500),
+            bytes_received: 1000,
+            ..Default::default()
+        };
+        let transfer = Transfer {
+            version: "HTTP/2",
+            status: "201 Created",
+            url: "http://localhost/items",
+            content_type: None,
+            remote_addr: Some("127.0.0.1:8080".parse().unwrap()),
+            timings: &timings,
+        };
+        let output = render(
+            r"%{http_code} %{http_version} %{remote_ip}:%{remote_port} %{time_connect} %{time_appconnect}\n%{size_download} %{speed_download} 100%%",
+            &transfer,
+        )
+        .unwrap();
+        assert_eq!(output, "201 2 127.0.0.1:8080 0.012000 0.000000\n1000 2000 100%");
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_and_unterminated() {
+        assert!(validate("%{time_total}\\n").is_ok());
+        assert!(validate("%{time_totl}").unwrap_err().to_string().contains("time_total"));
+        assert!(validate("%{time_total").is_err());
+    }
+}
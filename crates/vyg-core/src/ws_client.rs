@@ -1,25 +1,52 @@
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tokio_tungstenite::{client_async, tungstenite::protocol::Message, WebSocketStream};
 use futures_util::{StreamExt, SinkExt};
 use url::Url;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use vyg_display::logger;
+use crate::filter::Filter;
+use crate::tls::{self, Stream, TlsDetails};
+use crate::transport::{connect_tcp, with_timeout, TransportOptions};
 
-pub async fn connect_ws(url: &str, message: Option<String>) -> Result<()> {
-    let url = Url::parse(url)?;
-    let (ws_stream, _) = connect_async(url).await?;
+/// Connects to a WebSocket server, optionally sends `message` and prints everything received.
+/// The timeout covers the handshake and the wait for each incoming message. With a `filter`,
+/// only its results are printed for each text message, one per line, and the result is
+/// `false` if it failed on any of them. For `wss`, `on_tls` is shown what the TLS handshake
+/// negotiated before anything is printed.
+pub async fn connect_ws(
+    url: &str,
+    message: Option<String>,
+    filter: Option<&Filter>,
+    options: &TransportOptions,
+    on_tls: impl FnOnce(&TlsDetails),
+) -> Result<bool> {
+    let (ws_stream, tls) = handshake(url, options).await?;
+    if let Some(tls) = &tls {
+        on_tls(tls);
+    }
     println!("WebSocket handshake has been successfully completed");
 
     let (mut write, mut read) = ws_stream.split();
+    let mut filtered = true;
 
     if let Some(msg) = message {
         write.send(Message::Text(msg)).await?;
     }
 
-    while let Some(msg) = read.next().await {
+    while let Some(msg) = with_timeout(options.timeout, "Waiting for a WebSocket message", async {
+        Ok(read.next().await)
+    })
+    .await?
+    {
         let msg = msg?;
         match msg {
-            Message::Text(t) => {
-                println!("Received: {}", t);
-            }
+            Message::Text(t) => match filter.map(|filter| filter.render(t.as_bytes())) {
+                Some(Ok(results)) => results.iter().for_each(|result| println!("{}", result)),
+                Some(Err(e)) => {
+                    logger::error(&format!("Filter failed: {:#}", e));
+                    filtered = false;
+                }
+                None => println!("Received: {}", t),
+            },
             Message::Binary(b) => {
                 println!("Received binary: {:?}", b);
             }
@@ -33,5 +60,45 @@ pub async fn connect_ws(url: &str, message: Option<String>) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(filtered)
+}
+
+/// Connects to a WebSocket server, sends `message` if given and returns the first `replies` text
+/// messages received, or fewer if the server closes the connection first.
+pub async fn request_ws(url: &str, message: Option<String>, replies: usize, options: &TransportOptions) -> Result<Vec<String>> {
+    let (mut ws_stream, _) = handshake(url, options).await?;
+    if let Some(msg) = message {
+        ws_stream.send(Message::Text(msg)).await?;
+    }
+    let mut received = Vec::new();
+    while received.len() < replies {
+        let next = with_timeout(options.timeout, "Waiting for a WebSocket message", async { Ok(ws_stream.next().await) }).await?;
+        match next.transpose()? {
+            Some(Message::Text(text)) => received.push(text),
+            Some(Message::Binary(bytes)) => received.push(String::from_utf8_lossy(&bytes).into_owned()),
+            Some(Message::Close(_)) | None => break,
+            Some(_) => {}
+        }
+    }
+    // Best effort: the replies are in, whether or not the server acknowledges the close.
+    let _ = ws_stream.close(None).await;
+    Ok(received)
+}
+
+async fn handshake(url: &str, options: &TransportOptions) -> Result<(WebSocketStream<Box<dyn Stream>>, Option<TlsDetails>)> {
+    let url = Url::parse(url)?;
+    let host = url.host_str().ok_or_else(|| anyhow!("WebSocket URL has no host: {}", url))?;
+    let port = url.port_or_known_default().ok_or_else(|| anyhow!("WebSocket URL has no port: {}", url))?;
+    let stream = connect_tcp(&format!("{}:{}", host, port), options).await?;
+    with_timeout(options.timeout, "WebSocket handshake", async {
+        let (stream, tls): (Box<dyn Stream>, _) = if url.scheme() == "wss" {
+            let (stream, details) = tls::connect(stream, host, &options.tls).await?;
+            (stream, Some(details))
+        } else {
+            (Box::new(stream), None)
+        };
+        let (ws_stream, _) = client_async(url.as_str(), stream).await?;
+        Ok((ws_stream, tls))
+    })
+    .await
 }
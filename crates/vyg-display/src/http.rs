@@ -0,0 +1,57 @@
+use colored::Colorize;
+
+/// Renders an HTTP message head as it goes on the wire: the start line,
+/// one `Name: value` line per header and the blank line that ends the head.
+pub fn render_head(start_line: &str, headers: &[(String, String)]) -> String {
+    let mut head = format!("{}\r\n", start_line);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+/// Prints an HTTP message head with the start line and header names highlighted.
+/// A status code in the start line is colored by its class.
+pub fn print_head(start_line: &str, headers: &[(String, String)]) {
+    println!("{}", highlight_start_line(start_line));
+    for (name, value) in headers {
+        println!("{}: {}", name.cyan(), value);
+    }
+    println!();
+}
+
+fn highlight_start_line(line: &str) -> String {
+    let parts: Vec<&str> = line.splitn(3, ' ').collect();
+    match parts.as_slice() {
+        // A status line: `HTTP/1.1 404 Not Found`.
+        [version, code, rest @ ..] if version.starts_with("HTTP/") => {
+            let status = [*code].iter().chain(rest).copied().collect::<Vec<_>>().join(" ");
+            let status = match code.chars().next() {
+                Some('2') => status.green(),
+                Some('3') => status.cyan(),
+                Some('4') => status.yellow(),
+                Some('5') => status.red(),
+                _ => status.normal(),
+            };
+            format!("{} {}", version.blue(), status.bold())
+        }
+        // A request line: `GET /path HTTP/1.1`.
+        [method, target, version] => format!("{} {} {}", method.green().bold(), target, version.blue()),
+        _ => line.bold().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_head() {
+        let headers = [("host".to_string(), "example.com".to_string()), ("accept".to_string(), "*/*".to_string())];
+        assert_eq!(
+            render_head("GET /items?page=2 HTTP/1.1", &headers),
+            "GET /items?page=2 HTTP/1.1\r\nhost: example.com\r\naccept: */*\r\n\r\n"
+        );
+    }
+}
@@ -1,3 +1,6 @@
+pub mod http;
 pub mod json;
 pub mod table;
 pub mod logger;
+pub mod progress;
+pub mod waterfall;
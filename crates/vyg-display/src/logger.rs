@@ -13,3 +13,8 @@ pub fn warn(message: &str) {
 pub fn error(message: &str) {
     eprintln!("{} {}", "ERROR:".red().bold(), message);
 }
+
+pub fn success(message: &str) {
+    println!("{} {}", "OK:".green().bold(), message);
+    let _ = std::io::stdout().flush();
+}
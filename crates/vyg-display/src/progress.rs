@@ -0,0 +1,79 @@
+use indicatif::{ProgressBar, ProgressStyle};
+
+const SIZED_TEMPLATE: &str =
+    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, ETA {eta})";
+const UNSIZED_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] {bytes} ({binary_bytes_per_sec})";
+
+/// A byte-transfer progress bar drawn on stderr, so stdout stays clean for data.
+///
+/// Nothing is drawn when stderr is not a terminal.
+pub struct TransferProgress {
+    bar: ProgressBar,
+}
+
+impl TransferProgress {
+    /// Creates a progress bar. `total` is the full size if known, `position` the
+    /// number of bytes already present (e.g. when resuming a download).
+    pub fn new(total: Option<u64>, position: u64) -> Self {
+        let (bar, template) = match total {
+            Some(total) => (ProgressBar::new(total), SIZED_TEMPLATE),
+            None => (ProgressBar::no_length(), UNSIZED_TEMPLATE),
+        };
+        bar.set_style(
+            ProgressStyle::with_template(template)
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        bar.set_position(position);
+        // Rate and ETA should reflect this transfer only, not bytes resumed from disk.
+        bar.reset_eta();
+        TransferProgress { bar }
+    }
+
+    pub fn inc(&self, bytes: u64) {
+        self.bar.inc(bytes);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish();
+    }
+
+    /// Stops the bar at its current position, e.g. after a failed transfer.
+    pub fn abandon(&self) {
+        self.bar.abandon();
+    }
+}
+
+const COUNTED_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg} ({per_sec})";
+const UNCOUNTED_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] {pos} {msg} ({per_sec})";
+
+/// A progress bar counting finished items (e.g. requests) on stderr.
+pub struct CountProgress {
+    bar: ProgressBar,
+}
+
+impl CountProgress {
+    /// Creates a progress bar for `total` items if known, labelled with `unit`.
+    pub fn new(total: Option<u64>, unit: &str) -> Self {
+        let (bar, template) = match total {
+            Some(total) => (ProgressBar::new(total), COUNTED_TEMPLATE),
+            None => (ProgressBar::no_length(), UNCOUNTED_TEMPLATE),
+        };
+        bar.set_style(
+            ProgressStyle::with_template(template)
+                .expect("valid progress template")
+                .progress_chars("=> "),
+        );
+        bar.set_message(unit.to_string());
+        CountProgress { bar }
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Removes the bar, so a report can be printed in its place.
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
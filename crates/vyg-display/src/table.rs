@@ -1,16 +1,31 @@
-use cli_table::{print_stdout, Table, TableStruct};
+use cli_table::{print_stderr, print_stdout, Cell, Style, Table, TableStruct};
 use anyhow::Result;
 use std::io::Write;
 
 pub fn print_key_value_table(data: &[(String, String)]) -> Result<()> {
+    print_stdout(key_value_table(data))?;
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+/// Prints `data` like `print_key_value_table`, but to stderr, away from the response.
+pub fn eprint_key_value_table(data: &[(String, String)]) -> Result<()> {
+    print_stderr(key_value_table(data))?;
+    Ok(())
+}
+
+fn key_value_table(data: &[(String, String)]) -> TableStruct {
     let mut table_data = Vec::new();
     for (key, value) in data {
         table_data.push(vec![key.clone(), value.clone()]);
     }
+    table_data.table()
+}
 
-    let table: TableStruct = table_data.table();
-
-    print_stdout(table)?;
+/// Prints `rows` as a table with a bold header row of `titles`.
+pub fn print_table(titles: &[&str], rows: Vec<Vec<String>>) -> Result<()> {
+    let title: Vec<_> = titles.iter().map(|title| title.cell().bold(true)).collect();
+    print_stdout(rows.table().title(title))?;
     std::io::stdout().flush()?;
     Ok(())
 }
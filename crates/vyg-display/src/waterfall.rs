@@ -0,0 +1,113 @@
+use colored::Colorize;
+use std::time::Duration;
+
+const BAR_WIDTH: usize = 40;
+
+/// One step of a request, as an offset range from the start of the exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub label: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl Phase {
+    pub fn new(label: impl Into<String>, start: Duration, end: Duration) -> Self {
+        Phase { label: label.into(), start, end: end.max(start) }
+    }
+
+    fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Renders phases as rows of `label  [   ████      ]  12.3 ms`, scaled to the latest end.
+pub fn render_waterfall(phases: &[Phase]) -> Vec<String> {
+    let total = phases.iter().map(|phase| phase.end).max().unwrap_or_default();
+    let label_width = phases.iter().map(|phase| phase.label.len()).max().unwrap_or(0);
+
+    phases
+        .iter()
+        .map(|phase| {
+            let (offset, width) = bar_span(phase, total);
+            format!(
+                "{:<label_width$}  [{}{}{}]  {:>10}",
+                phase.label,
+                " ".repeat(offset),
+                "█".repeat(width),
+                " ".repeat(BAR_WIDTH - offset - width),
+                format_duration(phase.duration()),
+            )
+        })
+        .collect()
+}
+
+/// Prints the waterfall with colored bars, followed by the total time, to stderr.
+pub fn print_waterfall(phases: &[Phase]) {
+    let total = phases.iter().map(|phase| phase.end).max().unwrap_or_default();
+    let colors = ["cyan", "blue", "magenta", "yellow", "green"];
+    for (row, color) in render_waterfall(phases).into_iter().zip(colors.iter().cycle()) {
+        match (row.find('['), row.rfind(']')) {
+            (Some(open), Some(close)) => eprintln!(
+                "{}{}{}",
+                &row[..=open],
+                row[open + 1..close].color(*color),
+                &row[close..]
+            ),
+            _ => eprintln!("{}", row),
+        }
+    }
+    eprintln!("{}", format!("Total: {}", format_duration(total)).bold());
+}
+
+/// Formats a duration as milliseconds, or seconds once it exceeds one.
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_secs_f64() * 1000.0;
+    if millis >= 1000.0 {
+        format!("{:.2} s", millis / 1000.0)
+    } else {
+        format!("{:.1} ms", millis)
+    }
+}
+
+/// The column a phase's bar starts at and its width; every phase gets at least one cell.
+fn bar_span(phase: &Phase, total: Duration) -> (usize, usize) {
+    if total.is_zero() {
+        return (0, 1);
+    }
+    let scale = |offset: Duration| (offset.as_secs_f64() / total.as_secs_f64() * BAR_WIDTH as f64).round() as usize;
+    let offset = scale(phase.start).min(BAR_WIDTH - 1);
+    let width = scale(phase.end).saturating_sub(offset).clamp(1, BAR_WIDTH - offset);
+    (offset, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_waterfall_scales_to_total() {
+        let phases = [
+            Phase::new("DNS", Duration::ZERO, Duration::from_millis(10)),
+            Phase::new("Connect", Duration::from_millis(10), Duration::from_millis(20)),
+            Phase::new("Server", Duration::from_millis(20), Duration::from_millis(40)),
+        ];
+        let rows = render_waterfall(&phases);
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("DNS      [██████████ "));
+        assert!(rows[1].contains(&format!("[{}{}", " ".repeat(10), "█".repeat(10))));
+        assert!(rows[2].contains(&format!("{}]", "█".repeat(20))));
+        assert!(rows[2].ends_with("20.0 ms"));
+    }
+
+    #[test]
+    fn test_render_waterfall_short_phase_is_visible() {
+        let phases = [
+            Phase::new("a", Duration::ZERO, Duration::from_secs(10)),
+            Phase::new("b", Duration::from_secs(10), Duration::from_secs(10)),
+        ];
+        let rows = render_waterfall(&phases);
+        assert!(rows[1].contains(&format!("[{}█]", " ".repeat(BAR_WIDTH - 1))));
+        assert!(rows[0].ends_with("10.00 s"));
+    }
+}